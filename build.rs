@@ -0,0 +1,19 @@
+//! Embed the version of the latest migration as `SHOP_MIGRATION`, the services are
+//! ready once it is applied, see `infra::health`.
+
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    // versions are named like diesel does, `2026-10-19-130000_catalog_keys` is 20261019130000
+    let mut versions = std::fs::read_dir("migrations")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+            Some(name.split('_').next()?.replace('-', ""))
+        })
+        .collect::<Vec<_>>();
+    versions.sort();
+    let latest = versions.last().expect("no migrations");
+    println!("cargo:rustc-env=SHOP_MIGRATION={latest}");
+}
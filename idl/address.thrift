@@ -0,0 +1,18 @@
+namespace rs address.v1
+
+include "common.thrift"
+
+// An entry of the address book of a customer
+struct Address {
+    1: required i64 id;
+    2: required string customer_id;
+    3: required common.MailingAddress address;
+}
+
+service AddressService {
+    void ping();  // used for health check
+    list<Address> listAddresses(1: string customer_id);
+    Address createAddress(1: string customer_id, 2: common.MailingAddress address);
+    Address updateAddress(1: i64 id, 2: common.MailingAddress address);
+    Address deleteAddress(1: i64 id);
+}
\ No newline at end of file
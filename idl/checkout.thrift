@@ -25,22 +25,34 @@ struct Checkout {
     5: optional Payment payment;
     6: optional common.Money shipping_fee;
     7: optional string contact_email;
-    8: optional string receiver_name;
-    9: optional string receiver_country_code;
-    10: optional string receiver_address;
-    11: optional string receiver_postcode;
     12: optional string receiver_phone;
+    13: optional i64 shipping_id;  // `shipping` might be absent when it is not loaded
+    14: optional i64 payment_id;  // `payment` might be absent when it is not loaded
+    15: optional common.MailingAddress shipping_address;
+    16: optional common.MailingAddress billing_address;  // the shipping address if same as shipping
+    17: required bool billing_same_as_shipping;
 }
 
+// Fields of a checkout which can be cleared by `PutCheckout.clear`
+enum CheckoutField {
+    SHIPPING = 1,
+    PAYMENT = 2,
+    CONTACT_EMAIL = 3,
+    RECEIVER_PHONE = 4,
+    SHIPPING_ADDRESS = 5,
+    BILLING_ADDRESS = 6,
+}
+
+// A patch of a checkout, absent fields are unchanged
 struct PutCheckout {
     1: optional i64 shipping_id;
     2: optional i64 payment_id;
     3: optional string contact_email;
-    4: optional string receiver_name;
-    5: optional string receiver_country_code;
-    6: optional string receiver_address;
-    7: optional string receiver_postcode;
     8: optional string receiver_phone;
+    9: optional common.MailingAddress shipping_address;  // replaces the whole address
+    10: optional common.MailingAddress billing_address;  // replaces the whole address, implies not same as shipping
+    11: optional bool billing_same_as_shipping;
+    12: optional list<CheckoutField> clear;  // fields to remove, they must not be set at the same time
 }
 
 service CheckoutService {
@@ -0,0 +1,43 @@
+namespace rs collection.v1
+
+include "common.thrift"
+include "product.thrift"
+
+struct Collection {
+    1: required i64 id;
+    2: required string handle;
+    3: required string title;
+    4: required string description;
+}
+
+struct CollectionConnection {
+    1: required list<Collection> collections;
+    2: required bool hasPreviousPage;
+    3: required bool hasNextPage;
+}
+
+// A product in a collection, positioned by manual ordering
+struct CollectionProduct {
+    1: required i32 order_idx;
+    2: required product.Product product;
+}
+
+struct CollectionProductConnection {
+    1: required list<CollectionProduct> products;
+    2: required bool hasPreviousPage;
+    3: required bool hasNextPage;
+}
+
+struct NewCollection {
+    1: required string handle;
+    2: required string title;
+    3: optional string description;
+}
+
+service CollectionService {
+    void ping();  // used for health check
+    Collection getCollection(1: string handle);
+    CollectionConnection listCollections(1: common.PaginationOption params);
+    CollectionProductConnection listCollectionProducts(1: i64 id, 2: common.PaginationOption params);
+    Collection createCollection(1: NewCollection collection);
+}
@@ -18,4 +18,17 @@ struct PaginationOption {
     3: optional i32 first;
     4: optional i32 last;
     5: optional string order_by;
+}
+
+// A structured postal address, fields are validated by the service
+struct MailingAddress {
+    1: optional string first_name;
+    2: optional string last_name;
+    3: optional string company;
+    4: optional string line1;
+    5: optional string line2;
+    6: optional string city;
+    7: optional string province;
+    8: optional string zip;
+    9: optional string country_code;  // ISO 3166-1 alpha-2
 }
\ No newline at end of file
@@ -2,12 +2,27 @@ namespace rs product.v1
 
 include "common.thrift"
 
+// A product option, e.g. Edition: [Standard, Collector]
+struct ProductOption {
+    1: required i64 id;
+    2: required string name;
+    3: required list<string> values;
+    4: required i32 order_idx = 0;
+}
+
+// The value of an option selected by a variant, e.g. Edition=Collector
+struct SelectedOption {
+    1: required string name;
+    2: required string value;
+}
+
 struct ProductVariant {
     1: required i64 id;
     2: required common.Money price;
     3: required string title;
     4: required i32 inventory_count;
     5: required i32 order_idx = 0;
+    6: required list<SelectedOption> selected_options;
 }
 
 struct Product {
@@ -18,6 +33,16 @@ struct Product {
     5: required string currency_code;
     6: required list<common.Image> images;
     7: required list<ProductVariant> variants;
+    8: required string handle;
+    9: required list<ProductOption> options;
+}
+
+struct NewProduct {
+    1: required string title;
+    2: optional string sub_title;
+    3: optional string description;
+    4: required string currency_code;
+    5: optional string handle;  // generated from title if absent
 }
 
 struct ProductConnection {
@@ -26,8 +51,62 @@ struct ProductConnection {
     3: required bool hasNextPage;
 }
 
+// A variant in an imported catalog, identified by its key in the product, variants
+// not imported are identified by their ids
+struct CatalogVariant {
+    1: required string key;
+    2: required string title;
+    3: required i64 price;
+    4: optional i32 inventory_count;  // left unchanged if absent
+}
+
+struct CatalogImage {
+    1: required string url;
+    2: required string alt_text;
+}
+
+// A product in an imported catalog, identified by its key across imports, products
+// not imported are identified by their handles
+struct CatalogProduct {
+    1: required string key;
+    2: optional string handle;  // generated from title if absent
+    3: required string title;
+    4: required string sub_title;
+    5: required string description;
+    6: required string currency_code;
+    7: required list<CatalogVariant> variants;
+    8: required list<CatalogImage> images;
+}
+
+struct CatalogPage {
+    1: required list<CatalogProduct> products;
+    2: optional i64 next_cursor;  // absent on the last page
+}
+
+enum CatalogAction {
+    UNCHANGED = 0,
+    CREATED = 1,
+    UPDATED = 2,
+}
+
+// What an import did, or would do in a dry run, to a product
+struct CatalogChange {
+    1: required string key;
+    2: required CatalogAction action;
+    3: required i64 product_id;  // 0 if it would be created by a dry run
+    4: required string handle;
+    5: required list<string> changes;  // human readable, e.g. `title: "A" -> "B"`
+}
+
 service ProductService {
     void ping();  // used for health check
     Product getProduct(1: i64 id);
+    Product getProductByHandle(1: string handle);  // previous handles are redirected
     ProductConnection listProducts(1: common.PaginationOption params);
+    Product createProduct(1: NewProduct product);
+    Product changeProductHandle(1: i64 id, 2: string handle);
+    Product createProductOption(1: i64 id, 2: string name, 3: list<string> values);
+    Product setVariantOptions(1: i64 variant_id, 2: list<SelectedOption> selected_options);
+    list<CatalogChange> importCatalog(1: list<CatalogProduct> products, 2: bool dry_run);
+    CatalogPage exportCatalog(1: optional i64 cursor, 2: bool inventory);
 }
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+drop table t_collection_products;
+drop table t_collections;
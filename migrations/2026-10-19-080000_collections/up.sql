@@ -0,0 +1,56 @@
+-- Your SQL goes here
+create table if not exists t_collections
+(
+    id          bigserial               not null
+        constraint t_collections_pk
+            primary key,
+    handle      varchar                 not null
+        constraint t_collections_handle_uk
+            unique,
+    title       varchar   default ''    not null,
+    description text      default ''    not null,
+    created_at  timestamp default now() not null,
+    updated_at  timestamp default now() not null
+);
+
+comment on table t_collections is 'collections (categories) of products';
+
+comment on column t_collections.id is 'pk';
+
+comment on column t_collections.handle is 'unique human readable handle, e.g. switch-games';
+
+comment on column t_collections.title is 'title of this collection';
+
+comment on column t_collections.description is 'description of this collection';
+
+create table if not exists t_collection_products
+(
+    id         bigserial               not null
+        constraint t_collection_products_pk
+            primary key,
+    cid        bigint                  not null
+        constraint t_collection_products_t_collections_id_fk
+            references t_collections,
+    pid        bigint                  not null
+        constraint t_collection_products_t_products_id_fk
+            references t_products,
+    order_idx  int4      default 0     not null,
+    created_at timestamp default now() not null,
+    updated_at timestamp default now() not null,
+    constraint t_collection_products_cid_pid_uk
+        unique (cid, pid)
+);
+
+create index t_collection_products_cid_order_idx_index on t_collection_products (cid, order_idx);
+
+create index t_collection_products_pid_index on t_collection_products (pid);
+
+comment on table t_collection_products is 'membership of products in collections';
+
+comment on column t_collection_products.id is 'pk';
+
+comment on column t_collection_products.cid is 'fk to t_collections';
+
+comment on column t_collection_products.pid is 'fk to t_products';
+
+comment on column t_collection_products.order_idx is 'the manual position of the product in the collection';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop table t_product_redirects;
+alter table t_products
+    drop column handle;
@@ -0,0 +1,68 @@
+-- Your SQL goes here
+alter table t_products
+    add column handle varchar;
+
+-- generate handles for existing products from their titles
+update t_products
+set handle = trim(both '-' from regexp_replace(lower(title), '[^a-z0-9]+', '-', 'g'));
+
+update t_products
+set handle = 'product'
+where handle = '';
+
+-- products with the same title get the first free numeric suffix, e.g. `foo-2`,
+-- like the handles generated for new products
+do
+$$
+    declare
+        p      record;
+        suffix int;
+    begin
+        for p in select id, handle
+                 from t_products q
+                 where exists(select 1 from t_products r where r.handle = q.handle and r.id < q.id)
+                 order by id
+            loop
+                suffix := 2;
+                while exists(select 1 from t_products where handle = p.handle || '-' || suffix)
+                    loop
+                        suffix := suffix + 1;
+                    end loop;
+                update t_products set handle = p.handle || '-' || suffix where id = p.id;
+            end loop;
+    end
+$$;
+
+alter table t_products
+    alter column handle set not null;
+
+alter table t_products
+    add constraint t_products_handle_uk
+        unique (handle);
+
+comment on column t_products.handle is 'unique human readable handle used in storefront urls, e.g. super-mario-odyssey';
+
+create table if not exists t_product_redirects
+(
+    id         bigserial               not null
+        constraint t_product_redirects_pk
+            primary key,
+    handle     varchar                 not null
+        constraint t_product_redirects_handle_uk
+            unique,
+    pid        bigint                  not null
+        constraint t_product_redirects_t_products_id_fk
+            references t_products,
+    created_at timestamp default now() not null,
+    updated_at timestamp default now() not null
+);
+
+create index t_product_redirects_pid_index on t_product_redirects (pid);
+
+comment on table t_product_redirects is 'previous handles of products, used to redirect old storefront urls';
+
+comment on column t_product_redirects.id is 'pk';
+
+comment on column t_product_redirects.handle is 'the previous handle';
+
+comment on column t_product_redirects.pid is 'fk to t_products';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop table t_product_variant_options;
+drop table t_product_option_values;
+drop table t_product_options;
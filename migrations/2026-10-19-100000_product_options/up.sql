@@ -0,0 +1,82 @@
+-- Your SQL goes here
+create table if not exists t_product_options
+(
+    id         bigserial               not null
+        constraint t_product_options_pk
+            primary key,
+    pid        bigint                  not null
+        constraint t_product_options_t_products_id_fk
+            references t_products,
+    name       varchar                 not null,
+    order_idx  int4      default 0     not null,
+    created_at timestamp default now() not null,
+    updated_at timestamp default now() not null,
+    constraint t_product_options_pid_name_uk
+        unique (pid, name)
+);
+
+comment on table t_product_options is 'options of products, e.g. size, color, edition';
+
+comment on column t_product_options.id is 'pk';
+
+comment on column t_product_options.pid is 'fk to t_products';
+
+comment on column t_product_options.name is 'name of this option, e.g. Edition';
+
+comment on column t_product_options.order_idx is 'the index in options of a product';
+
+create table if not exists t_product_option_values
+(
+    id         bigserial               not null
+        constraint t_product_option_values_pk
+            primary key,
+    oid        bigint                  not null
+        constraint t_product_option_values_t_product_options_id_fk
+            references t_product_options,
+    value      varchar                 not null,
+    order_idx  int4      default 0     not null,
+    created_at timestamp default now() not null,
+    updated_at timestamp default now() not null,
+    constraint t_product_option_values_oid_value_uk
+        unique (oid, value)
+);
+
+comment on table t_product_option_values is 'values of product options';
+
+comment on column t_product_option_values.id is 'pk';
+
+comment on column t_product_option_values.oid is 'fk to t_product_options';
+
+comment on column t_product_option_values.value is 'the value, e.g. Collector';
+
+comment on column t_product_option_values.order_idx is 'the index in values of an option';
+
+create table if not exists t_product_variant_options
+(
+    id         bigserial               not null
+        constraint t_product_variant_options_pk
+            primary key,
+    vid        bigint                  not null
+        constraint t_product_variant_options_t_product_variants_id_fk
+            references t_product_variants,
+    oid        bigint                  not null
+        constraint t_product_variant_options_t_product_options_id_fk
+            references t_product_options,
+    ovid       bigint                  not null
+        constraint t_product_variant_options_t_product_option_values_id_fk
+            references t_product_option_values,
+    created_at timestamp default now() not null,
+    updated_at timestamp default now() not null,
+    constraint t_product_variant_options_vid_oid_uk
+        unique (vid, oid)
+);
+
+comment on table t_product_variant_options is 'the option values selected by variants';
+
+comment on column t_product_variant_options.id is 'pk';
+
+comment on column t_product_variant_options.vid is 'fk to t_product_variants';
+
+comment on column t_product_variant_options.oid is 'fk to t_product_options, at most one value of an option is selected by a variant';
+
+comment on column t_product_variant_options.ovid is 'fk to t_product_option_values';
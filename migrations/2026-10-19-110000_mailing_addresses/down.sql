@@ -0,0 +1,27 @@
+-- This file should undo anything in `up.sql`
+alter table t_checkouts
+    add column full_name    varchar,
+    add column country_code varchar,
+    add column address      varchar,
+    add column postcode     varchar;
+
+update t_checkouts c
+set full_name    = nullif(concat_ws(' ', a.first_name, a.last_name), ''),
+    country_code = a.country_code,
+    address      = nullif(concat_ws(', ', a.company, a.line1, a.line2, a.city, a.province), ''),
+    postcode     = a.zip
+from t_mailing_addresses a
+where a.id = c.shipping_aid;
+
+comment on column t_checkouts.full_name is 'receiver full name, required when checkout';
+
+comment on column t_checkouts.country_code is 'receiver country code, required when checkout';
+
+comment on column t_checkouts.address is 'receiver address, required when checkout';
+
+comment on column t_checkouts.postcode is 'receiver post code, required when checkout';
+
+alter table t_checkouts
+    drop column shipping_aid;
+
+drop table t_mailing_addresses;
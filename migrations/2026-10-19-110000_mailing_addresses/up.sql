@@ -0,0 +1,63 @@
+-- Your SQL goes here
+create table if not exists t_mailing_addresses
+(
+    id           bigserial               not null
+        constraint t_mailing_addresses_pk
+            primary key,
+    customer_id  varchar,
+    first_name   varchar,
+    last_name    varchar,
+    company      varchar,
+    line1        varchar,
+    line2        varchar,
+    city         varchar,
+    province     varchar,
+    zip          varchar,
+    country_code varchar,
+    created_at   timestamp default now() not null,
+    updated_at   timestamp default now() not null
+);
+
+create index t_mailing_addresses_customer_id_index on t_mailing_addresses (customer_id);
+
+comment on table t_mailing_addresses is 'structured addresses of checkouts and address books';
+
+comment on column t_mailing_addresses.id is 'pk';
+
+comment on column t_mailing_addresses.customer_id is 'customer owning this address book entry, null for addresses of checkouts';
+
+comment on column t_mailing_addresses.line1 is 'street address';
+
+comment on column t_mailing_addresses.line2 is 'apartment, suite, unit, etc.';
+
+comment on column t_mailing_addresses.province is 'region, state or province';
+
+comment on column t_mailing_addresses.zip is 'zip or postal code';
+
+comment on column t_mailing_addresses.country_code is 'ISO 3166-1 alpha-2 country code';
+
+alter table t_checkouts
+    add column shipping_aid bigint;
+
+-- allocate an address for every checkout with receiver data, the full name
+-- cannot be split reliably so it is kept as the last name
+update t_checkouts
+set shipping_aid = nextval('t_mailing_addresses_id_seq')
+where coalesce(full_name, country_code, address, postcode) is not null;
+
+insert into t_mailing_addresses (id, last_name, line1, zip, country_code)
+select shipping_aid, full_name, address, postcode, country_code
+from t_checkouts
+where shipping_aid is not null;
+
+alter table t_checkouts
+    add constraint t_checkouts_t_mailing_addresses_id_fk
+        foreign key (shipping_aid) references t_mailing_addresses;
+
+alter table t_checkouts
+    drop column full_name,
+    drop column country_code,
+    drop column address,
+    drop column postcode;
+
+comment on column t_checkouts.shipping_aid is 'fk of shipping address, required when checkout';
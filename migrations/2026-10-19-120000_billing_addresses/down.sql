@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+alter table t_checkouts
+    drop column billing_aid,
+    drop column billing_same_as_shipping;
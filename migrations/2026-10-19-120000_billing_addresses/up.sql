@@ -0,0 +1,10 @@
+-- Your SQL goes here
+alter table t_checkouts
+    add column billing_aid              bigint
+        constraint t_checkouts_t_mailing_addresses_billing_id_fk
+            references t_mailing_addresses,
+    add column billing_same_as_shipping boolean default true not null;
+
+comment on column t_checkouts.billing_aid is 'fk of billing address, required when paid unless it is same as shipping';
+
+comment on column t_checkouts.billing_same_as_shipping is 'the shipping address is used as the billing address';
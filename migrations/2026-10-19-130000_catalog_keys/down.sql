@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table t_product_variants
+    drop column external_key;
+
+alter table t_products
+    drop column external_key;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+alter table t_products
+    add column external_key varchar
+        constraint t_products_external_key_uk
+            unique;
+
+comment on column t_products.external_key is 'key of the product in the imported catalog, optional';
+
+alter table t_product_variants
+    add column external_key varchar,
+    add constraint t_product_variants_pid_external_key_uk
+        unique (pid, external_key);
+
+comment on column t_product_variants.external_key is 'key of the variant in the imported catalog, unique in a product, optional';
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+alter table t_cart_entries
+    add column variant integer default 0 not null;
+
+update t_cart_entries e
+set variant = v.order_idx
+from t_product_variants v
+where v.id = e.vid;
+
+alter table t_cart_entries
+    drop column vid;
+
+comment on column t_cart_entries.variant is 'the index of variants selected in product';
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+alter table t_cart_entries
+    add column vid bigint
+        constraint t_cart_entries_t_product_variants_id_fk
+            references t_product_variants;
+
+update t_cart_entries e
+set vid = v.id
+from t_product_variants v
+where v.pid = e.pid
+  and v.order_idx = e.variant;
+
+delete
+from t_cart_entries
+where vid is null;
+
+alter table t_cart_entries
+    alter column vid set not null,
+    drop column variant;
+
+create index t_cart_entries_vid_index on t_cart_entries (vid);
+
+comment on column t_cart_entries.vid is 'fk to t_product_variants, the variant selected in product';
@@ -0,0 +1,467 @@
+//! The record format of catalog files. A record is a product with its variants and
+//! images, e.g. in JSON:
+//!
+//! ```json
+//! {
+//!   "key": "SMO",
+//!   "handle": "super-mario-odyssey",
+//!   "title": "Super Mario Odyssey",
+//!   "sub_title": "Nintendo Switch",
+//!   "description": "A 3D platformer.",
+//!   "currency_code": "USD",
+//!   "variants": [{"key": "SMO-STD", "title": "Standard", "price": "59.99", "inventory_count": 10}],
+//!   "images": [{"url": "https://example.com/mario.png", "alt_text": "Cover"}]
+//! }
+//! ```
+//!
+//! A CSV file has a row per variant or image with the columns in [CSV_HEADER], the
+//! product columns may be left empty in all but the first row of a product.
+
+use crate::admin::csv;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::{Error as DeError, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use volo_gen::product::v1::{
+    CatalogAction, CatalogChange, CatalogImage, CatalogProduct, CatalogVariant,
+};
+
+pub const CSV_HEADER: [&str; 12] = [
+    "key",
+    "handle",
+    "title",
+    "sub_title",
+    "description",
+    "currency_code",
+    "variant_key",
+    "variant_title",
+    "price",
+    "inventory_count",
+    "image_url",
+    "image_alt_text",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProductRecord {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub sub_title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_currency_code")]
+    pub currency_code: String,
+    #[serde(default)]
+    pub variants: Vec<VariantRecord>,
+    #[serde(default)]
+    pub images: Vec<ImageRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VariantRecord {
+    pub key: String,
+    pub title: String,
+    pub price: Price,
+    // the inventory is left unchanged by an import if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inventory_count: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ImageRecord {
+    pub url: String,
+    #[serde(default)]
+    pub alt_text: String,
+}
+
+fn default_currency_code() -> String {
+    "USD".to_string()
+}
+
+/// An amount in cents, written as a decimal with at most two fraction digits, e.g.
+/// `59.99`. A JSON number is accepted as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Price(pub i64);
+
+impl FromStr for Price {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid price `{s}`, expect a decimal like 59.99");
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if int.is_empty()
+            || frac.len() > 2
+            || !int.bytes().all(|b| b.is_ascii_digit())
+            || !frac.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let int = int.parse::<i64>().map_err(|_| invalid())?;
+        let frac = format!("{frac:0<2}")
+            .parse::<i64>()
+            .map_err(|_| invalid())?;
+        int.checked_mul(100)
+            .and_then(|v| v.checked_add(frac))
+            .map(Price)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Display for Price {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PriceVisitor;
+
+        impl<'de> Visitor<'de> for PriceVisitor {
+            type Value = Price;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("a decimal like 59.99")
+            }
+
+            fn visit_str<E: DeError>(self, v: &str) -> Result<Price, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: DeError>(self, v: u64) -> Result<Price, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_i64<E: DeError>(self, v: i64) -> Result<Price, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_f64<E: DeError>(self, v: f64) -> Result<Price, E> {
+                let cents = (v * 100.0).round();
+                if !v.is_finite() || v < 0.0 || (v * 100.0 - cents).abs() > 1e-6 {
+                    return Err(E::custom(format!("invalid price `{v}`")));
+                }
+                Ok(Price(cents as i64))
+            }
+        }
+
+        deserializer.deserialize_any(PriceVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// An array of records
+    Json,
+    /// A record per line
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    /// Guess the format by the extension of a file.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "jsonl" | "ndjson" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            _ => bail!("unknown format `{s}`, expect json, jsonl or csv"),
+        }
+    }
+}
+
+/// Parse records of a catalog file.
+pub fn read(format: Format, text: &str) -> Result<Vec<ProductRecord>> {
+    match format {
+        Format::Json => Ok(serde_json::from_str(text)?),
+        Format::Jsonl => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("line {}", i + 1)))
+            .collect(),
+        Format::Csv => read_csv(text),
+    }
+}
+
+fn read_csv(text: &str) -> Result<Vec<ProductRecord>> {
+    let mut rows = csv::parse(text)?.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Ok(vec![]);
+    };
+    let columns = CSV_HEADER
+        .iter()
+        .map(|name| {
+            header
+                .iter()
+                .position(|v| v.trim() == *name)
+                .ok_or_else(|| anyhow!("line 1: column `{name}` is missing"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut records: Vec<ProductRecord> = vec![];
+    let mut index = HashMap::new();
+    for (line, row) in rows {
+        let cell = |column: usize| {
+            row.get(columns[column])
+                .map(|v| v.as_str())
+                .unwrap_or_default()
+        };
+        let [key, handle, title, sub_title, description, currency_code, variant_key, variant_title, price, inventory_count, image_url, image_alt_text] =
+            std::array::from_fn(cell);
+        if key.is_empty() {
+            bail!("line {line}: key is empty");
+        }
+        let record = match index.get(key) {
+            Some(&i) => {
+                let record: &mut ProductRecord = &mut records[i];
+                let conflicts = [
+                    (
+                        "handle",
+                        handle,
+                        record.handle.as_deref().unwrap_or_default(),
+                    ),
+                    ("title", title, &record.title),
+                    ("sub_title", sub_title, &record.sub_title),
+                    ("description", description, &record.description),
+                    ("currency_code", currency_code, &record.currency_code),
+                ];
+                for (name, value, first) in conflicts {
+                    if !value.is_empty() && value != first {
+                        bail!("line {line}: {name} of product {key} differs from its first row");
+                    }
+                }
+                record
+            }
+            None => {
+                index.insert(key.to_string(), records.len());
+                records.push(ProductRecord {
+                    key: key.to_string(),
+                    handle: (!handle.is_empty()).then(|| handle.to_string()),
+                    title: title.to_string(),
+                    sub_title: sub_title.to_string(),
+                    description: description.to_string(),
+                    currency_code: if currency_code.is_empty() {
+                        default_currency_code()
+                    } else {
+                        currency_code.to_string()
+                    },
+                    variants: vec![],
+                    images: vec![],
+                });
+                records.last_mut().unwrap()
+            }
+        };
+        if !variant_key.is_empty() {
+            record.variants.push(VariantRecord {
+                key: variant_key.to_string(),
+                title: variant_title.to_string(),
+                price: price.parse().with_context(|| format!("line {line}"))?,
+                inventory_count: match inventory_count {
+                    "" => None,
+                    count => Some(count.parse().with_context(|| {
+                        format!("line {line}: invalid inventory_count `{count}`")
+                    })?),
+                },
+            });
+        }
+        if !image_url.is_empty() {
+            record.images.push(ImageRecord {
+                url: image_url.to_string(),
+                alt_text: image_alt_text.to_string(),
+            });
+        }
+    }
+    Ok(records)
+}
+
+/// Write records to a catalog file one by one, the file is completed by
+/// [Writer::finish].
+pub struct Writer<W: Write> {
+    format: Format,
+    out: W,
+    count: usize,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(format: Format, out: W) -> Self {
+        Self {
+            format,
+            out,
+            count: 0,
+        }
+    }
+
+    pub fn write(&mut self, record: &ProductRecord) -> Result<()> {
+        match self.format {
+            Format::Json => {
+                self.out
+                    .write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+                serde_json::to_writer(&mut self.out, record)?;
+            }
+            Format::Jsonl => {
+                serde_json::to_writer(&mut self.out, record)?;
+                self.out.write_all(b"\n")?;
+            }
+            Format::Csv => {
+                if self.count == 0 {
+                    csv::write_row(&mut self.out, &CSV_HEADER)?;
+                }
+                write_csv(&mut self.out, record)?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Returns the count of records written.
+    pub fn finish(mut self) -> Result<usize> {
+        match self.format {
+            Format::Json if self.count == 0 => self.out.write_all(b"[]\n")?,
+            Format::Json => self.out.write_all(b"\n]\n")?,
+            Format::Csv if self.count == 0 => csv::write_row(&mut self.out, &CSV_HEADER)?,
+            _ => {}
+        }
+        self.out.flush()?;
+        Ok(self.count)
+    }
+}
+
+// the columns of the product are only written in the first row of it
+fn write_csv<W: Write>(out: &mut W, record: &ProductRecord) -> std::io::Result<()> {
+    fn product(row: usize, value: &str) -> &str {
+        if row == 0 {
+            value
+        } else {
+            ""
+        }
+    }
+    let rows = record.variants.len().max(record.images.len()).max(1);
+    for i in 0..rows {
+        let variant = record.variants.get(i);
+        let image = record.images.get(i);
+        let price = variant.map(|v| v.price.to_string()).unwrap_or_default();
+        let inventory_count = variant
+            .and_then(|v| v.inventory_count)
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        csv::write_row(
+            out,
+            &[
+                &record.key,
+                product(i, record.handle.as_deref().unwrap_or_default()),
+                product(i, &record.title),
+                product(i, &record.sub_title),
+                product(i, &record.description),
+                product(i, &record.currency_code),
+                variant.map(|v| v.key.as_str()).unwrap_or_default(),
+                variant.map(|v| v.title.as_str()).unwrap_or_default(),
+                &price,
+                &inventory_count,
+                image.map(|v| v.url.as_str()).unwrap_or_default(),
+                image.map(|v| v.alt_text.as_str()).unwrap_or_default(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+impl From<CatalogProduct> for ProductRecord {
+    fn from(value: CatalogProduct) -> Self {
+        ProductRecord {
+            key: value.key.to_string(),
+            handle: value.handle.map(|v| v.to_string()),
+            title: value.title.to_string(),
+            sub_title: value.sub_title.to_string(),
+            description: value.description.to_string(),
+            currency_code: value.currency_code.to_string(),
+            variants: value
+                .variants
+                .into_iter()
+                .map(|v| VariantRecord {
+                    key: v.key.to_string(),
+                    title: v.title.to_string(),
+                    price: Price(v.price),
+                    inventory_count: v.inventory_count,
+                })
+                .collect(),
+            images: value
+                .images
+                .into_iter()
+                .map(|v| ImageRecord {
+                    url: v.url.to_string(),
+                    alt_text: v.alt_text.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<ProductRecord> for CatalogProduct {
+    fn from(value: ProductRecord) -> Self {
+        CatalogProduct {
+            key: value.key.into(),
+            handle: value.handle.map(Into::into),
+            title: value.title.into(),
+            sub_title: value.sub_title.into(),
+            description: value.description.into(),
+            currency_code: value.currency_code.into(),
+            variants: value
+                .variants
+                .into_iter()
+                .map(|v| CatalogVariant {
+                    key: v.key.into(),
+                    title: v.title.into(),
+                    price: v.price.0,
+                    inventory_count: v.inventory_count,
+                })
+                .collect(),
+            images: value
+                .images
+                .into_iter()
+                .map(|v| CatalogImage {
+                    url: v.url.into(),
+                    alt_text: v.alt_text.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Render a change as a line with its details indented below, e.g.
+/// `~ SMO super-mario-odyssey` followed by `    title: "A" -> "B"`.
+pub fn render(change: &CatalogChange) -> String {
+    let mark = match change.action {
+        CatalogAction::Created => '+',
+        CatalogAction::Updated => '~',
+        _ => '=',
+    };
+    let mut out = format!("{mark} {} {}", change.key, change.handle);
+    for detail in &change.changes {
+        out.push_str("\n    ");
+        out.push_str(detail);
+    }
+    out
+}
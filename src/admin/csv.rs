@@ -0,0 +1,71 @@
+//! A minimal RFC 4180 CSV reader and writer, fields containing separators, quotes or
+//! line breaks are quoted and quotes in them are doubled.
+
+use anyhow::{bail, Result};
+use std::io::Write;
+
+/// Parse all rows of a CSV document, each row is returned with the line it starts at.
+pub fn parse(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if quoted {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => {
+                    if ch == '\n' {
+                        line += 1;
+                    }
+                    field.push(ch);
+                }
+            }
+            continue;
+        }
+        match ch {
+            '"' if field.is_empty() => quoted = true,
+            '"' => bail!("line {line}: unexpected quote in an unquoted field"),
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push((start, std::mem::take(&mut row)));
+                line += 1;
+                start = line;
+            }
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        bail!("line {start}: unterminated quoted field");
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push((start, row));
+    }
+    // blank lines are not rows
+    rows.retain(|(_, row)| !(row.len() == 1 && row[0].is_empty()));
+    Ok(rows)
+}
+
+pub fn write_row<W: Write>(w: &mut W, fields: &[&str]) -> std::io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            w.write_all(field.as_bytes())?;
+        }
+    }
+    w.write_all(b"\r\n")
+}
@@ -0,0 +1,5 @@
+//! Administration tasks run by `shop-admin`, they share the configuration and
+//! resolvers of the graphql service, see [crate::graphql::Resolver].
+
+pub mod catalog;
+pub mod csv;
@@ -1,7 +1,19 @@
 use shop_backend::graphql::Resolver;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
-    let resolver = Resolver::new("config/sys-graphql.toml");
-    resolver.serve().await
+async fn main() -> ExitCode {
+    match Resolver::new("config/sys-graphql.toml") {
+        Ok(resolver) => match resolver.serve().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                tracing::error!("failed to serve: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -1,6 +1,19 @@
-#![feature(impl_trait_in_assoc_type)]
+use shop_backend::rpc::Resolver;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() {
-    unimplemented!()
+async fn main() -> ExitCode {
+    match Resolver::new("config/product-thrift.toml") {
+        Ok(resolver) => match resolver.serve().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                tracing::error!("failed to serve: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
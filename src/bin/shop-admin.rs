@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail, Context, Result};
+use shop_backend::admin::catalog::{self, Format};
+use shop_backend::graphql::Resolver;
+use shop_backend::infra::mqsrs::{Mutation, Query};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use volo_gen::product::v1::CatalogAction;
+
+const USAGE: &str = "\
+Usage: shop-admin [--config <FILE>] <COMMAND>
+
+Commands:
+  import [--dry-run] [--format <FORMAT>] <FILE>
+      Import products of a catalog, products are matched with the existing ones by
+      their keys. With --dry-run, the changes are printed but not written.
+      FORMAT is json, jsonl or csv, it is guessed by the extension of FILE if absent.
+      FILE is `-` to read the standard input.
+  export [--inventory] [--format <FORMAT>] [--output <FILE>]
+      Export all products in the format read by import, to the standard output
+      unless --output. The inventory is left out unless --inventory, it is left
+      unchanged when such a catalog is imported. FORMAT is json, jsonl or csv, it is
+      guessed by the extension of FILE if absent, jsonl by default.
+
+Options:
+  --config <FILE>  Configuration of the graphql service [default: config/sys-graphql.toml]
+  -h, --help       Print this help
+";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<()> {
+    let mut config = "config/sys-graphql.toml".to_string();
+    let mut args = args.into_iter();
+    let command = loop {
+        match args.next().as_deref() {
+            Some("--config") => config = args.next().context("--config requires a file")?,
+            Some("-h" | "--help") | None => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            Some(command) => break command.to_string(),
+        }
+    };
+    match command.as_str() {
+        "import" => import(&config, args.collect()).await,
+        "export" => export(&config, args.collect()).await,
+        _ => bail!("unknown command `{command}`, see --help"),
+    }
+}
+
+async fn import(config: &str, args: Vec<String>) -> Result<()> {
+    let mut dry_run = false;
+    let mut format = None;
+    let mut file = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--format" => format = Some(args.next().context("--format requires a value")?),
+            _ if file.is_none() => file = Some(arg),
+            _ => bail!("unexpected argument `{arg}`, see --help"),
+        }
+    }
+    let file = file.context("import requires a file, see --help")?;
+    let format = match format {
+        Some(format) => format.parse()?,
+        None => Format::from_path(Path::new(&file))
+            .context("cannot guess the format of the file, use --format")?,
+    };
+    let text = if file == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        std::fs::read_to_string(&file).with_context(|| format!("cannot read {file}"))?
+    };
+    let products = catalog::read(format, &text)
+        .with_context(|| format!("cannot parse {file}"))?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    let resolver = Resolver::new(config)?;
+    let changes = resolver
+        .create_import_catalog()
+        .execute((products, dry_run))
+        .await
+        .map_err(|e| anyhow!("{e}"))?;
+    for change in &changes {
+        println!("{}", catalog::render(change));
+    }
+    let count = |action| changes.iter().filter(|v| v.action == action).count();
+    println!(
+        "{} products: {} created, {} updated, {} unchanged{}",
+        changes.len(),
+        count(CatalogAction::Created),
+        count(CatalogAction::Updated),
+        count(CatalogAction::Unchanged),
+        if dry_run { " (dry run)" } else { "" }
+    );
+    Ok(())
+}
+
+async fn export(config: &str, args: Vec<String>) -> Result<()> {
+    let mut inventory = false;
+    let mut format = None;
+    let mut output = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--inventory" => inventory = true,
+            "--format" => format = Some(args.next().context("--format requires a value")?),
+            "--output" => output = Some(args.next().context("--output requires a file")?),
+            _ => bail!("unexpected argument `{arg}`, see --help"),
+        }
+    }
+    let format = match (format, &output) {
+        (Some(format), _) => format.parse()?,
+        (None, Some(output)) => Format::from_path(Path::new(output))
+            .context("cannot guess the format of the file, use --format")?,
+        (None, None) => Format::Jsonl,
+    };
+    let out: Box<dyn Write> = match &output {
+        Some(output) => {
+            Box::new(File::create(output).with_context(|| format!("cannot create {output}"))?)
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut writer = catalog::Writer::new(format, BufWriter::new(out));
+
+    let resolver = Resolver::new(config)?;
+    let export = resolver.create_export_catalog();
+    let mut cursor = None;
+    loop {
+        let page = export
+            .execute((cursor, inventory))
+            .await
+            .map_err(|e| anyhow!("{e}"))?;
+        for product in page.products {
+            writer.write(&product.into())?;
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    let count = writer.finish()?;
+    eprintln!("{count} products exported");
+    Ok(())
+}
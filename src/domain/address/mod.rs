@@ -0,0 +1,59 @@
+pub mod model;
+pub mod mutation;
+pub mod query;
+
+use crate::infra::error::Result;
+use crate::infra::mqsrs::traced;
+use crate::infra::mqsrs::Mutation;
+use crate::infra::mqsrs::Query;
+use volo_gen::address::v1::Address;
+use volo_gen::common::v1::MailingAddress;
+
+pub mod graphql {
+    use super::*;
+    use crate::graphql::Resolver;
+
+    impl Resolver {
+        pub fn create_list_addresses(&self) -> impl Query<String, Result<Vec<Address>>> + '_ {
+            use crate::domain::address::query::list_addresses::execute;
+
+            traced("list_addresses", move |customer_id: String| {
+                self.pg_blocking(move |conn| execute(customer_id, conn))
+            })
+        }
+
+        pub fn create_create_address(
+            &self,
+        ) -> impl Mutation<(String, MailingAddress), Result<Address>> + '_ {
+            use crate::domain::address::mutation::create_address::execute;
+
+            traced(
+                "create_address",
+                move |(customer_id, address): (String, MailingAddress)| {
+                    self.pg_blocking(move |conn| execute(customer_id, address, conn))
+                },
+            )
+        }
+
+        pub fn create_update_address(
+            &self,
+        ) -> impl Mutation<(String, i64, MailingAddress), Result<Address>> + '_ {
+            use crate::domain::address::mutation::update_address::execute;
+
+            traced(
+                "update_address",
+                move |(customer_id, id, address): (String, i64, MailingAddress)| {
+                    self.pg_blocking(move |conn| execute(customer_id, id, address, conn))
+                },
+            )
+        }
+
+        pub fn create_delete_address(&self) -> impl Mutation<(String, i64), Result<Address>> + '_ {
+            use crate::domain::address::mutation::delete_address::execute;
+
+            traced("delete_address", move |(customer_id, id): (String, i64)| {
+                self.pg_blocking(move |conn| execute(customer_id, id, conn))
+            })
+        }
+    }
+}
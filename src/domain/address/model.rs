@@ -0,0 +1,267 @@
+use crate::infra::error::Result;
+use crate::infra::error::Status;
+use crate::infra::validate::{self, Validator};
+use crate::schema::t_mailing_addresses;
+use diesel::prelude::*;
+use volo_gen::address::v1::Address;
+use volo_gen::common::v1::MailingAddress;
+
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = t_mailing_addresses)]
+pub struct QueryMailingAddress {
+    pub id: i64,
+    pub customer_id: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub company: Option<String>,
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub city: Option<String>,
+    pub province: Option<String>,
+    pub zip: Option<String>,
+    pub country_code: Option<String>,
+}
+
+/// All fields are written, `None` clears the field.
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = t_mailing_addresses, treat_none_as_null = true)]
+pub struct PutMailingAddress<'a> {
+    pub first_name: Option<&'a str>,
+    pub last_name: Option<&'a str>,
+    pub company: Option<&'a str>,
+    pub line1: Option<&'a str>,
+    pub line2: Option<&'a str>,
+    pub city: Option<&'a str>,
+    pub province: Option<&'a str>,
+    pub zip: Option<&'a str>,
+    pub country_code: Option<&'a str>,
+}
+
+impl<'a> From<&'a MailingAddress> for PutMailingAddress<'a> {
+    fn from(value: &'a MailingAddress) -> Self {
+        Self {
+            first_name: value.first_name.as_deref(),
+            last_name: value.last_name.as_deref(),
+            company: value.company.as_deref(),
+            line1: value.line1.as_deref(),
+            line2: value.line2.as_deref(),
+            city: value.city.as_deref(),
+            province: value.province.as_deref(),
+            zip: value.zip.as_deref(),
+            country_code: value.country_code.as_deref(),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_mailing_addresses)]
+pub struct NewMailingAddress<'a> {
+    pub customer_id: Option<&'a str>,
+    #[diesel(embed)]
+    pub address: PutMailingAddress<'a>,
+}
+
+/// A mailing address of a checkout, or an entry of an address book if it has
+/// a customer.
+pub struct MailingAddressDomain {
+    id: i64,
+    customer_id: Option<String>,
+    address: MailingAddress,
+}
+
+impl From<QueryMailingAddress> for MailingAddressDomain {
+    fn from(value: QueryMailingAddress) -> Self {
+        Self {
+            id: value.id,
+            customer_id: value.customer_id,
+            address: MailingAddress {
+                first_name: value.first_name.map(Into::into),
+                last_name: value.last_name.map(Into::into),
+                company: value.company.map(Into::into),
+                line1: value.line1.map(Into::into),
+                line2: value.line2.map(Into::into),
+                city: value.city.map(Into::into),
+                province: value.province.map(Into::into),
+                zip: value.zip.map(Into::into),
+                country_code: value.country_code.map(Into::into),
+            },
+        }
+    }
+}
+
+impl MailingAddressDomain {
+    pub(in crate::domain) fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub(in crate::domain) fn into_mailing_address(self) -> MailingAddress {
+        self.address
+    }
+
+    pub(in crate::domain) fn into_address(self) -> Address {
+        Address {
+            id: self.id,
+            customer_id: self.customer_id.unwrap_or_default().into(),
+            address: self.address,
+        }
+    }
+
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query(id: i64, conn: &mut PgConnection) -> Result<Self> {
+        let address = t_mailing_addresses::table
+            .find(id)
+            .select(QueryMailingAddress::as_select())
+            .get_result(conn)
+            .map_err(|e| {
+                if matches!(e, diesel::NotFound) {
+                    Status::not_found(format!("mailing_address({})", id))
+                } else {
+                    Status::internal()
+                }
+            })?;
+        Ok(address.into())
+    }
+
+    /// Query an entry of the address book of `customer_id`, addresses of checkouts and
+    /// entries of other customers are not found.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query_entry(
+        id: i64,
+        customer_id: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Self> {
+        let address = t_mailing_addresses::table
+            .find(id)
+            .filter(t_mailing_addresses::customer_id.eq(customer_id))
+            .select(QueryMailingAddress::as_select())
+            .get_result(conn)
+            .map_err(|e| {
+                if matches!(e, diesel::NotFound) {
+                    Status::not_found(format!("mailing_address({})", id))
+                } else {
+                    Status::internal()
+                }
+            })?;
+        Ok(address.into())
+    }
+
+    /// List the address book of a customer, in the order of creation.
+    pub(in crate::domain) fn list_by_customer(
+        customer_id: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>> {
+        let res = t_mailing_addresses::table
+            .filter(t_mailing_addresses::customer_id.eq(customer_id))
+            .order(t_mailing_addresses::id.asc())
+            .select(QueryMailingAddress::as_select())
+            .get_results(conn)?;
+        Ok(res.into_iter().map(Into::into).collect())
+    }
+
+    pub(in crate::domain) fn create(
+        customer_id: Option<&str>,
+        address: &MailingAddress,
+        conn: &mut PgConnection,
+    ) -> Result<Self> {
+        let address = diesel::insert_into(t_mailing_addresses::table)
+            .values(NewMailingAddress {
+                customer_id,
+                address: address.into(),
+            })
+            .returning(QueryMailingAddress::as_returning())
+            .get_result(conn)?;
+        Ok(address.into())
+    }
+
+    /// Replace all fields of the address.
+    pub(in crate::domain) fn update(
+        &mut self,
+        address: MailingAddress,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::update(t_mailing_addresses::table)
+            .filter(t_mailing_addresses::id.eq(self.id))
+            .filter(t_mailing_addresses::customer_id.is_not_distinct_from(&self.customer_id))
+            .set((
+                PutMailingAddress::from(&address),
+                t_mailing_addresses::updated_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+        self.address = address;
+        Ok(())
+    }
+
+    pub(in crate::domain) fn delete(self, conn: &mut PgConnection) -> Result<Self> {
+        diesel::delete(t_mailing_addresses::table)
+            .filter(t_mailing_addresses::id.eq(self.id))
+            .filter(t_mailing_addresses::customer_id.is_not_distinct_from(&self.customer_id))
+            .execute(conn)?;
+        Ok(self)
+    }
+}
+
+/// Normalize the address and record violations of `field`, e.g. `shippingAddress`,
+/// fields are named as in the GraphQL input. Blank fields are cleared, country
+/// code and zip are in upper case.
+pub(in crate::domain) fn normalize_address(
+    field: &str,
+    address: MailingAddress,
+    v: &mut Validator,
+) -> MailingAddress {
+    let clean = |value: Option<pilota::FastStr>, upper: bool| {
+        value
+            .map(|v| {
+                if upper {
+                    v.trim().to_uppercase()
+                } else {
+                    v.trim().to_string()
+                }
+            })
+            .filter(|v| !v.is_empty())
+            .map(Into::into)
+    };
+    let address = MailingAddress {
+        first_name: clean(address.first_name, false),
+        last_name: clean(address.last_name, false),
+        company: clean(address.company, false),
+        line1: clean(address.line1, false),
+        line2: clean(address.line2, false),
+        city: clean(address.city, false),
+        province: clean(address.province, false),
+        zip: clean(address.zip, true),
+        country_code: clean(address.country_code, true),
+    };
+    if let Some(code) = &address.country_code {
+        v.check(
+            &format!("{field}.countryCode"),
+            validate::is_country_code(code),
+            "not an ISO 3166-1 alpha-2 country code",
+        );
+    }
+    if let Some(zip) = &address.zip {
+        let country = address.country_code.as_deref().unwrap_or_default();
+        v.check(
+            &format!("{field}.zip"),
+            validate::is_postcode(country, zip),
+            format!("not a postcode of country '{}'", country),
+        );
+    }
+    address
+}
+
+/// Record violations of `field` unless the address is complete enough to ship to.
+pub(in crate::domain) fn require_address(field: &str, address: &MailingAddress, v: &mut Validator) {
+    let required = [
+        ("lastName", &address.last_name),
+        ("line1", &address.line1),
+        ("city", &address.city),
+        ("countryCode", &address.country_code),
+    ];
+    for (name, value) in required {
+        v.check(&format!("{field}.{name}"), value.is_some(), "required");
+    }
+}
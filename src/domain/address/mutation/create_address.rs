@@ -0,0 +1,20 @@
+use crate::domain::address::model::{normalize_address, require_address, MailingAddressDomain};
+use crate::infra::error::Result;
+use crate::infra::validate::Validator;
+use diesel::PgConnection;
+use volo_gen::address::v1::Address;
+use volo_gen::common::v1::MailingAddress;
+
+pub(in crate::domain) fn execute(
+    customer_id: String,
+    address: MailingAddress,
+    conn: &mut PgConnection,
+) -> Result<Address> {
+    let mut v = Validator::new();
+    let address = normalize_address("address", address, &mut v);
+    require_address("address", &address, &mut v);
+    v.check("customerId", !customer_id.trim().is_empty(), "required");
+    v.finish()?;
+    let address = MailingAddressDomain::create(Some(customer_id.trim()), &address, conn)?;
+    Ok(address.into_address())
+}
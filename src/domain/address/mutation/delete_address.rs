@@ -0,0 +1,15 @@
+use crate::domain::address::model::MailingAddressDomain;
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::address::v1::Address;
+
+pub(in crate::domain) fn execute(
+    customer_id: String,
+    id: i64,
+    conn: &mut PgConnection,
+) -> Result<Address> {
+    conn.transaction(|conn| {
+        let entry = MailingAddressDomain::query_entry(id, &customer_id, conn)?;
+        Ok(entry.delete(conn)?.into_address())
+    })
+}
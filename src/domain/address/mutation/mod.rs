@@ -0,0 +1,3 @@
+pub mod create_address;
+pub mod delete_address;
+pub mod update_address;
@@ -0,0 +1,23 @@
+use crate::domain::address::model::{normalize_address, require_address, MailingAddressDomain};
+use crate::infra::error::Result;
+use crate::infra::validate::Validator;
+use diesel::{Connection, PgConnection};
+use volo_gen::address::v1::Address;
+use volo_gen::common::v1::MailingAddress;
+
+pub(in crate::domain) fn execute(
+    customer_id: String,
+    id: i64,
+    address: MailingAddress,
+    conn: &mut PgConnection,
+) -> Result<Address> {
+    let mut v = Validator::new();
+    let address = normalize_address("address", address, &mut v);
+    require_address("address", &address, &mut v);
+    v.finish()?;
+    conn.transaction(|conn| {
+        let mut entry = MailingAddressDomain::query_entry(id, &customer_id, conn)?;
+        entry.update(address, conn)?;
+        Ok(entry.into_address())
+    })
+}
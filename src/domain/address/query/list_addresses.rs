@@ -0,0 +1,15 @@
+use crate::domain::address::model::MailingAddressDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::address::v1::Address;
+
+pub(in crate::domain) fn execute(
+    customer_id: String,
+    conn: &mut PgConnection,
+) -> Result<Vec<Address>> {
+    let addresses = MailingAddressDomain::list_by_customer(&customer_id, conn)?;
+    Ok(addresses
+        .into_iter()
+        .map(MailingAddressDomain::into_address)
+        .collect())
+}
@@ -3,11 +3,18 @@ pub mod mutation;
 pub mod query;
 
 use crate::infra::error::Result;
+use crate::infra::mqsrs::traced;
 use crate::infra::mqsrs::Mutation;
 use crate::infra::mqsrs::Query;
-use std::ops::DerefMut;
+use crate::infra::mqsrs::Subscription;
+use futures::stream::BoxStream;
 use volo_gen::cart::v1::Cart;
 
+/// The channel notified when entries of the cart change.
+pub fn cart_channel(id: i64) -> String {
+    format!("cart:{id}")
+}
+
 pub mod graphql {
     use super::*;
     use crate::graphql::Resolver;
@@ -16,25 +23,50 @@ pub mod graphql {
         pub fn create_get_cart(&self) -> impl Query<i64, Result<Cart>> + '_ {
             use crate::domain::cart::query::get_cart::execute;
 
-            move |req: i64| async move { execute(req, self.pg_conn()?.deref_mut()) }
+            traced("get_cart", move |req: i64| {
+                self.pg_blocking(move |conn| execute(req, conn))
+            })
+        }
+
+        pub fn create_subscribe_cart(
+            &self,
+        ) -> impl Subscription<i64, Result<Cart>, OutStream = BoxStream<'static, Result<Cart>>> + '_
+        {
+            use crate::domain::cart::query::get_cart::execute;
+
+            move |id: i64| {
+                self.pg_subscribe(vec![cart_channel(id)], move |_, conn| execute(id, conn))
+            }
         }
 
         pub fn create_create_cart(&self) -> impl Mutation<(), Result<Cart>> + '_ {
             use crate::domain::cart::mutation::create_cart::execute;
 
-            move |_: ()| async move { execute(self.pg_conn()?.deref_mut()) }
+            traced("create_cart", move |_: ()| self.pg_blocking(execute))
         }
 
         pub fn create_add_to_cart(&self) -> impl Mutation<(i64, i64), Result<Cart>> + '_ {
             use crate::domain::cart::mutation::add_to_cart::execute;
 
-            move |req: (i64, i64)| async move { execute(req.0, req.1, self.pg_conn()?.deref_mut()) }
+            traced("add_to_cart", move |req: (i64, i64)| async move {
+                let cart = self
+                    .pg_blocking(move |conn| execute(req.0, req.1, conn))
+                    .await?;
+                self.publish(cart_channel(cart.id)).await;
+                Ok(cart)
+            })
         }
 
         pub fn create_remove_from_cart(&self) -> impl Mutation<(i64, i64), Result<Cart>> + '_ {
             use crate::domain::cart::mutation::remove_from_cart::execute;
 
-            move |req: (i64, i64)| async move { execute(req.0, req.1, self.pg_conn()?.deref_mut()) }
+            traced("remove_from_cart", move |req: (i64, i64)| async move {
+                let cart = self
+                    .pg_blocking(move |conn| execute(req.0, req.1, conn))
+                    .await?;
+                self.publish(cart_channel(cart.id)).await;
+                Ok(cart)
+            })
         }
     }
 }
@@ -22,7 +22,7 @@ pub struct QueryCartEntry {
     pub cid: i64,
     pub pid: i64,
     pub quantity: i32,
-    pub variant: i32,
+    pub vid: i64,
 }
 
 #[derive(Insertable, Debug)]
@@ -31,7 +31,18 @@ pub struct NewCartEntry {
     pub cid: i64,
     pub pid: i64,
     pub quantity: i32,
-    pub variant: i32,
+    pub vid: i64,
+}
+
+/// The index of the variant `vid` in the variants of `product`, as the entries of a
+/// cart select it.
+fn variant_index(product: &volo_gen::product::v1::Product, vid: i64) -> Result<i32> {
+    product
+        .variants
+        .iter()
+        .position(|v| v.id == vid)
+        .map(|idx| idx as i32)
+        .ok_or_else(|| Status::not_found(format!("variant({}) of product({})", vid, product.id)))
 }
 
 pub struct CartDomain(Cart);
@@ -82,7 +93,7 @@ impl CartDomain {
             .load::<QueryProductVariant>(conn)?
             .grouped_by(&products);
         let pids = products.iter().map(|v| v.id).collect::<Vec<_>>();
-        let products = products
+        let mut products = products
             .into_iter()
             .zip(images.into_iter().zip(variants))
             .map(|(product, (images, variants))| {
@@ -91,15 +102,19 @@ impl CartDomain {
             .zip(pids)
             .map(|(k, v)| (v, k))
             .collect::<HashMap<_, _>>();
+        ProductDomain::attach_options(products.values_mut(), conn)?;
         let entries = entries
             .into_iter()
-            .map(|v| CartEntry {
-                id: v.id,
-                quantity: v.quantity,
-                product: products[&v.pid].clone(),
-                variants: v.variant,
+            .map(|v| {
+                let product = products[&v.pid].clone();
+                Ok(CartEntry {
+                    id: v.id,
+                    quantity: v.quantity,
+                    variants: variant_index(&product, v.vid)?,
+                    product,
+                })
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
         Ok(CartDomain(Cart { id, entries }))
     }
 
@@ -123,23 +138,25 @@ impl CartDomain {
             return Ok(());
         };
         conn.transaction(|conn| {
-            let (pid, order_idx) = t_product_variants::table
+            let pid = t_product_variants::table
                 .find(variant_id)
-                .select((t_product_variants::pid, t_product_variants::order_idx))
+                .select(t_product_variants::pid)
                 .get_result(conn)?;
-            diesel::insert_into(t_cart_entries::table)
+            let id = diesel::insert_into(t_cart_entries::table)
                 .values(&NewCartEntry {
                     cid: self.0.id,
                     pid,
                     quantity: 1,
-                    variant: order_idx,
+                    vid: variant_id,
                 })
-                .execute(conn)?;
+                .returning(t_cart_entries::id)
+                .get_result(conn)?;
+            let product = ProductDomain::query(pid, conn)?.into_product();
             self.0.entries.push(CartEntry {
-                id: self.0.id,
-                product: ProductDomain::query(pid, conn)?.into_product(),
+                id,
                 quantity: 1,
-                variants: order_idx,
+                variants: variant_index(&product, variant_id)?,
+                product,
             });
             Ok(())
         })
@@ -1,4 +1,5 @@
 use crate::domain::cart::model::CartDomain;
+use crate::domain::checkout::model::CheckoutDomain;
 use crate::infra::error::Result;
 use diesel::{Connection, PgConnection};
 use volo_gen::cart::v1::Cart;
@@ -11,6 +12,7 @@ pub(in crate::domain) fn execute(
     conn.transaction(|conn| {
         let mut cart = CartDomain::query(cart_id, conn)?;
         cart.add_item(variant_id, conn)?;
+        CheckoutDomain::update_shipping_fee_by_cart_id(cart_id, conn)?;
         Ok(cart.into_cart())
     })
 }
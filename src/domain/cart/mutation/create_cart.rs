@@ -1,8 +1,11 @@
 use crate::domain::cart::model::CartDomain;
 use crate::infra::error::Result;
+use crate::infra::metrics::CARTS_CREATED;
 use diesel::PgConnection;
 use volo_gen::cart::v1::Cart;
 
 pub(in crate::domain) fn execute(conn: &mut PgConnection) -> Result<Cart> {
-    CartDomain::create(conn).map(|v| v.into_cart())
+    let cart = CartDomain::create(conn)?;
+    CARTS_CREATED.inc(&[]);
+    Ok(cart.into_cart())
 }
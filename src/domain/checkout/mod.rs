@@ -3,45 +3,100 @@ pub mod mutation;
 pub mod query;
 
 use crate::infra::error::Result;
+use crate::infra::mqsrs::traced;
 use crate::infra::mqsrs::Mutation;
 use crate::infra::mqsrs::Query;
-use std::ops::DerefMut;
+use crate::infra::mqsrs::Subscription;
+use futures::stream::BoxStream;
+use std::collections::HashMap;
 use volo_gen::checkout::v1::Checkout;
 
+/// The channel notified when the checkout changes, e.g. its information is submitted
+/// or the payment is confirmed.
+pub fn checkout_channel(id: i64) -> String {
+    format!("checkout:{id}")
+}
+
 pub mod graphql {
     use super::*;
+    use crate::domain::cart::cart_channel;
     use crate::graphql::Resolver;
+    use futures::{stream, StreamExt};
     use volo_gen::checkout::v1::{Payment, PutCheckout, Shipping};
 
     impl Resolver {
         pub fn create_get_checkout(&self) -> impl Query<i64, Result<Checkout>> + '_ {
             use crate::domain::checkout::query::get_checkout::execute;
 
-            move |id: i64| async move { execute(id, self.pg_conn()?.deref_mut()) }
+            traced("get_checkout", move |id: i64| {
+                self.pg_blocking(move |conn| execute(id, conn))
+            })
+        }
+
+        /// Changes of the cart are notified as well since they change the checkout.
+        pub fn create_subscribe_checkout(
+            &self,
+        ) -> impl Subscription<i64, Result<Checkout>, OutStream = BoxStream<'static, Result<Checkout>>>
+               + '_ {
+            use crate::domain::checkout::query::get_checkout::execute;
+
+            move |id: i64| async move {
+                let checkout = match self.pg_blocking(move |conn| execute(id, conn)).await {
+                    Ok(checkout) => checkout,
+                    Err(err) => return stream::once(async move { Err(err) }).boxed(),
+                };
+                let channels = vec![checkout_channel(id), cart_channel(checkout.cart.id)];
+                self.pg_subscribe(channels, move |_, conn| execute(id, conn))
+                    .await
+            }
         }
 
         pub fn create_get_checkout_by_cart_id(&self) -> impl Query<i64, Result<Checkout>> + '_ {
             use crate::domain::checkout::query::get_checkout_by_cart_id::execute;
 
-            move |cid: i64| async move { execute(cid, self.pg_conn()?.deref_mut()) }
+            traced("get_checkout_by_cart_id", move |cid: i64| {
+                self.pg_blocking(move |conn| execute(cid, conn))
+            })
         }
 
         pub fn create_list_shipping(&self) -> impl Query<(), Result<Vec<Shipping>>> + '_ {
             use crate::domain::checkout::query::list_shipping::execute;
 
-            move |_: ()| async move { execute(self.pg_conn()?.deref_mut()) }
+            traced("list_shipping", move |_: ()| self.pg_blocking(execute))
         }
 
         pub fn create_list_payment(&self) -> impl Query<(), Result<Vec<Payment>>> + '_ {
             use crate::domain::checkout::query::list_payments::execute;
 
-            move |_: ()| async move { execute(self.pg_conn()?.deref_mut()) }
+            traced("list_payment", move |_: ()| self.pg_blocking(execute))
+        }
+
+        pub fn create_load_shipping(
+            &self,
+        ) -> impl Query<Vec<i64>, Result<HashMap<i64, Shipping>>> + '_ {
+            use crate::domain::checkout::query::load_shipping::execute;
+
+            traced("load_shipping", move |ids: Vec<i64>| {
+                self.pg_blocking(move |conn| execute(ids, conn))
+            })
+        }
+
+        pub fn create_load_payment(
+            &self,
+        ) -> impl Query<Vec<i64>, Result<HashMap<i64, Payment>>> + '_ {
+            use crate::domain::checkout::query::load_payments::execute;
+
+            traced("load_payment", move |ids: Vec<i64>| {
+                self.pg_blocking(move |conn| execute(ids, conn))
+            })
         }
 
         pub fn create_create_checkout(&self) -> impl Mutation<i64, Result<Checkout>> + '_ {
             use crate::domain::checkout::mutation::create_checkout::execute;
 
-            move |cid: i64| async move { execute(cid, self.pg_conn()?.deref_mut()) }
+            traced("create_checkout", move |cid: i64| {
+                self.pg_blocking(move |conn| execute(cid, conn))
+            })
         }
 
         pub fn create_submit_information(
@@ -49,9 +104,14 @@ pub mod graphql {
         ) -> impl Mutation<(i64, PutCheckout), Result<Checkout>> + '_ {
             use crate::domain::checkout::mutation::submit_information::execute;
 
-            move |(id, put): (i64, PutCheckout)| async move {
-                execute(id, put, self.pg_conn()?.deref_mut())
-            }
+            traced(
+                "submit_information",
+                move |(id, put): (i64, PutCheckout)| async move {
+                    let checkout = self.pg_blocking(move |conn| execute(id, put, conn)).await?;
+                    self.publish(checkout_channel(checkout.id)).await;
+                    Ok(checkout)
+                },
+            )
         }
     }
 }
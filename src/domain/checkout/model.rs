@@ -1,12 +1,15 @@
+use crate::domain::address::model::{normalize_address, require_address, MailingAddressDomain};
 use crate::domain::cart::model::{CartDomain, QueryCart};
-use crate::infra::error::{PreconditionViolation, Result, Status};
+use crate::infra::error::{Code, PreconditionViolation, Result, Status};
+use crate::infra::validate::{self, Validator};
 use crate::schema::{t_checkouts, t_payment_methods, t_shipping_methods};
 use diesel::data_types::PgMoney;
 use diesel::prelude::*;
+use std::collections::HashMap;
 use std::default::Default;
 use std::ops::Deref;
-use volo_gen::checkout::v1::{Checkout, Payment, PutCheckout, Shipping};
-use volo_gen::common::v1::Money;
+use volo_gen::checkout::v1::{Checkout, CheckoutField, Payment, PutCheckout, Shipping};
+use volo_gen::common::v1::{MailingAddress, Money};
 
 #[derive(Queryable, Selectable, Associations, Identifiable, Debug)]
 #[diesel(belongs_to(QueryCart, foreign_key = cid))]
@@ -19,26 +22,25 @@ pub struct QueryCheckout {
     pub pid: Option<i64>,
     pub shipping_fee: Option<PgMoney>,
     pub email: Option<String>,
-    pub full_name: Option<String>,
-    pub country_code: Option<String>,
-    pub address: Option<String>,
-    pub postcode: Option<String>,
     pub phone: Option<String>,
+    pub shipping_aid: Option<i64>,
+    pub billing_aid: Option<i64>,
+    pub billing_same_as_shipping: bool,
 }
 
+/// `None` leaves the column unchanged, `Some(None)` clears it.
 #[derive(AsChangeset, Default)]
 #[diesel(table_name = t_checkouts)]
 pub struct MutateCheckout<'a> {
     pub status: Option<i32>,
-    pub sid: Option<i64>,
-    pub pid: Option<i64>,
-    pub shipping_fee: Option<PgMoney>,
-    pub email: Option<&'a str>,
-    pub full_name: Option<&'a str>,
-    pub country_code: Option<&'a str>,
-    pub address: Option<&'a str>,
-    pub postcode: Option<&'a str>,
-    pub phone: Option<&'a str>,
+    pub sid: Option<Option<i64>>,
+    pub pid: Option<Option<i64>>,
+    pub shipping_fee: Option<Option<PgMoney>>,
+    pub email: Option<Option<&'a str>>,
+    pub phone: Option<Option<&'a str>>,
+    pub shipping_aid: Option<Option<i64>>,
+    pub billing_aid: Option<Option<i64>>,
+    pub billing_same_as_shipping: Option<bool>,
 }
 
 #[derive(Insertable, Debug)]
@@ -79,6 +81,29 @@ impl PaymentDomain {
         }))
     }
 
+    pub(in crate::domain) fn query_many(
+        ids: impl IntoIterator<Item = i64>,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, Self>> {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        let res = t_payment_methods::table
+            .filter(t_payment_methods::id.eq_any(ids))
+            .select(QueryPayment::as_select())
+            .get_results(conn)?;
+        Ok(res
+            .into_iter()
+            .map(|v| {
+                (
+                    v.id,
+                    Self(Payment {
+                        id: v.id,
+                        vendor: v.vendor.into(),
+                    }),
+                )
+            })
+            .collect())
+    }
+
     pub(in crate::domain) fn list(conn: &mut PgConnection) -> Result<Vec<Self>> {
         let res = t_payment_methods::table
             .select(QueryPayment::as_select())
@@ -128,6 +153,29 @@ impl ShippingDomain {
         }))
     }
 
+    pub(in crate::domain) fn query_many(
+        ids: impl IntoIterator<Item = i64>,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, Self>> {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        let res = t_shipping_methods::table
+            .filter(t_shipping_methods::id.eq_any(ids))
+            .select(QueryShipping::as_select())
+            .get_results(conn)?;
+        Ok(res
+            .into_iter()
+            .map(|v| {
+                (
+                    v.id,
+                    Self(Shipping {
+                        id: v.id,
+                        vendor: v.vendor.into(),
+                    }),
+                )
+            })
+            .collect())
+    }
+
     pub(in crate::domain) fn list(conn: &mut PgConnection) -> Result<Vec<Self>> {
         let res = t_shipping_methods::table
             .select(QueryShipping::as_select())
@@ -145,14 +193,9 @@ impl ShippingDomain {
 
     pub(in crate::domain) fn calculate_fee(&self, checkout: &CheckoutDomain) -> Option<Money> {
         // todo calculate fee by shipping vendor defined
-        let _country_code = match checkout.0.receiver_country_code.as_deref() {
-            None => return None,
-            Some(code) => code,
-        };
-        let _postcode = match checkout.0.receiver_postcode.as_deref() {
-            None => return None,
-            Some(code) => code,
-        };
+        let address = checkout.0.shipping_address.as_ref()?;
+        let _country_code = address.country_code.as_deref()?;
+        let _zip = address.zip.as_deref()?;
         let _vendor = self.0.vendor.deref();
         debug_assert!(!checkout.0.cart.entries.is_empty());
         Some(Money {
@@ -192,32 +235,37 @@ impl CheckoutDomain {
             ]));
         }
         let currency_code = cart.entries[0].product.currency_code.clone();
-        let shipping = if let Some(sid) = checkout.sid {
-            Some(ShippingDomain::query(sid, conn)?.into_shipping())
+        let shipping_address = if let Some(aid) = checkout.shipping_aid {
+            Some(MailingAddressDomain::query(aid, conn)?.into_mailing_address())
         } else {
             None
         };
-        let payment = if let Some(pid) = checkout.pid {
-            Some(PaymentDomain::query(pid, conn)?.into_payment())
+        let billing_address = if checkout.billing_same_as_shipping {
+            shipping_address.clone()
+        } else if let Some(aid) = checkout.billing_aid {
+            Some(MailingAddressDomain::query(aid, conn)?.into_mailing_address())
         } else {
             None
         };
+        // shipping and payment are loaded on demand, see [ShippingDomain::query_many]
+        // and [PaymentDomain::query_many].
         Ok(CheckoutDomain(Checkout {
             id,
             cart,
             status: checkout.status,
-            shipping,
+            shipping: None,
+            shipping_id: checkout.sid,
+            payment_id: checkout.pid,
             shipping_fee: checkout.shipping_fee.map(|money| Money {
                 amount: money.0,
                 currency_code,
             }),
             contact_email: checkout.email.map(Into::into),
-            receiver_name: checkout.full_name.map(Into::into),
-            receiver_country_code: checkout.country_code.map(Into::into),
-            receiver_address: checkout.address.map(Into::into),
-            receiver_postcode: checkout.postcode.map(Into::into),
             receiver_phone: checkout.phone.map(Into::into),
-            payment,
+            shipping_address,
+            billing_address,
+            billing_same_as_shipping: checkout.billing_same_as_shipping,
+            payment: None,
         }))
     }
 
@@ -252,55 +300,220 @@ impl CheckoutDomain {
         Self::query(id, conn)
     }
 
+    /// Validate and normalize the submitted information, fields are named as
+    /// in the GraphQL input.
+    fn validate(mut put: PutCheckout) -> Result<PutCheckout> {
+        let mut v = Validator::new();
+        if let Some(email) = &put.contact_email {
+            let email = email.trim().to_string();
+            v.check("email", validate::is_email(&email), "not an email address");
+            put.contact_email = Some(email.into());
+        }
+        if let Some(address) = put.shipping_address.take() {
+            put.shipping_address = Some(normalize_address("shippingAddress", address, &mut v));
+        }
+        if let Some(address) = put.billing_address.take() {
+            v.check(
+                "billingSameAsShipping",
+                put.billing_same_as_shipping != Some(true),
+                "must not be true with a billing address",
+            );
+            put.billing_address = Some(normalize_address("billingAddress", address, &mut v));
+        }
+        if let Some(phone) = &put.receiver_phone {
+            match validate::normalize_phone(phone) {
+                Some(phone) => put.receiver_phone = Some(phone.into()),
+                None => v.check("phone", false, "not a phone number in international format"),
+            }
+        }
+        for field in put.clear.iter().flatten() {
+            let (name, set) = match field {
+                CheckoutField::Shipping => ("shippingId", put.shipping_id.is_some()),
+                CheckoutField::Payment => ("paymentId", put.payment_id.is_some()),
+                CheckoutField::ContactEmail => ("email", put.contact_email.is_some()),
+                CheckoutField::ReceiverPhone => ("phone", put.receiver_phone.is_some()),
+                CheckoutField::ShippingAddress => {
+                    ("shippingAddress", put.shipping_address.is_some())
+                }
+                CheckoutField::BillingAddress => ("billingAddress", put.billing_address.is_some()),
+            };
+            v.check(name, !set, "must not be set and cleared at the same time");
+        }
+        v.finish()?;
+        Ok(put)
+    }
+
+    /// The payment step requires a contact email and complete shipping and billing
+    /// addresses.
+    fn check_payable(&self) -> Result<()> {
+        let mut v = Validator::new();
+        v.check("email", self.0.contact_email.is_some(), "required");
+        let mut require = |field, address: &Option<MailingAddress>| match address {
+            Some(address) => require_address(field, address, &mut v),
+            None => v.check(field, false, "required"),
+        };
+        require("shippingAddress", &self.0.shipping_address);
+        if !self.0.billing_same_as_shipping {
+            require("billingAddress", &self.0.billing_address);
+        }
+        v.finish()
+    }
+
+    /// Replace an address of the checkout, returns the id of the address if it is
+    /// created at the first time.
+    fn put_address(
+        aid: Option<i64>,
+        address: &MailingAddress,
+        conn: &mut PgConnection,
+    ) -> Result<Option<i64>> {
+        if let Some(aid) = aid {
+            MailingAddressDomain::query(aid, conn)?.update(address.clone(), conn)?;
+            Ok(None)
+        } else {
+            Ok(Some(
+                MailingAddressDomain::create(None, address, conn)?.id(),
+            ))
+        }
+    }
+
+    /// Recalculate the shipping fee, it is cleared unless both the shipping method
+    /// and the shipping address are known.
+    pub(in crate::domain) fn update_shipping_fee(&mut self, conn: &mut PgConnection) -> Result<()> {
+        let fee = match self.0.shipping_id {
+            Some(sid) => ShippingDomain::query(sid, conn)?.calculate_fee(self),
+            None => None,
+        };
+        diesel::update(t_checkouts::table)
+            .filter(t_checkouts::id.eq(self.0.id))
+            .set(MutateCheckout {
+                shipping_fee: Some(fee.as_ref().map(|fee| PgMoney(fee.amount))),
+                ..Default::default()
+            })
+            .execute(conn)?;
+        self.0.shipping_fee = fee;
+        Ok(())
+    }
+
+    /// Recalculate the shipping fee of the checkout of a cart once the cart changes,
+    /// nothing is done if the cart has not been checked out.
+    pub(in crate::domain) fn update_shipping_fee_by_cart_id(
+        cid: i64,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        let id = t_checkouts::table
+            .filter(t_checkouts::cid.eq(cid))
+            .select(t_checkouts::id)
+            .first::<i64>(conn)
+            .optional()?;
+        let Some(id) = id else {
+            return Ok(());
+        };
+        match Self::query(id, conn) {
+            Ok(mut checkout) => checkout.update_shipping_fee(conn),
+            // nothing to ship in an empty cart
+            Err(e) if e.code() == Code::FailedPrecondition => {
+                diesel::update(t_checkouts::table)
+                    .filter(t_checkouts::id.eq(id))
+                    .set(MutateCheckout {
+                        shipping_fee: Some(None),
+                        ..Default::default()
+                    })
+                    .execute(conn)?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Apply a patch, fields absent are unchanged and fields in `put.clear` are
+    /// removed. The checkout is reloaded and its shipping fee is recalculated.
     pub(in crate::domain) fn submit_information(
         &mut self,
         put: PutCheckout,
         conn: &mut PgConnection,
     ) -> Result<()> {
+        let put = Self::validate(put)?;
+        // a separate billing address is dropped once the shipping one is used instead
+        let cleared = |field| {
+            put.clear.iter().flatten().any(|v| *v == field)
+                || (field == CheckoutField::BillingAddress
+                    && put.billing_same_as_shipping == Some(true))
+        };
         conn.transaction(|conn| {
+            if let Some(sid) = put.shipping_id {
+                ShippingDomain::query(sid, conn)?;
+            }
+            if let Some(pid) = put.payment_id {
+                PaymentDomain::query(pid, conn)?;
+            }
+            let (shipping_aid, billing_aid) = t_checkouts::table
+                .find(self.0.id)
+                .select((t_checkouts::shipping_aid, t_checkouts::billing_aid))
+                .get_result::<(Option<i64>, Option<i64>)>(conn)?;
+            let new_shipping_aid = match &put.shipping_address {
+                Some(address) => Self::put_address(shipping_aid, address, conn)?,
+                None => None,
+            };
+            let new_billing_aid = match &put.billing_address {
+                Some(address) => Self::put_address(billing_aid, address, conn)?,
+                None => None,
+            };
+            // submitting a billing address means it differs from the shipping one
+            let same_as_shipping = put
+                .billing_same_as_shipping
+                .or(put.billing_address.as_ref().map(|_| false));
             diesel::update(t_checkouts::table)
                 .filter(t_checkouts::id.eq(self.0.id))
-                .set(MutateCheckout {
-                    status: None,
-                    sid: put.shipping_id,
-                    pid: put.payment_id,
-                    shipping_fee: None,
-                    email: put.contact_email.as_deref(),
-                    full_name: put.receiver_name.as_deref(),
-                    country_code: put.receiver_country_code.as_deref(),
-                    address: put.receiver_address.as_deref(),
-                    postcode: put.receiver_postcode.as_deref(),
-                    phone: put.receiver_phone.as_deref(),
-                })
+                .set((
+                    MutateCheckout {
+                        status: None,
+                        sid: patch(put.shipping_id, cleared(CheckoutField::Shipping)),
+                        pid: patch(put.payment_id, cleared(CheckoutField::Payment)),
+                        shipping_fee: None,
+                        email: patch(
+                            put.contact_email.as_deref(),
+                            cleared(CheckoutField::ContactEmail),
+                        ),
+                        phone: patch(
+                            put.receiver_phone.as_deref(),
+                            cleared(CheckoutField::ReceiverPhone),
+                        ),
+                        shipping_aid: patch(
+                            new_shipping_aid,
+                            cleared(CheckoutField::ShippingAddress),
+                        ),
+                        billing_aid: patch(new_billing_aid, cleared(CheckoutField::BillingAddress)),
+                        billing_same_as_shipping: same_as_shipping,
+                    },
+                    // the changeset is never empty, e.g. only the address is submitted
+                    t_checkouts::updated_at.eq(diesel::dsl::now),
+                ))
                 .execute(conn)?;
-            let shipping = if let Some(sid) = put.shipping_id {
-                let shipping = ShippingDomain::query(sid, conn)?;
-                self.0.shipping = Some(shipping.clone().into_shipping());
-                Some(shipping)
-            } else {
-                None
-            };
-            if let Some(pid) = put.payment_id {
-                self.0.payment = Some(PaymentDomain::query(pid, conn)?.into_payment());
-            }
-            self.0.contact_email = put.contact_email;
-            self.0.receiver_name = put.receiver_name;
-            self.0.receiver_address = put.receiver_address;
-            self.0.receiver_phone = put.receiver_phone;
-            if let Some(shipping) = shipping {
-                let fee = shipping.calculate_fee(self);
-                if let Some(fee) = fee {
-                    diesel::update(t_checkouts::table)
-                        .filter(t_checkouts::id.eq(self.0.id))
-                        .set(MutateCheckout {
-                            shipping_fee: Some(PgMoney(fee.amount)),
-                            ..Default::default()
-                        })
-                        .execute(conn)?;
-                    self.0.shipping_fee = Some(fee)
+            // addresses are owned by the checkout, they are removed once cleared
+            let addresses = [
+                (CheckoutField::ShippingAddress, shipping_aid),
+                (CheckoutField::BillingAddress, billing_aid),
+            ];
+            for (field, aid) in addresses {
+                if let (true, Some(aid)) = (cleared(field), aid) {
+                    MailingAddressDomain::query(aid, conn)?.delete(conn)?;
                 }
             }
+            *self = Self::query(self.0.id, conn)?;
+            self.update_shipping_fee(conn)?;
+            if self.0.payment_id.is_some() {
+                self.check_payable()?;
+            }
             Ok(())
         })
     }
 }
+
+/// Patch of a nullable column, `None` leaves it unchanged and `Some(None)` clears it.
+fn patch<T>(value: Option<T>, cleared: bool) -> Option<Option<T>> {
+    if cleared {
+        Some(None)
+    } else {
+        value.map(Some)
+    }
+}
@@ -0,0 +1,15 @@
+use crate::domain::checkout::model::PaymentDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use std::collections::HashMap;
+use volo_gen::checkout::v1::Payment;
+
+pub(in crate::domain) fn execute(
+    ids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i64, Payment>> {
+    Ok(PaymentDomain::query_many(ids, conn)?
+        .into_iter()
+        .map(|(id, v)| (id, v.into_payment()))
+        .collect())
+}
@@ -0,0 +1,15 @@
+use crate::domain::checkout::model::ShippingDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use std::collections::HashMap;
+use volo_gen::checkout::v1::Shipping;
+
+pub(in crate::domain) fn execute(
+    ids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i64, Shipping>> {
+    Ok(ShippingDomain::query_many(ids, conn)?
+        .into_iter()
+        .map(|(id, v)| (id, v.into_shipping()))
+        .collect())
+}
@@ -2,3 +2,5 @@ pub mod get_checkout;
 pub mod get_checkout_by_cart_id;
 pub mod list_payments;
 pub mod list_shipping;
+pub mod load_payments;
+pub mod load_shipping;
@@ -0,0 +1,93 @@
+pub mod model;
+pub mod mutation;
+pub mod query;
+
+use crate::infra::error::Result;
+use crate::infra::mqsrs::traced;
+use crate::infra::mqsrs::Mutation;
+use crate::infra::mqsrs::Query;
+use volo_gen::collection::v1::{
+    Collection, CollectionConnection, CollectionProductConnection, NewCollection,
+};
+use volo_gen::common::v1::PaginationOption;
+
+pub mod graphql {
+    use super::*;
+    use crate::graphql::Resolver;
+
+    impl Resolver {
+        pub fn create_get_collection(&self) -> impl Query<String, Result<Collection>> + '_ {
+            use crate::domain::collection::query::get_collection::execute;
+
+            traced("get_collection", move |handle: String| {
+                self.pg_blocking(move |conn| execute(handle, conn))
+            })
+        }
+
+        pub fn create_list_collection(
+            &self,
+        ) -> impl Query<PaginationOption, Result<CollectionConnection>> + '_ {
+            use crate::domain::collection::query::list_collections::execute;
+
+            traced("list_collection", move |req: PaginationOption| {
+                self.pg_blocking(move |conn| execute(req, conn))
+            })
+        }
+
+        pub fn create_list_collection_products(
+            &self,
+        ) -> impl Query<(i64, PaginationOption), Result<CollectionProductConnection>> + '_ {
+            use crate::domain::collection::query::list_collection_products::execute;
+
+            traced(
+                "list_collection_products",
+                move |(id, req): (i64, PaginationOption)| {
+                    self.pg_blocking(move |conn| execute(id, req, conn))
+                },
+            )
+        }
+
+        pub fn create_create_collection(
+            &self,
+        ) -> impl Mutation<NewCollection, Result<Collection>> + '_ {
+            use crate::domain::collection::mutation::create_collection::execute;
+
+            traced("create_collection", move |req: NewCollection| {
+                self.pg_blocking(move |conn| execute(req, conn))
+            })
+        }
+
+        pub fn create_add_to_collection(
+            &self,
+        ) -> impl Mutation<(i64, Vec<i64>), Result<Collection>> + '_ {
+            use crate::domain::collection::mutation::add_to_collection::execute;
+
+            traced("add_to_collection", move |(id, pids): (i64, Vec<i64>)| {
+                self.pg_blocking(move |conn| execute(id, pids, conn))
+            })
+        }
+
+        pub fn create_remove_from_collection(
+            &self,
+        ) -> impl Mutation<(i64, Vec<i64>), Result<Collection>> + '_ {
+            use crate::domain::collection::mutation::remove_from_collection::execute;
+
+            traced(
+                "remove_from_collection",
+                move |(id, pids): (i64, Vec<i64>)| {
+                    self.pg_blocking(move |conn| execute(id, pids, conn))
+                },
+            )
+        }
+
+        pub fn create_reorder_collection(
+            &self,
+        ) -> impl Mutation<(i64, Vec<i64>), Result<Collection>> + '_ {
+            use crate::domain::collection::mutation::reorder_collection::execute;
+
+            traced("reorder_collection", move |(id, pids): (i64, Vec<i64>)| {
+                self.pg_blocking(move |conn| execute(id, pids, conn))
+            })
+        }
+    }
+}
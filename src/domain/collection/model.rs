@@ -0,0 +1,338 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::{PreconditionViolation, Range, Result, Status};
+use crate::schema::{t_collection_products, t_collections, t_products};
+use diesel::prelude::*;
+use volo_gen::collection::v1::{
+    Collection, CollectionConnection, CollectionProduct, CollectionProductConnection,
+};
+use volo_gen::common::v1::PaginationOption;
+
+const MAX_DATA_LEN: i64 = 100;
+
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = t_collections)]
+pub struct QueryCollection {
+    pub id: i64,
+    pub handle: String,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_collections)]
+pub struct NewCollection<'a> {
+    pub handle: &'a str,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+}
+
+#[derive(Queryable, Selectable, Associations, Identifiable, Debug)]
+#[diesel(belongs_to(QueryCollection, foreign_key = cid))]
+#[diesel(table_name = t_collection_products)]
+pub struct QueryCollectionProduct {
+    pub id: i64,
+    pub cid: i64,
+    pub pid: i64,
+    pub order_idx: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = t_collection_products)]
+pub struct NewCollectionProduct {
+    pub cid: i64,
+    pub pid: i64,
+    pub order_idx: i32,
+}
+
+pub struct CollectionDomain(Collection);
+
+impl From<QueryCollection> for CollectionDomain {
+    fn from(value: QueryCollection) -> Self {
+        CollectionDomain(Collection {
+            id: value.id,
+            handle: value.handle.into(),
+            title: value.title.into(),
+            description: value.description.into(),
+        })
+    }
+}
+
+impl CollectionDomain {
+    pub(in crate::domain) fn into_collection(self) -> Collection {
+        self.0
+    }
+
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query(id: i64, conn: &mut PgConnection) -> Result<Self> {
+        let collection = t_collections::table
+            .find(id)
+            .select(QueryCollection::as_select())
+            .get_result(conn)
+            .map_err(|e| {
+                if matches!(e, diesel::NotFound) {
+                    Status::not_found(format!("collection({})", id))
+                } else {
+                    Status::internal()
+                }
+            })?;
+        Ok(collection.into())
+    }
+
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query_by_handle(
+        handle: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Self> {
+        let collection = t_collections::table
+            .filter(t_collections::handle.eq(handle))
+            .select(QueryCollection::as_select())
+            .get_result(conn)
+            .map_err(|e| {
+                if matches!(e, diesel::NotFound) {
+                    Status::not_found(format!("collection(handle: {})", handle))
+                } else {
+                    Status::internal()
+                }
+            })?;
+        Ok(collection.into())
+    }
+
+    /// List collections, cursor is the id of collection, same as [ProductDomain::list].
+    /// Status maybe returned:
+    /// 1. out_of_range
+    /// 2. internal
+    pub(in crate::domain) fn list(
+        option: PaginationOption,
+        conn: &mut PgConnection,
+    ) -> Result<CollectionConnection> {
+        let mut start = 0;
+        let mut end = MAX_DATA_LEN;
+        if let Some(after) = option.after {
+            let total = t_collections::table.count().get_result::<i64>(conn)?;
+            if after >= total {
+                return Err(Status::out_of_range("after", Range::Continuous(0, total)));
+            }
+            start = after + 1;
+            end = start + MAX_DATA_LEN;
+        };
+        if let Some(before) = option.before {
+            if before <= 0 {
+                return Err(Status::out_of_range("before", Range::StartAt(start)));
+            }
+            end = before;
+        };
+        let mut collections = t_collections::table
+            .filter(t_collections::id.between(start, end))
+            .order(t_collections::id.asc())
+            .select(QueryCollection::as_select())
+            .load(conn)?
+            .into_iter()
+            .map(|v| CollectionDomain::from(v).0)
+            .collect::<Vec<_>>();
+        let mut has_previous_page = false;
+        let mut has_next_page = false;
+        if let Some(first) = option.first.map(|v| v as usize) {
+            if first < collections.len() {
+                collections.truncate(first);
+                has_next_page = true;
+            }
+        } else if let Some(last) = option.last.map(|v| v as usize) {
+            if last < collections.len() {
+                collections = collections.drain((collections.len() - last)..).collect();
+                has_previous_page = true;
+            }
+        }
+        Ok(CollectionConnection {
+            collections,
+            has_previous_page,
+            has_next_page,
+        })
+    }
+
+    /// List products in this collection by manual ordering, the cursor is `order_idx`
+    /// of the product in this collection. At most `MAX_DATA_LEN` lines will be queried.
+    /// Status maybe returned:
+    /// 1. out_of_range
+    /// 2. internal
+    pub(in crate::domain) fn list_products(
+        &self,
+        option: PaginationOption,
+        conn: &mut PgConnection,
+    ) -> Result<CollectionProductConnection> {
+        let mut query = t_collection_products::table
+            .filter(t_collection_products::cid.eq(self.0.id))
+            .select(QueryCollectionProduct::as_select())
+            .into_boxed();
+        if let Some(after) = option.after {
+            let after = i32::try_from(after)
+                .map_err(|_| Status::out_of_range("after", Range::EndAt(i32::MAX)))?;
+            query = query.filter(t_collection_products::order_idx.gt(after));
+        }
+        if let Some(before) = option.before {
+            if before <= 0 {
+                return Err(Status::out_of_range("before", Range::StartAt(1)));
+            }
+            let before = i32::try_from(before)
+                .map_err(|_| Status::out_of_range("before", Range::EndAt(i32::MAX)))?;
+            query = query.filter(t_collection_products::order_idx.lt(before));
+        }
+        let entries: Vec<QueryCollectionProduct> = query
+            .order(t_collection_products::order_idx.asc())
+            .limit(MAX_DATA_LEN)
+            .load(conn)?;
+        let mut products = ProductDomain::query_many(entries.iter().map(|v| v.pid), conn)?;
+        let mut products = entries
+            .into_iter()
+            .filter_map(|v| {
+                products.remove(&v.pid).map(|product| CollectionProduct {
+                    order_idx: v.order_idx,
+                    product: product.into_product(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut has_previous_page = false;
+        let mut has_next_page = false;
+        if let Some(first) = option.first.map(|v| v as usize) {
+            if first < products.len() {
+                products.truncate(first);
+                has_next_page = true;
+            }
+        } else if let Some(last) = option.last.map(|v| v as usize) {
+            if last < products.len() {
+                products = products.drain((products.len() - last)..).collect();
+                has_previous_page = true;
+            }
+        }
+        Ok(CollectionProductConnection {
+            products,
+            has_previous_page,
+            has_next_page,
+        })
+    }
+
+    /// Status maybe returned:
+    /// 1. already_exists
+    /// 2. internal
+    pub(in crate::domain) fn create(new: NewCollection, conn: &mut PgConnection) -> Result<Self> {
+        if t_collections::table
+            .filter(t_collections::handle.eq(new.handle))
+            .select(t_collections::id)
+            .first::<i64>(conn)
+            .is_ok()
+        {
+            return Err(Status::already_exists(format!(
+                "collection(handle: {})",
+                new.handle
+            )));
+        }
+        let collection = diesel::insert_into(t_collections::table)
+            .values(&new)
+            .returning(QueryCollection::as_returning())
+            .get_result(conn)?;
+        Ok(collection.into())
+    }
+
+    /// Append products to the end of this collection, products that are already
+    /// in this collection will be ignored.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn add_products(
+        &self,
+        pids: Vec<i64>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        conn.transaction(|conn| {
+            let found: Vec<i64> = t_products::table
+                .filter(t_products::id.eq_any(&pids))
+                .select(t_products::id)
+                .load(conn)?;
+            if let Some(pid) = pids.iter().find(|pid| !found.contains(pid)) {
+                return Err(Status::not_found(format!("product({})", pid)));
+            }
+            let members: Vec<i64> = t_collection_products::table
+                .filter(t_collection_products::cid.eq(self.0.id))
+                .select(t_collection_products::pid)
+                .load(conn)?;
+            let next_idx = t_collection_products::table
+                .filter(t_collection_products::cid.eq(self.0.id))
+                .select(diesel::dsl::max(t_collection_products::order_idx))
+                .get_result::<Option<i32>>(conn)?
+                .map_or(0, |v| v + 1);
+            let mut news: Vec<NewCollectionProduct> = vec![];
+            for pid in pids {
+                if members.contains(&pid) || news.iter().any(|v| v.pid == pid) {
+                    continue;
+                }
+                news.push(NewCollectionProduct {
+                    cid: self.0.id,
+                    pid,
+                    order_idx: next_idx + news.len() as i32,
+                });
+            }
+            diesel::insert_into(t_collection_products::table)
+                .values(&news)
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Remove products from this collection, the order of remaining products is kept.
+    pub(in crate::domain) fn remove_products(
+        &self,
+        pids: Vec<i64>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::delete(t_collection_products::table)
+            .filter(t_collection_products::cid.eq(self.0.id))
+            .filter(t_collection_products::pid.eq_any(pids))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Move the given products to the front of this collection in the given order,
+    /// the other products follow them in their previous order.
+    /// Status maybe returned:
+    /// 1. failed_precondition
+    /// 2. internal
+    pub(in crate::domain) fn reorder_products(
+        &self,
+        pids: Vec<i64>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        conn.transaction(|conn| {
+            let members: Vec<i64> = t_collection_products::table
+                .filter(t_collection_products::cid.eq(self.0.id))
+                .order(t_collection_products::order_idx.asc())
+                .select(t_collection_products::pid)
+                .load(conn)?;
+            if let Some(pid) = pids.iter().find(|pid| !members.contains(pid)) {
+                return Err(Status::failed_precondition().with_precondition(vec![
+                    PreconditionViolation {
+                        r#type: "logic".to_string(),
+                        subject: "nintendo-shop/collection".to_string(),
+                        description: format!(
+                            "Product({}) is not in collection({})",
+                            pid, self.0.id
+                        ),
+                    },
+                ]));
+            }
+            let ordered = pids
+                .iter()
+                .chain(members.iter().filter(|pid| !pids.contains(pid)));
+            for (idx, pid) in ordered.enumerate() {
+                diesel::update(t_collection_products::table)
+                    .filter(t_collection_products::cid.eq(self.0.id))
+                    .filter(t_collection_products::pid.eq(pid))
+                    .set(t_collection_products::order_idx.eq(idx as i32))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+}
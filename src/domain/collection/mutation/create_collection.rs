@@ -0,0 +1,21 @@
+use crate::domain::collection::model::{CollectionDomain, NewCollection as InsertCollection};
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::collection::v1::{Collection, NewCollection};
+
+pub(in crate::domain) fn execute(
+    new: NewCollection,
+    conn: &mut PgConnection,
+) -> Result<Collection> {
+    conn.transaction(|conn| {
+        let collection = CollectionDomain::create(
+            InsertCollection {
+                handle: &new.handle,
+                title: &new.title,
+                description: new.description.as_deref(),
+            },
+            conn,
+        )?;
+        Ok(collection.into_collection())
+    })
+}
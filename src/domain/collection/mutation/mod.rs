@@ -0,0 +1,4 @@
+pub mod add_to_collection;
+pub mod create_collection;
+pub mod remove_from_collection;
+pub mod reorder_collection;
@@ -0,0 +1,16 @@
+use crate::domain::collection::model::CollectionDomain;
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::collection::v1::Collection;
+
+pub(in crate::domain) fn execute(
+    id: i64,
+    pids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<Collection> {
+    conn.transaction(|conn| {
+        let collection = CollectionDomain::query(id, conn)?;
+        collection.reorder_products(pids, conn)?;
+        Ok(collection.into_collection())
+    })
+}
@@ -0,0 +1,8 @@
+use crate::domain::collection::model::CollectionDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::collection::v1::Collection;
+
+pub(in crate::domain) fn execute(handle: String, conn: &mut PgConnection) -> Result<Collection> {
+    CollectionDomain::query_by_handle(&handle, conn).map(|v| v.into_collection())
+}
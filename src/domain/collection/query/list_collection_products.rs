@@ -0,0 +1,13 @@
+use crate::domain::collection::model::CollectionDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::collection::v1::CollectionProductConnection;
+use volo_gen::common::v1::PaginationOption;
+
+pub(in crate::domain) fn execute(
+    id: i64,
+    option: PaginationOption,
+    conn: &mut PgConnection,
+) -> Result<CollectionProductConnection> {
+    CollectionDomain::query(id, conn)?.list_products(option, conn)
+}
@@ -0,0 +1,12 @@
+use crate::domain::collection::model::CollectionDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::collection::v1::CollectionConnection;
+use volo_gen::common::v1::PaginationOption;
+
+pub(in crate::domain) fn execute(
+    option: PaginationOption,
+    conn: &mut PgConnection,
+) -> Result<CollectionConnection> {
+    CollectionDomain::list(option, conn)
+}
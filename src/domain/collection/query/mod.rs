@@ -0,0 +1,3 @@
+pub mod get_collection;
+pub mod list_collection_products;
+pub mod list_collections;
@@ -1,3 +1,5 @@
+pub mod address;
 pub mod cart;
 pub mod checkout;
+pub mod collection;
 pub mod product;
@@ -3,21 +3,283 @@ pub mod mutation;
 pub mod query;
 
 use crate::infra::error::*;
-use crate::infra::mqsrs::Query;
-use std::ops::DerefMut;
-use volo_gen::common::v1::PaginationOption;
-use volo_gen::product::v1::Product;
-use volo_gen::product::v1::ProductConnection;
+use crate::infra::mqsrs::{traced, Mutation, Query, Subscription};
+use futures::stream::BoxStream;
+use std::collections::HashMap;
+use volo_gen::common::v1::{Image, PaginationOption};
+use volo_gen::product::v1::{
+    CatalogAction, CatalogChange, CatalogPage, CatalogProduct, ProductConnection,
+};
+use volo_gen::product::v1::{NewProduct, Product, ProductOption, ProductVariant, SelectedOption};
+
+/// The channel notified when the inventory of the variant changes.
+pub fn variant_inventory_channel(id: i64) -> String {
+    format!("variant:{id}:inventory")
+}
+
+/// The namespace of the cached pages of the catalog, bumped by the mutations.
+const CATALOG: &str = "catalog";
+
+fn product_key(id: i64) -> String {
+    format!("product:{id}")
+}
 
 pub mod graphql {
     use super::*;
     use crate::graphql::Resolver;
+    use crate::infra::resolver::BaseResolver;
+
+    impl Resolver {
+        pub fn create_get_product(&self) -> impl Query<i64, Result<Product>> + '_ {
+            use crate::domain::product::query::get_product::execute;
+
+            traced("get_product", move |req: i64| {
+                let ttl = self.resolve(&self.product_ttl);
+                self.pg_cached(
+                    ttl,
+                    move |_| product_key(req),
+                    move |conn| execute(req, conn),
+                )
+            })
+        }
+
+        pub fn create_get_product_by_handle(&self) -> impl Query<String, Result<Product>> + '_ {
+            use crate::domain::product::query::get_product_by_handle::execute;
+
+            traced("get_product_by_handle", move |handle: String| {
+                self.pg_blocking(move |conn| execute(handle, conn))
+            })
+        }
+
+        /// The variants are loaded from the database, not the cache, to get the latest
+        /// inventory.
+        pub fn create_subscribe_variant_inventory(
+            &self,
+        ) -> impl Subscription<
+            Vec<i64>,
+            Result<ProductVariant>,
+            OutStream = BoxStream<'static, Result<ProductVariant>>,
+        > + '_ {
+            use crate::domain::product::query::get_variant::execute;
+
+            move |ids: Vec<i64>| {
+                let channels = ids
+                    .into_iter()
+                    .map(|id| (variant_inventory_channel(id), id))
+                    .collect::<HashMap<_, _>>();
+                self.pg_subscribe(channels.keys().cloned().collect(), move |channel, conn| {
+                    execute(channels[channel], conn)
+                })
+            }
+        }
+
+        pub fn create_load_products(
+            &self,
+        ) -> impl Query<Vec<i64>, Result<HashMap<i64, Product>>> + '_ {
+            use crate::domain::product::query::load_products::execute;
+
+            traced("load_products", move |ids: Vec<i64>| {
+                let ttl = self.resolve(&self.product_ttl);
+                self.pg_cached_many(ttl, ids, |id| product_key(*id), execute)
+            })
+        }
+
+        pub fn create_load_product_images(
+            &self,
+        ) -> impl Query<Vec<i64>, Result<HashMap<i64, Vec<Image>>>> + '_ {
+            use crate::domain::product::query::load_images::execute;
+
+            traced("load_product_images", move |pids: Vec<i64>| {
+                self.pg_blocking(move |conn| execute(pids, conn))
+            })
+        }
+
+        #[allow(clippy::type_complexity)]
+        pub fn create_load_product_variants(
+            &self,
+        ) -> impl Query<Vec<i64>, Result<HashMap<i64, (Vec<ProductOption>, Vec<ProductVariant>)>>> + '_
+        {
+            use crate::domain::product::query::load_variants::execute;
+
+            traced("load_product_variants", move |pids: Vec<i64>| {
+                self.pg_blocking(move |conn| execute(pids, conn))
+            })
+        }
+
+        pub fn create_list_product(
+            &self,
+        ) -> impl Query<PaginationOption, Result<ProductConnection>> + '_ {
+            use crate::domain::product::query::list_products::execute;
+
+            traced("list_product", move |req: PaginationOption| {
+                let ttl = self.resolve(&self.catalog_ttl);
+                let page = page_key(&req);
+                self.pg_cached(
+                    ttl,
+                    move |cache| format!("{CATALOG}:{}:{page}", cache.version(CATALOG)),
+                    move |conn| execute(req, conn),
+                )
+            })
+        }
+
+        pub fn create_create_product(&self) -> impl Mutation<NewProduct, Result<Product>> + '_ {
+            use crate::domain::product::mutation::create_product::execute;
+
+            traced("create_product", move |req: NewProduct| async move {
+                let product = self.pg_blocking(move |conn| execute(req, conn)).await?;
+                self.evict_product(product.id).await;
+                Ok(product)
+            })
+        }
+
+        pub fn create_change_product_handle(
+            &self,
+        ) -> impl Mutation<(i64, String), Result<Product>> + '_ {
+            use crate::domain::product::mutation::change_product_handle::execute;
+
+            traced(
+                "change_product_handle",
+                move |(id, handle): (i64, String)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(id, handle, conn))
+                        .await?;
+                    self.evict_product(product.id).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        pub fn create_create_product_option(
+            &self,
+        ) -> impl Mutation<(i64, String, Vec<String>), Result<Product>> + '_ {
+            use crate::domain::product::mutation::create_product_option::execute;
+
+            traced(
+                "create_product_option",
+                move |(id, name, values): (i64, String, Vec<String>)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(id, name, values, conn))
+                        .await?;
+                    self.evict_product(product.id).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        pub fn create_set_variant_options(
+            &self,
+        ) -> impl Mutation<(i64, Vec<SelectedOption>), Result<Product>> + '_ {
+            use crate::domain::product::mutation::set_variant_options::execute;
+
+            traced(
+                "set_variant_options",
+                move |(variant_id, selected): (i64, Vec<SelectedOption>)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(variant_id, selected, conn))
+                        .await?;
+                    self.evict_product(product.id).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        /// Products are not read through the cache, a page is exported as it is stored.
+        pub fn create_export_catalog(
+            &self,
+        ) -> impl Query<(Option<i64>, bool), Result<CatalogPage>> + '_ {
+            use crate::domain::product::query::export_catalog::execute;
+
+            traced(
+                "export_catalog",
+                move |(cursor, inventory): (Option<i64>, bool)| {
+                    self.pg_blocking(move |conn| execute(cursor, inventory, conn))
+                },
+            )
+        }
+
+        pub fn create_import_catalog(
+            &self,
+        ) -> impl Mutation<(Vec<CatalogProduct>, bool), Result<Vec<CatalogChange>>> + '_ {
+            use crate::domain::product::mutation::import_catalog::execute;
+
+            traced(
+                "import_catalog",
+                move |(products, dry_run): (Vec<CatalogProduct>, bool)| async move {
+                    let (changes, inventory) = self
+                        .pg_blocking(move |conn| execute(products, dry_run, conn))
+                        .await?;
+                    for id in inventory {
+                        self.publish(variant_inventory_channel(id)).await;
+                    }
+                    if !dry_run {
+                        let ids = changes
+                            .iter()
+                            .filter(|v| v.action != CatalogAction::Unchanged)
+                            .map(|v| v.product_id)
+                            .collect::<Vec<_>>();
+                        if !ids.is_empty() {
+                            let _ = self
+                                .cache_blocking(move |cache| {
+                                    for id in ids {
+                                        cache.evict(&product_key(id));
+                                    }
+                                    cache.bump(CATALOG);
+                                    Ok(())
+                                })
+                                .await;
+                        }
+                    }
+                    Ok(changes)
+                },
+            )
+        }
+
+        /// Drop the cached product and catalog once a mutation of the product has
+        /// been committed.
+        async fn evict_product(&self, id: i64) {
+            let _ = self
+                .cache_blocking(move |cache| {
+                    cache.evict(&product_key(id));
+                    cache.bump(CATALOG);
+                    Ok(())
+                })
+                .await;
+        }
+    }
+
+    fn page_key(option: &PaginationOption) -> String {
+        format!(
+            "{:?}:{:?}:{:?}:{:?}:{}",
+            option.after,
+            option.before,
+            option.first,
+            option.last,
+            option.order_by.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// The products are read from the database, not the cache of the graphql service,
+/// the mutations evict it all the same.
+pub mod rpc {
+    use super::*;
+    use crate::rpc::Resolver;
 
     impl Resolver {
         pub fn create_get_product(&self) -> impl Query<i64, Result<Product>> + '_ {
             use crate::domain::product::query::get_product::execute;
 
-            move |req: i64| async move { execute(req, self.pg_conn()?.deref_mut()) }
+            traced("get_product", move |id: i64| {
+                self.pg_blocking(move |conn| execute(id, conn))
+            })
+        }
+
+        pub fn create_get_product_by_handle(&self) -> impl Query<String, Result<Product>> + '_ {
+            use crate::domain::product::query::get_product_by_handle::execute;
+
+            traced("get_product_by_handle", move |handle: String| {
+                self.pg_blocking(move |conn| execute(handle, conn))
+            })
         }
 
         pub fn create_list_product(
@@ -25,7 +287,124 @@ pub mod graphql {
         ) -> impl Query<PaginationOption, Result<ProductConnection>> + '_ {
             use crate::domain::product::query::list_products::execute;
 
-            move |req: PaginationOption| async move { execute(req, self.pg_conn()?.deref_mut()) }
+            traced("list_product", move |req: PaginationOption| {
+                self.pg_blocking(move |conn| execute(req, conn))
+            })
+        }
+
+        pub fn create_create_product(&self) -> impl Mutation<NewProduct, Result<Product>> + '_ {
+            use crate::domain::product::mutation::create_product::execute;
+
+            traced("create_product", move |req: NewProduct| async move {
+                let product = self.pg_blocking(move |conn| execute(req, conn)).await?;
+                self.evict_products(vec![product.id]).await;
+                Ok(product)
+            })
+        }
+
+        pub fn create_change_product_handle(
+            &self,
+        ) -> impl Mutation<(i64, String), Result<Product>> + '_ {
+            use crate::domain::product::mutation::change_product_handle::execute;
+
+            traced(
+                "change_product_handle",
+                move |(id, handle): (i64, String)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(id, handle, conn))
+                        .await?;
+                    self.evict_products(vec![product.id]).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        pub fn create_create_product_option(
+            &self,
+        ) -> impl Mutation<(i64, String, Vec<String>), Result<Product>> + '_ {
+            use crate::domain::product::mutation::create_product_option::execute;
+
+            traced(
+                "create_product_option",
+                move |(id, name, values): (i64, String, Vec<String>)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(id, name, values, conn))
+                        .await?;
+                    self.evict_products(vec![product.id]).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        pub fn create_set_variant_options(
+            &self,
+        ) -> impl Mutation<(i64, Vec<SelectedOption>), Result<Product>> + '_ {
+            use crate::domain::product::mutation::set_variant_options::execute;
+
+            traced(
+                "set_variant_options",
+                move |(variant_id, selected): (i64, Vec<SelectedOption>)| async move {
+                    let product = self
+                        .pg_blocking(move |conn| execute(variant_id, selected, conn))
+                        .await?;
+                    self.evict_products(vec![product.id]).await;
+                    Ok(product)
+                },
+            )
+        }
+
+        pub fn create_export_catalog(
+            &self,
+        ) -> impl Query<(Option<i64>, bool), Result<CatalogPage>> + '_ {
+            use crate::domain::product::query::export_catalog::execute;
+
+            traced(
+                "export_catalog",
+                move |(cursor, inventory): (Option<i64>, bool)| {
+                    self.pg_blocking(move |conn| execute(cursor, inventory, conn))
+                },
+            )
+        }
+
+        pub fn create_import_catalog(
+            &self,
+        ) -> impl Mutation<(Vec<CatalogProduct>, bool), Result<Vec<CatalogChange>>> + '_ {
+            use crate::domain::product::mutation::import_catalog::execute;
+
+            traced(
+                "import_catalog",
+                move |(products, dry_run): (Vec<CatalogProduct>, bool)| async move {
+                    let (changes, inventory) = self
+                        .pg_blocking(move |conn| execute(products, dry_run, conn))
+                        .await?;
+                    for id in inventory {
+                        self.publish(variant_inventory_channel(id)).await;
+                    }
+                    let ids = changes
+                        .iter()
+                        .filter(|v| v.action != CatalogAction::Unchanged)
+                        .map(|v| v.product_id)
+                        .collect::<Vec<_>>();
+                    if !dry_run && !ids.is_empty() {
+                        self.evict_products(ids).await;
+                    }
+                    Ok(changes)
+                },
+            )
+        }
+
+        /// Drop the products and the catalog cached by the graphql service once a
+        /// mutation has been committed.
+        async fn evict_products(&self, ids: Vec<i64>) {
+            let _ = self
+                .cache_blocking(move |cache| {
+                    for id in ids {
+                        cache.evict(&product_key(id));
+                    }
+                    cache.bump(CATALOG);
+                    Ok(())
+                })
+                .await;
         }
     }
 }
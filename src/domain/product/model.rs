@@ -1,10 +1,18 @@
-use crate::infra::error::Status;
+use crate::infra::error::{PreconditionViolation, Status};
 use crate::infra::error::{Range, Result};
-use crate::schema::{t_product_images, t_product_variants, t_products};
+use crate::schema::{
+    t_cart_entries, t_product_images, t_product_option_values, t_product_options,
+    t_product_redirects, t_product_variant_options, t_product_variants, t_products,
+};
 use diesel::data_types::PgMoney;
 use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use std::collections::{HashMap, HashSet};
 use volo_gen::common::v1::{Image, Money, PaginationOption};
-use volo_gen::product::v1::{Product, ProductConnection, ProductVariant};
+use volo_gen::product::v1::{
+    CatalogImage, CatalogPage, CatalogProduct, CatalogVariant, Product, ProductConnection,
+    ProductOption, ProductVariant, SelectedOption,
+};
 
 const MAX_DATA_LEN: i64 = 100;
 
@@ -16,6 +24,7 @@ pub struct QueryProduct {
     pub sub_title: String,
     pub description: String,
     pub currency_code: String,
+    pub handle: String,
 }
 
 #[derive(Insertable)]
@@ -25,6 +34,8 @@ pub struct NewProduct<'a> {
     pub sub_title: Option<&'a str>,
     pub description: Option<&'a str>,
     pub currency_code: &'a str,
+    pub handle: &'a str,
+    pub external_key: Option<&'a str>,
 }
 
 #[derive(AsChangeset)]
@@ -34,6 +45,15 @@ pub struct MutateProduct<'a> {
     pub sub_title: Option<&'a str>,
     pub description: Option<&'a str>,
     pub currency_code: Option<&'a str>,
+    pub handle: Option<&'a str>,
+    pub external_key: Option<&'a str>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_product_redirects)]
+pub struct NewProductRedirect<'a> {
+    pub handle: &'a str,
+    pub pid: i64,
 }
 
 #[derive(Queryable, Selectable, Associations, Identifiable, Debug)]
@@ -59,6 +79,86 @@ pub struct QueryProductVariant {
     pub order_idx: i32,
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = t_product_images)]
+pub struct NewProductImage<'a> {
+    pub pid: i64,
+    pub url: &'a str,
+    pub alt_text: &'a str,
+    pub order_idx: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_product_variants)]
+pub struct NewProductVariant<'a> {
+    pub pid: i64,
+    pub price: PgMoney,
+    pub title: &'a str,
+    pub inventory_count: i32,
+    pub order_idx: i32,
+    pub external_key: &'a str,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = t_product_variants)]
+pub struct MutateProductVariant<'a> {
+    pub price: Option<PgMoney>,
+    pub title: Option<&'a str>,
+    pub inventory_count: Option<i32>,
+    pub order_idx: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = t_product_options)]
+pub struct QueryProductOption {
+    pub id: i64,
+    pub pid: i64,
+    pub name: String,
+    pub order_idx: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_product_options)]
+pub struct NewProductOption<'a> {
+    pub pid: i64,
+    pub name: &'a str,
+    pub order_idx: i32,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = t_product_option_values)]
+pub struct QueryProductOptionValue {
+    pub id: i64,
+    pub oid: i64,
+    pub value: String,
+    pub order_idx: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_product_option_values)]
+pub struct NewProductOptionValue<'a> {
+    pub oid: i64,
+    pub value: &'a str,
+    pub order_idx: i32,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = t_product_variant_options)]
+pub struct QueryProductVariantOption {
+    pub id: i64,
+    pub vid: i64,
+    pub oid: i64,
+    pub ovid: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = t_product_variant_options)]
+pub struct NewProductVariantOption {
+    pub vid: i64,
+    pub oid: i64,
+    pub ovid: i64,
+}
+
 // Domain model hold an IDL model to representing data layout.
 pub struct ProductDomain(Product);
 
@@ -74,6 +174,7 @@ impl ProductDomain {
             sub_title: product.sub_title.into(),
             description: product.description.into(),
             currency_code: product.currency_code.to_string().into(),
+            handle: product.handle.into(),
             images: images
                 .into_iter()
                 .map(|v| Image {
@@ -84,24 +185,117 @@ impl ProductDomain {
                 .collect(),
             variants: variants
                 .into_iter()
-                .map(|v| ProductVariant {
-                    id: v.id,
-                    price: Money {
-                        amount: v.price.0,
-                        currency_code: product.currency_code.to_string().into(),
-                    },
-                    title: v.title.into(),
-                    inventory_count: v.inventory_count,
-                    order_idx: v.order_idx,
-                })
+                .map(|v| Self::merge_variant(v, &product.currency_code))
                 .collect(),
+            options: vec![],
         })
     }
 
+    fn merge_variant(variant: QueryProductVariant, currency_code: &str) -> ProductVariant {
+        ProductVariant {
+            id: variant.id,
+            price: Money {
+                amount: variant.price.0,
+                currency_code: currency_code.to_string().into(),
+            },
+            title: variant.title.into(),
+            inventory_count: variant.inventory_count,
+            order_idx: variant.order_idx,
+            selected_options: vec![],
+        }
+    }
+
+    /// Load options of products and the options selected by their variants in batch,
+    /// `merge_query` leaves them empty.
+    pub(in crate::domain) fn attach_options<'a>(
+        products: impl IntoIterator<Item = &'a mut Product>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        let products = products
+            .into_iter()
+            .map(|v| (v.id, &mut v.options, &mut v.variants));
+        Self::attach_options_by_pid(products, conn)
+    }
+
+    /// Like [ProductDomain::attach_options], with the options and variants of products
+    /// given by their ids.
+    fn attach_options_by_pid<'a>(
+        products: impl IntoIterator<
+            Item = (i64, &'a mut Vec<ProductOption>, &'a mut Vec<ProductVariant>),
+        >,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        let mut products = products.into_iter().collect::<Vec<_>>();
+        let pids = products.iter().map(|v| v.0).collect::<Vec<_>>();
+        let vids = products
+            .iter()
+            .flat_map(|v| v.2.iter().map(|v| v.id))
+            .collect::<Vec<_>>();
+        let options: Vec<QueryProductOption> = t_product_options::table
+            .filter(t_product_options::pid.eq_any(pids))
+            .order((t_product_options::pid, t_product_options::order_idx))
+            .select(QueryProductOption::as_select())
+            .load(conn)?;
+        if options.is_empty() {
+            return Ok(());
+        }
+        let oids = options.iter().map(|v| v.id).collect::<Vec<_>>();
+        let values: Vec<QueryProductOptionValue> = t_product_option_values::table
+            .filter(t_product_option_values::oid.eq_any(oids))
+            .order((
+                t_product_option_values::oid,
+                t_product_option_values::order_idx,
+            ))
+            .select(QueryProductOptionValue::as_select())
+            .load(conn)?;
+        let selections: Vec<QueryProductVariantOption> = t_product_variant_options::table
+            .filter(t_product_variant_options::vid.eq_any(vids))
+            .select(QueryProductVariantOption::as_select())
+            .load(conn)?;
+        let option_by_id = options.iter().map(|v| (v.id, v)).collect::<HashMap<_, _>>();
+        let value_by_id = values.iter().map(|v| (v.id, v)).collect::<HashMap<_, _>>();
+        for (pid, product_options, variants) in products.iter_mut() {
+            **product_options = options
+                .iter()
+                .filter(|option| option.pid == *pid)
+                .map(|option| ProductOption {
+                    id: option.id,
+                    name: option.name.clone().into(),
+                    values: values
+                        .iter()
+                        .filter(|value| value.oid == option.id)
+                        .map(|value| value.value.clone().into())
+                        .collect(),
+                    order_idx: option.order_idx,
+                })
+                .collect();
+            for variant in variants.iter_mut() {
+                let mut selected = selections
+                    .iter()
+                    .filter(|v| v.vid == variant.id)
+                    .filter_map(|v| Some((option_by_id.get(&v.oid)?, value_by_id.get(&v.ovid)?)))
+                    .collect::<Vec<_>>();
+                selected.sort_by_key(|(option, _)| option.order_idx);
+                variant.selected_options = selected
+                    .into_iter()
+                    .map(|(option, value)| SelectedOption {
+                        name: option.name.clone().into(),
+                        value: value.value.clone().into(),
+                    })
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+
     pub(in crate::domain) fn into_product(self) -> Product {
         self.0
     }
 
+    pub(in crate::domain) fn product(&self) -> &Product {
+        &self.0
+    }
+
     /// Query a product from database and return a domain model which might be used in
     /// further computing.
     /// Do serial query without a transaction, we dont need strong consistency.
@@ -126,7 +320,122 @@ impl ProductDomain {
         let variants = QueryProductVariant::belonging_to(&product)
             .select(QueryProductVariant::as_select())
             .load(conn)?;
-        Ok(Self::merge_query(product, images, variants))
+        let mut product = Self::merge_query(product, images, variants);
+        Self::attach_options(Some(&mut product.0), conn)?;
+        Ok(product)
+    }
+
+    /// Query a product by its handle, the previous handles of a product are redirected
+    /// to it, the current handle can be found in the returned product.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query_by_handle(
+        handle: &str,
+        conn: &mut PgConnection,
+    ) -> Result<ProductDomain> {
+        let id = t_products::table
+            .filter(t_products::handle.eq(handle))
+            .select(t_products::id)
+            .first::<i64>(conn)
+            .optional()?;
+        let id = match id {
+            Some(id) => id,
+            None => t_product_redirects::table
+                .filter(t_product_redirects::handle.eq(handle))
+                .select(t_product_redirects::pid)
+                .first::<i64>(conn)
+                .optional()?
+                .ok_or_else(|| Status::not_found(format!("product(handle: {})", handle)))?,
+        };
+        Self::query(id, conn)
+    }
+
+    /// Query products in batch, products that are not found will be absent in the result.
+    /// Do serial query without a transaction, we dont need strong consistency.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_many(
+        ids: impl IntoIterator<Item = i64>,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, ProductDomain>> {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        let products = t_products::table
+            .filter(t_products::id.eq_any(ids))
+            .select(QueryProduct::as_select())
+            .load(conn)?;
+        let images = QueryProductImage::belonging_to(&products)
+            .select(QueryProductImage::as_select())
+            .load(conn)?
+            .grouped_by(&products);
+        let variants = QueryProductVariant::belonging_to(&products)
+            .select(QueryProductVariant::as_select())
+            .load(conn)?
+            .grouped_by(&products);
+        let mut products = products
+            .into_iter()
+            .zip(images.into_iter().zip(variants))
+            .map(|(product, (images, variants))| {
+                (product.id, Self::merge_query(product, images, variants))
+            })
+            .collect::<HashMap<_, _>>();
+        Self::attach_options(products.values_mut().map(|v| &mut v.0), conn)?;
+        Ok(products)
+    }
+
+    /// Query images of products in batch, ordered by `order_idx`.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_images(
+        pids: impl IntoIterator<Item = i64>,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, Vec<Image>>> {
+        let pids = pids.into_iter().collect::<Vec<_>>();
+        let images: Vec<QueryProductImage> = t_product_images::table
+            .filter(t_product_images::pid.eq_any(pids))
+            .order((t_product_images::pid, t_product_images::order_idx))
+            .select(QueryProductImage::as_select())
+            .load(conn)?;
+        let mut res: HashMap<i64, Vec<Image>> = HashMap::new();
+        for image in images {
+            res.entry(image.pid).or_default().push(Image {
+                url: image.url.into(),
+                alt_text: image.alt_text.into(),
+                order_idx: image.order_idx,
+            });
+        }
+        Ok(res)
+    }
+
+    /// Query options and variants of products in batch.
+    /// Status maybe returned:
+    /// 1. internal
+    #[allow(clippy::type_complexity)]
+    pub(in crate::domain) fn query_variants(
+        pids: impl IntoIterator<Item = i64>,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, (Vec<ProductOption>, Vec<ProductVariant>)>> {
+        let mut res: HashMap<i64, (Vec<ProductOption>, Vec<ProductVariant>)> = pids
+            .into_iter()
+            .map(|pid| (pid, Default::default()))
+            .collect();
+        // the prices are in the currencies of the products
+        let variants: Vec<(QueryProductVariant, String)> = t_product_variants::table
+            .inner_join(t_products::table)
+            .filter(t_product_variants::pid.eq_any(res.keys()))
+            .order((t_product_variants::pid, t_product_variants::order_idx))
+            .select((QueryProductVariant::as_select(), t_products::currency_code))
+            .load(conn)?;
+        for (variant, currency_code) in variants {
+            if let Some((_, variants)) = res.get_mut(&variant.pid) {
+                variants.push(Self::merge_variant(variant, &currency_code));
+            }
+        }
+        let products = res
+            .iter_mut()
+            .map(|(pid, (options, variants))| (*pid, options, variants));
+        Self::attach_options_by_pid(products, conn)?;
+        Ok(res)
     }
 
     /// List products, if the `before` is not set in PaginationOption, it will query
@@ -172,6 +481,7 @@ impl ProductDomain {
             .zip(images.into_iter().zip(variants))
             .map(|(product, (images, variants))| Self::merge_query(product, images, variants).0)
             .collect::<Vec<_>>();
+        Self::attach_options(products.iter_mut(), conn)?;
         let mut has_previous_page = false;
         let mut has_next_page = false;
         if let Some(first) = option.first.map(|v| v as usize) {
@@ -192,9 +502,642 @@ impl ProductDomain {
         })
     }
 
-    // TODO
-    pub(in crate::domain) fn create() {}
+    /// Check whether a handle is used by any product, including the previous handles.
+    fn is_handle_taken(handle: &str, conn: &mut PgConnection) -> Result<bool> {
+        let in_products = diesel::select(diesel::dsl::exists(
+            t_products::table.filter(t_products::handle.eq(handle)),
+        ))
+        .get_result::<bool>(conn)?;
+        let in_redirects = diesel::select(diesel::dsl::exists(
+            t_product_redirects::table.filter(t_product_redirects::handle.eq(handle)),
+        ))
+        .get_result::<bool>(conn)?;
+        Ok(in_products || in_redirects)
+    }
+
+    /// Generate a unique handle from the title, a numeric suffix is appended when
+    /// the handle is taken, e.g. `super-mario-odyssey-2`.
+    pub(in crate::domain) fn generate_handle(
+        title: &str,
+        conn: &mut PgConnection,
+    ) -> Result<String> {
+        let handle = slugify(title);
+        if !Self::is_handle_taken(&handle, conn)? {
+            return Ok(handle);
+        }
+        // slugs only contain `[a-z0-9-]`, there is nothing to escape in the pattern.
+        let pattern = format!("{}-%", handle);
+        let mut taken = t_products::table
+            .filter(t_products::handle.like(&pattern))
+            .select(t_products::handle)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        taken.extend(
+            t_product_redirects::table
+                .filter(t_product_redirects::handle.like(&pattern))
+                .select(t_product_redirects::handle)
+                .load::<String>(conn)?,
+        );
+        let suffix = (2..)
+            .find(|n| !taken.contains(&format!("{}-{}", handle, n)))
+            .expect("suffix always exists");
+        Ok(format!("{}-{}", handle, suffix))
+    }
+
+    /// Make sure a handle specified by client is valid and not taken.
+    /// Status maybe returned:
+    /// 1. invalid_argument
+    /// 2. already_exists
+    /// 3. internal
+    pub(in crate::domain) fn check_handle(handle: &str, conn: &mut PgConnection) -> Result<()> {
+        if !is_valid_handle(handle) {
+            return Err(Status::invalid_argument(
+                "handle",
+                handle,
+                "lowercase letters, digits and single hyphens",
+            ));
+        }
+        if Self::is_handle_taken(handle, conn)? {
+            return Err(Status::already_exists(format!(
+                "product(handle: {})",
+                handle
+            )));
+        }
+        Ok(())
+    }
+
+    /// Status maybe returned:
+    /// 1. already_exists
+    /// 2. internal
+    pub(in crate::domain) fn create(
+        new: NewProduct,
+        conn: &mut PgConnection,
+    ) -> Result<ProductDomain> {
+        let id = diesel::insert_into(t_products::table)
+            .values(&new)
+            .returning(t_products::id)
+            .get_result::<i64>(conn)
+            .map_err(|e| {
+                if matches!(
+                    e,
+                    DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)
+                ) {
+                    Status::already_exists(format!("product(handle: {})", new.handle))
+                } else {
+                    Status::from(e)
+                }
+            })?;
+        Self::query(id, conn)
+    }
+
+    /// Change the handle of this product, the previous handle is kept as a redirect.
+    /// A previous handle of this product can be reclaimed.
+    /// Status maybe returned:
+    /// 1. invalid_argument
+    /// 2. already_exists
+    /// 3. internal
+    pub(in crate::domain) fn change_handle(
+        &mut self,
+        handle: &str,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        if self.0.handle == handle {
+            return Ok(());
+        }
+        conn.transaction(|conn| {
+            diesel::delete(t_product_redirects::table)
+                .filter(t_product_redirects::handle.eq(handle))
+                .filter(t_product_redirects::pid.eq(self.0.id))
+                .execute(conn)?;
+            Self::check_handle(handle, conn)?;
+            diesel::update(t_products::table)
+                .filter(t_products::id.eq(self.0.id))
+                .set(t_products::handle.eq(handle))
+                .execute(conn)?;
+            diesel::insert_into(t_product_redirects::table)
+                .values(NewProductRedirect {
+                    handle: &self.0.handle,
+                    pid: self.0.id,
+                })
+                .execute(conn)?;
+            self.0.handle = handle.to_string().into();
+            Ok(())
+        })
+    }
 
     // TODO
     pub(in crate::domain) fn mutate() {}
+
+    /// Query the product which a variant belongs to.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query_by_variant(
+        variant_id: i64,
+        conn: &mut PgConnection,
+    ) -> Result<ProductDomain> {
+        let pid = Self::query_pid_by_variant(variant_id, conn)?;
+        Self::query(pid, conn)
+    }
+
+    /// Like [ProductDomain::query_by_variant], with the product locked by
+    /// [ProductDomain::query_for_update].
+    pub(in crate::domain) fn query_by_variant_for_update(
+        variant_id: i64,
+        conn: &mut PgConnection,
+    ) -> Result<ProductDomain> {
+        let pid = Self::query_pid_by_variant(variant_id, conn)?;
+        Self::query_for_update(pid, conn)
+    }
+
+    /// Query a product whose row is locked until the end of the transaction, so that
+    /// its options and the options selected by its variants are changed one at a time.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. internal
+    pub(in crate::domain) fn query_for_update(
+        id: i64,
+        conn: &mut PgConnection,
+    ) -> Result<ProductDomain> {
+        t_products::table
+            .find(id)
+            .select(t_products::id)
+            .for_update()
+            .first::<i64>(conn)
+            .optional()?
+            .ok_or_else(|| Status::not_found(format!("product({})", id)))?;
+        Self::query(id, conn)
+    }
+
+    fn query_pid_by_variant(variant_id: i64, conn: &mut PgConnection) -> Result<i64> {
+        t_product_variants::table
+            .find(variant_id)
+            .select(t_product_variants::pid)
+            .first::<i64>(conn)
+            .optional()?
+            .ok_or_else(|| Status::not_found(format!("product_variant({})", variant_id)))
+    }
+
+    /// Append an option with its values to this product, before any variant selects
+    /// options, as their selections would miss the new option.
+    /// Status maybe returned:
+    /// 1. invalid_argument
+    /// 2. already_exists
+    /// 3. failed_precondition
+    /// 4. internal
+    pub(in crate::domain) fn add_option(
+        &mut self,
+        name: &str,
+        values: Vec<String>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(Status::invalid_argument("name", name, "a non-empty name"));
+        }
+        if self.0.options.iter().any(|v| v.name == name) {
+            return Err(Status::already_exists(format!(
+                "product_option(pid: {}, name: {})",
+                self.0.id, name
+            )));
+        }
+        if values.is_empty() {
+            return Err(Status::invalid_argument(
+                "values",
+                "[]",
+                "at least one value",
+            ));
+        }
+        let mut seen = HashSet::new();
+        if let Some(value) = values
+            .iter()
+            .find(|v| v.trim().is_empty() || !seen.insert(v.as_str()))
+        {
+            return Err(Status::invalid_argument(
+                "values",
+                value,
+                "non-empty and distinct values",
+            ));
+        }
+        if let Some(variant) = self
+            .0
+            .variants
+            .iter()
+            .find(|v| !v.selected_options.is_empty())
+        {
+            return Err(Status::failed_precondition().with_precondition(vec![
+                PreconditionViolation {
+                    r#type: "logic".to_string(),
+                    subject: "nintendo-shop/product".to_string(),
+                    description: format!(
+                        "Product_variant({}) already selects options of product({})",
+                        variant.id, self.0.id
+                    ),
+                },
+            ]));
+        }
+        conn.transaction(|conn| {
+            let oid = diesel::insert_into(t_product_options::table)
+                .values(NewProductOption {
+                    pid: self.0.id,
+                    name,
+                    order_idx: self.0.options.len() as i32,
+                })
+                .returning(t_product_options::id)
+                .get_result::<i64>(conn)?;
+            diesel::insert_into(t_product_option_values::table)
+                .values(
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, value)| NewProductOptionValue {
+                            oid,
+                            value,
+                            order_idx: idx as i32,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)?;
+            self.0.options.push(ProductOption {
+                id: oid,
+                name: name.to_string().into(),
+                values: values.into_iter().map(Into::into).collect(),
+                order_idx: self.0.options.len() as i32,
+            });
+            Ok(())
+        })
+    }
+
+    /// Select option values for a variant of this product, every option of this product
+    /// must be selected exactly once and the combination must be unique among variants.
+    /// Status maybe returned:
+    /// 1. not_found
+    /// 2. invalid_argument
+    /// 3. already_exists
+    /// 4. internal
+    pub(in crate::domain) fn set_variant_options(
+        &mut self,
+        variant_id: i64,
+        selected: Vec<SelectedOption>,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        if !self.0.variants.iter().any(|v| v.id == variant_id) {
+            return Err(Status::not_found(format!(
+                "product_variant({})",
+                variant_id
+            )));
+        }
+        let mut selected_names = HashSet::new();
+        for option in selected.iter() {
+            if !selected_names.insert(option.name.as_str()) {
+                return Err(Status::invalid_argument(
+                    "selectedOptions",
+                    option.name.as_str(),
+                    "each option selected once",
+                ));
+            }
+            let valid = self
+                .0
+                .options
+                .iter()
+                .find(|v| v.name == option.name)
+                .map(|v| v.values.contains(&option.value));
+            match valid {
+                None => {
+                    return Err(Status::invalid_argument(
+                        "selectedOptions.name",
+                        option.name.as_str(),
+                        format!("one of options of product({})", self.0.id),
+                    ))
+                }
+                Some(false) => {
+                    return Err(Status::invalid_argument(
+                        "selectedOptions.value",
+                        option.value.as_str(),
+                        format!("one of values of option '{}'", option.name),
+                    ))
+                }
+                Some(true) => {}
+            }
+        }
+        if let Some(missing) = self
+            .0
+            .options
+            .iter()
+            .find(|v| !selected_names.contains(v.name.as_str()))
+        {
+            return Err(Status::invalid_argument(
+                "selectedOptions",
+                "incomplete options",
+                format!("a value of option '{}'", missing.name),
+            ));
+        }
+        let duplicated = self
+            .0
+            .variants
+            .iter()
+            .filter(|v| v.id != variant_id)
+            .find(|v| is_same_selection(&v.selected_options, &selected));
+        if let Some(variant) = duplicated {
+            return Err(Status::already_exists(format!(
+                "product_variant({}) with the same options",
+                variant.id
+            )));
+        }
+        conn.transaction(|conn| {
+            diesel::delete(t_product_variant_options::table)
+                .filter(t_product_variant_options::vid.eq(variant_id))
+                .execute(conn)?;
+            let oids = self.0.options.iter().map(|v| v.id).collect::<Vec<_>>();
+            let values: Vec<QueryProductOptionValue> = t_product_option_values::table
+                .filter(t_product_option_values::oid.eq_any(oids))
+                .select(QueryProductOptionValue::as_select())
+                .load(conn)?;
+            let news = selected
+                .iter()
+                .filter_map(|selected| {
+                    let option = self.0.options.iter().find(|v| v.name == selected.name)?;
+                    let value = values
+                        .iter()
+                        .find(|v| v.oid == option.id && v.value == selected.value)?;
+                    Some(NewProductVariantOption {
+                        vid: variant_id,
+                        oid: option.id,
+                        ovid: value.id,
+                    })
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(t_product_variant_options::table)
+                .values(&news)
+                .execute(conn)?;
+            Ok::<_, Status>(())
+        })?;
+        let order = |name: &str| {
+            self.0
+                .options
+                .iter()
+                .position(|v| v.name == name)
+                .unwrap_or(usize::MAX)
+        };
+        let mut selected = selected;
+        selected.sort_by_key(|v| order(&v.name));
+        if let Some(variant) = self.0.variants.iter_mut().find(|v| v.id == variant_id) {
+            variant.selected_options = selected;
+        }
+        Ok(())
+    }
+
+    /// Query a product by its key in the imported catalog, a product without a key is
+    /// identified by its handle, see [ProductDomain::query_catalog].
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_by_external_key(
+        key: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Option<ProductDomain>> {
+        let id = t_products::table
+            .filter(t_products::external_key.eq(key))
+            .select(t_products::id)
+            .first::<i64>(conn)
+            .optional()?;
+        let id = match id {
+            Some(id) => Some(id),
+            None => t_products::table
+                .filter(t_products::external_key.is_null())
+                .filter(t_products::handle.eq(key))
+                .select(t_products::id)
+                .first::<i64>(conn)
+                .optional()?,
+        };
+        id.map(|id| Self::query(id, conn)).transpose()
+    }
+
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_external_key(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<Option<String>> {
+        Ok(t_products::table
+            .find(self.0.id)
+            .select(t_products::external_key)
+            .get_result(conn)?)
+    }
+
+    /// Query a page of products ordered by id in the record format of imported catalogs,
+    /// the page after `after` is returned if it is set. Products and variants without
+    /// keys get their handles and ids as keys. The inventory is left out unless
+    /// `inventory`.
+    /// Do serial query without a transaction, we dont need strong consistency.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_catalog(
+        after: Option<i64>,
+        inventory: bool,
+        conn: &mut PgConnection,
+    ) -> Result<CatalogPage> {
+        let (products, keys): (Vec<_>, Vec<_>) = t_products::table
+            .filter(t_products::id.gt(after.unwrap_or(0)))
+            .order(t_products::id)
+            .limit(MAX_DATA_LEN)
+            .select((QueryProduct::as_select(), t_products::external_key))
+            .load::<(QueryProduct, Option<String>)>(conn)?
+            .into_iter()
+            .unzip();
+        let images = QueryProductImage::belonging_to(&products)
+            .select(QueryProductImage::as_select())
+            .order(t_product_images::order_idx)
+            .load(conn)?
+            .grouped_by(&products);
+        let variants = QueryProductVariant::belonging_to(&products)
+            .select(QueryProductVariant::as_select())
+            .order(t_product_variants::order_idx)
+            .load(conn)?
+            .grouped_by(&products);
+        let variant_keys = t_product_variants::table
+            .filter(t_product_variants::pid.eq_any(products.iter().map(|v| v.id)))
+            .filter(t_product_variants::external_key.is_not_null())
+            .select((t_product_variants::id, t_product_variants::external_key))
+            .load::<(i64, Option<String>)>(conn)?
+            .into_iter()
+            .filter_map(|(id, key)| Some((id, key?)))
+            .collect::<HashMap<_, _>>();
+        let next_cursor = (products.len() == MAX_DATA_LEN as usize)
+            .then(|| products.last().map(|v| v.id))
+            .flatten();
+        let products = products
+            .into_iter()
+            .zip(keys)
+            .zip(images.into_iter().zip(variants))
+            .map(|((product, key), (images, variants))| CatalogProduct {
+                key: key.unwrap_or(product.handle.clone()).into(),
+                handle: Some(product.handle.into()),
+                title: product.title.into(),
+                sub_title: product.sub_title.into(),
+                description: product.description.into(),
+                currency_code: product.currency_code.into(),
+                variants: variants
+                    .into_iter()
+                    .map(|v| CatalogVariant {
+                        key: variant_keys
+                            .get(&v.id)
+                            .cloned()
+                            .unwrap_or(v.id.to_string())
+                            .into(),
+                        title: v.title.into(),
+                        price: v.price.0,
+                        inventory_count: inventory.then_some(v.inventory_count),
+                    })
+                    .collect(),
+                images: images
+                    .into_iter()
+                    .map(|v| CatalogImage {
+                        url: v.url.into(),
+                        alt_text: v.alt_text.into(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(CatalogPage {
+            products,
+            next_cursor,
+        })
+    }
+
+    /// Query the keys in the imported catalog of the variants of this product, variants
+    /// not imported are absent.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn query_variant_keys(
+        &self,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i64, String>> {
+        let keys = t_product_variants::table
+            .filter(t_product_variants::pid.eq(self.0.id))
+            .filter(t_product_variants::external_key.is_not_null())
+            .select((t_product_variants::id, t_product_variants::external_key))
+            .load::<(i64, Option<String>)>(conn)?;
+        Ok(keys
+            .into_iter()
+            .filter_map(|(id, key)| Some((id, key?)))
+            .collect())
+    }
+
+    /// Update this product, the handle is changed by [ProductDomain::change_handle].
+    /// The changes are not reflected in this domain model until it is queried again.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn update(
+        &self,
+        mutate: MutateProduct,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::update(t_products::table.find(self.0.id))
+            .set((&mutate, t_products::updated_at.eq(diesel::dsl::now)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn insert_variants(
+        &self,
+        news: &[NewProductVariant],
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::insert_into(t_product_variants::table)
+            .values(news)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn update_variant(
+        &self,
+        variant_id: i64,
+        mutate: MutateProductVariant,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::update(t_product_variants::table)
+            .filter(t_product_variants::id.eq(variant_id))
+            .filter(t_product_variants::pid.eq(self.0.id))
+            .set((&mutate, t_product_variants::updated_at.eq(diesel::dsl::now)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Delete variants of this product with the options they selected and the cart
+    /// entries of them, the number of the deleted cart entries is returned.
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn delete_variants(
+        &self,
+        variant_ids: &[i64],
+        conn: &mut PgConnection,
+    ) -> Result<usize> {
+        let variant_ids = t_product_variants::table
+            .filter(t_product_variants::id.eq_any(variant_ids))
+            .filter(t_product_variants::pid.eq(self.0.id))
+            .select(t_product_variants::id)
+            .load::<i64>(conn)?;
+        diesel::delete(t_product_variant_options::table)
+            .filter(t_product_variant_options::vid.eq_any(&variant_ids))
+            .execute(conn)?;
+        let entries = diesel::delete(t_cart_entries::table)
+            .filter(t_cart_entries::vid.eq_any(&variant_ids))
+            .execute(conn)?;
+        diesel::delete(t_product_variants::table)
+            .filter(t_product_variants::id.eq_any(&variant_ids))
+            .execute(conn)?;
+        Ok(entries)
+    }
+
+    /// Status maybe returned:
+    /// 1. internal
+    pub(in crate::domain) fn replace_images(
+        &self,
+        news: &[NewProductImage],
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        diesel::delete(t_product_images::table)
+            .filter(t_product_images::pid.eq(self.0.id))
+            .execute(conn)?;
+        diesel::insert_into(t_product_images::table)
+            .values(news)
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+/// Generate a handle from a title, e.g. `Nintendo Switch - OLED Model` becomes
+/// `nintendo-switch-oled-model`. Titles without any ascii alphanumeric fall back
+/// to `product`.
+fn slugify(title: &str) -> String {
+    let mut handle = String::with_capacity(title.len());
+    for ch in title.chars() {
+        if ch.is_ascii_alphanumeric() {
+            handle.push(ch.to_ascii_lowercase());
+        } else if !handle.is_empty() && !handle.ends_with('-') {
+            handle.push('-');
+        }
+    }
+    let handle = handle.trim_end_matches('-');
+    if handle.is_empty() {
+        return "product".to_string();
+    }
+    handle.to_string()
+}
+
+pub(in crate::domain) fn is_valid_handle(handle: &str) -> bool {
+    handle.split('-').all(|part| {
+        !part.is_empty() && part.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9'))
+    })
+}
+
+fn is_same_selection(lhs: &[SelectedOption], rhs: &[SelectedOption]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .all(|l| rhs.iter().any(|r| l.name == r.name && l.value == r.value))
 }
@@ -0,0 +1,16 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::product::v1::Product;
+
+pub(in crate::domain) fn execute(
+    id: i64,
+    handle: String,
+    conn: &mut PgConnection,
+) -> Result<Product> {
+    conn.transaction(|conn| {
+        let mut product = ProductDomain::query(id, conn)?;
+        product.change_handle(&handle, conn)?;
+        Ok(product.into_product())
+    })
+}
@@ -1 +1,28 @@
+use crate::domain::product::model::{NewProduct as InsertProduct, ProductDomain};
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::product::v1::{NewProduct, Product};
 
+pub(in crate::domain) fn execute(new: NewProduct, conn: &mut PgConnection) -> Result<Product> {
+    conn.transaction(|conn| {
+        let handle = match new.handle.as_deref() {
+            Some(handle) => {
+                ProductDomain::check_handle(handle, conn)?;
+                handle.to_string()
+            }
+            None => ProductDomain::generate_handle(&new.title, conn)?,
+        };
+        let product = ProductDomain::create(
+            InsertProduct {
+                title: &new.title,
+                sub_title: new.sub_title.as_deref(),
+                description: new.description.as_deref(),
+                currency_code: &new.currency_code,
+                handle: &handle,
+                external_key: None,
+            },
+            conn,
+        )?;
+        Ok(product.into_product())
+    })
+}
@@ -0,0 +1,17 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::product::v1::Product;
+
+pub(in crate::domain) fn execute(
+    id: i64,
+    name: String,
+    values: Vec<String>,
+    conn: &mut PgConnection,
+) -> Result<Product> {
+    conn.transaction(|conn| {
+        let mut product = ProductDomain::query_for_update(id, conn)?;
+        product.add_option(&name, values, conn)?;
+        Ok(product.into_product())
+    })
+}
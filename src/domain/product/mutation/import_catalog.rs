@@ -0,0 +1,387 @@
+use crate::domain::product::model::{
+    is_valid_handle, MutateProduct, MutateProductVariant, NewProduct as InsertProduct,
+    NewProductImage, NewProductVariant, ProductDomain,
+};
+use crate::infra::error::{Result, Status};
+use crate::infra::validate::Validator;
+use diesel::data_types::PgMoney;
+use diesel::{Connection, PgConnection};
+use std::collections::{HashMap, HashSet};
+use volo_gen::product::v1::{CatalogAction, CatalogChange, CatalogProduct};
+
+/// Why the transaction of an import is rolled back.
+enum Abort {
+    /// The changes of a dry run, which are not to be kept.
+    DryRun(Vec<CatalogChange>),
+    Failed(Status),
+}
+
+impl From<diesel::result::Error> for Abort {
+    fn from(e: diesel::result::Error) -> Self {
+        Abort::Failed(e.into())
+    }
+}
+
+/// Import products of a catalog in a transaction, products are matched with the existing
+/// ones by their keys. The variants and images of a matched product are replaced by the
+/// imported ones, products absent in the catalog are left as they are. The carts keep
+/// the variants which are reordered, and lose the removed ones. A dry run rolls
+/// back the transaction, the changes it returns are what the import would do.
+/// The variants whose inventory changed are returned with the changes, none on a dry run.
+pub(in crate::domain) fn execute(
+    products: Vec<CatalogProduct>,
+    dry_run: bool,
+    conn: &mut PgConnection,
+) -> Result<(Vec<CatalogChange>, Vec<i64>)> {
+    validate(&products)?;
+    let mut inventory = vec![];
+    let res = conn.transaction(|conn| {
+        let changes = products
+            .iter()
+            .map(|product| import(product, &mut inventory, conn))
+            .collect::<Result<Vec<_>>>()
+            .map_err(Abort::Failed)?;
+        if dry_run {
+            return Err(Abort::DryRun(changes));
+        }
+        Ok(changes)
+    });
+    match res {
+        Ok(changes) => Ok((changes, inventory)),
+        Err(Abort::DryRun(mut changes)) => {
+            for change in changes.iter_mut() {
+                if change.action == CatalogAction::Created {
+                    change.product_id = 0;
+                }
+            }
+            Ok((changes, vec![]))
+        }
+        Err(Abort::Failed(e)) => Err(e),
+    }
+}
+
+fn validate(products: &[CatalogProduct]) -> Result<()> {
+    let mut v = Validator::new();
+    let mut keys = HashSet::new();
+    for (i, product) in products.iter().enumerate() {
+        let field = |name: &str| format!("[{i}].{name}");
+        v.check(&field("key"), !product.key.is_empty(), "must not be empty");
+        v.check(
+            &field("key"),
+            keys.insert(product.key.as_str()),
+            "is duplicated",
+        );
+        if let Some(handle) = &product.handle {
+            v.check(
+                &field("handle"),
+                is_valid_handle(handle),
+                "lowercase letters, digits and single hyphens",
+            );
+        }
+        v.check(
+            &field("title"),
+            !product.title.trim().is_empty(),
+            "must not be empty",
+        );
+        v.check(
+            &field("currency_code"),
+            product.currency_code.len() == 3
+                && product
+                    .currency_code
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase()),
+            "an ISO 4217 code, e.g. USD",
+        );
+        let mut variant_keys = HashSet::new();
+        for (j, variant) in product.variants.iter().enumerate() {
+            let field = |name: &str| format!("[{i}].variants[{j}].{name}");
+            v.check(&field("key"), !variant.key.is_empty(), "must not be empty");
+            v.check(
+                &field("key"),
+                variant_keys.insert(variant.key.as_str()),
+                "is duplicated",
+            );
+            v.check(&field("price"), variant.price >= 0, "must not be negative");
+            v.check(
+                &field("inventory_count"),
+                variant.inventory_count.unwrap_or_default() >= 0,
+                "must not be negative",
+            );
+        }
+        let mut urls = HashSet::new();
+        for (j, image) in product.images.iter().enumerate() {
+            let field = |name: &str| format!("[{i}].images[{j}].{name}");
+            v.check(&field("url"), !image.url.is_empty(), "must not be empty");
+            v.check(
+                &field("url"),
+                urls.insert(image.url.as_str()),
+                "is duplicated",
+            );
+        }
+    }
+    v.finish()
+}
+
+fn import(
+    product: &CatalogProduct,
+    inventory: &mut Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<CatalogChange> {
+    match ProductDomain::query_by_external_key(&product.key, conn)? {
+        Some(existing) => update(existing, product, inventory, conn),
+        None => create(product, conn),
+    }
+}
+
+fn create(product: &CatalogProduct, conn: &mut PgConnection) -> Result<CatalogChange> {
+    let handle = match product.handle.as_deref() {
+        Some(handle) => {
+            ProductDomain::check_handle(handle, conn)?;
+            handle.to_string()
+        }
+        None => ProductDomain::generate_handle(&product.title, conn)?,
+    };
+    let created = ProductDomain::create(
+        InsertProduct {
+            title: &product.title,
+            sub_title: Some(&product.sub_title),
+            description: Some(&product.description),
+            currency_code: &product.currency_code,
+            handle: &handle,
+            external_key: Some(&product.key),
+        },
+        conn,
+    )?;
+    let id = created.product().id;
+    created.insert_variants(&new_variants(id, product.variants.iter().enumerate()), conn)?;
+    created.replace_images(&new_images(id, product), conn)?;
+    Ok(CatalogChange {
+        key: product.key.clone(),
+        action: CatalogAction::Created,
+        product_id: id,
+        handle: handle.into(),
+        changes: vec![],
+    })
+}
+
+fn update(
+    mut existing: ProductDomain,
+    product: &CatalogProduct,
+    inventory: &mut Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<CatalogChange> {
+    let mut changes = vec![];
+    let current = existing.product().clone();
+    // a product not imported is identified by its handle until it gets a key
+    let adopted = existing.query_external_key(conn)?.is_none();
+    if adopted {
+        changes.push(format!("key: none -> {:?}", product.key));
+    }
+
+    if let Some(handle) = product.handle.as_deref() {
+        if current.handle != handle {
+            changes.push(format!("handle: {:?} -> {:?}", current.handle, handle));
+            existing.change_handle(handle, conn)?;
+        }
+    }
+
+    let changed = |field: &str, from: &str, to: &str, changes: &mut Vec<String>| {
+        (from != to).then(|| changes.push(format!("{field}: {from:?} -> {to:?}")))
+    };
+    let mutate = MutateProduct {
+        title: changed("title", &current.title, &product.title, &mut changes)
+            .map(|_| product.title.as_str()),
+        sub_title: changed(
+            "sub_title",
+            &current.sub_title,
+            &product.sub_title,
+            &mut changes,
+        )
+        .map(|_| product.sub_title.as_str()),
+        description: (current.description != product.description).then(|| {
+            changes.push("description changed".to_string());
+            product.description.as_str()
+        }),
+        currency_code: changed(
+            "currency_code",
+            &current.currency_code,
+            &product.currency_code,
+            &mut changes,
+        )
+        .map(|_| product.currency_code.as_str()),
+        handle: None,
+        external_key: adopted.then_some(product.key.as_str()),
+    };
+    if mutate.title.is_some()
+        || mutate.sub_title.is_some()
+        || mutate.description.is_some()
+        || mutate.currency_code.is_some()
+        || mutate.external_key.is_some()
+    {
+        existing.update(mutate, conn)?;
+    }
+
+    // variants are matched by their keys, or ids if they are not imported
+    let keys = existing.query_variant_keys(conn)?;
+    let key = |id: &i64| keys.get(id).cloned().unwrap_or(id.to_string());
+    let mut variants = current.variants.iter().collect::<Vec<_>>();
+    variants.sort_by_key(|v| v.order_idx);
+    let by_key = variants
+        .iter()
+        .map(|v| (key(&v.id), *v))
+        .collect::<HashMap<_, _>>();
+    let mut matched = HashSet::new();
+    let mut added = vec![];
+    for (idx, variant) in product.variants.iter().enumerate() {
+        let Some(old) = by_key.get(variant.key.as_str()) else {
+            changes.push(format!("variant {}: added", variant.key));
+            added.push((idx, variant));
+            continue;
+        };
+        matched.insert(old.id);
+        let field = |name: &str, from: String, to: String, changes: &mut Vec<String>| {
+            (from != to)
+                .then(|| changes.push(format!("variant {}: {name} {from} -> {to}", variant.key)))
+        };
+        let mutate = MutateProductVariant {
+            price: field(
+                "price",
+                money(old.price.amount),
+                money(variant.price),
+                &mut changes,
+            )
+            .map(|_| PgMoney(variant.price)),
+            title: field(
+                "title",
+                format!("{:?}", old.title),
+                format!("{:?}", variant.title),
+                &mut changes,
+            )
+            .map(|_| variant.title.as_str()),
+            inventory_count: variant.inventory_count.and_then(|count| {
+                field(
+                    "inventory_count",
+                    old.inventory_count.to_string(),
+                    count.to_string(),
+                    &mut changes,
+                )
+                .map(|_| count)
+            }),
+            order_idx: field(
+                "position",
+                old.order_idx.to_string(),
+                idx.to_string(),
+                &mut changes,
+            )
+            .map(|_| idx as i32),
+        };
+        if mutate.price.is_some()
+            || mutate.title.is_some()
+            || mutate.inventory_count.is_some()
+            || mutate.order_idx.is_some()
+        {
+            if mutate.inventory_count.is_some() {
+                inventory.push(old.id);
+            }
+            existing.update_variant(old.id, mutate, conn)?;
+        }
+    }
+    let removed = variants
+        .iter()
+        .filter(|v| !matched.contains(&v.id))
+        .map(|v| {
+            changes.push(format!("variant {}: removed", key(&v.id)));
+            v.id
+        })
+        .collect::<Vec<_>>();
+    if !removed.is_empty() {
+        // the carts select variants by their ids, so only the removed ones leave them
+        let entries = existing.delete_variants(&removed, conn)?;
+        if entries > 0 {
+            changes.push(format!(
+                "cart entries of removed variants: {entries} removed"
+            ));
+        }
+    }
+    if !added.is_empty() {
+        existing.insert_variants(&new_variants(current.id, added.into_iter()), conn)?;
+    }
+
+    // images are replaced as a whole if any of them changes
+    let mut images = current.images.iter().collect::<Vec<_>>();
+    images.sort_by_key(|v| v.order_idx);
+    let same = images.len() == product.images.len()
+        && images
+            .iter()
+            .zip(&product.images)
+            .all(|(old, new)| old.url == new.url && old.alt_text == new.alt_text);
+    if !same {
+        let before = changes.len();
+        for new in &product.images {
+            match images.iter().find(|old| old.url == new.url) {
+                None => changes.push(format!("image {}: added", new.url)),
+                Some(old) if old.alt_text != new.alt_text => changes.push(format!(
+                    "image {}: alt_text {:?} -> {:?}",
+                    new.url, old.alt_text, new.alt_text
+                )),
+                Some(_) => {}
+            }
+        }
+        for old in &images {
+            if !product.images.iter().any(|new| new.url == old.url) {
+                changes.push(format!("image {}: removed", old.url));
+            }
+        }
+        if changes.len() == before {
+            changes.push("images reordered".to_string());
+        }
+        existing.replace_images(&new_images(current.id, product), conn)?;
+    }
+
+    let handle = product.handle.clone().unwrap_or(current.handle);
+    Ok(CatalogChange {
+        key: product.key.clone(),
+        action: if changes.is_empty() {
+            CatalogAction::Unchanged
+        } else {
+            CatalogAction::Updated
+        },
+        product_id: current.id,
+        handle,
+        changes: changes.into_iter().map(Into::into).collect(),
+    })
+}
+
+fn new_variants<'a>(
+    pid: i64,
+    variants: impl Iterator<Item = (usize, &'a volo_gen::product::v1::CatalogVariant)>,
+) -> Vec<NewProductVariant<'a>> {
+    variants
+        .map(|(idx, variant)| NewProductVariant {
+            pid,
+            price: PgMoney(variant.price),
+            title: &variant.title,
+            inventory_count: variant.inventory_count.unwrap_or_default(),
+            order_idx: idx as i32,
+            external_key: &variant.key,
+        })
+        .collect()
+}
+
+fn new_images(pid: i64, product: &CatalogProduct) -> Vec<NewProductImage<'_>> {
+    product
+        .images
+        .iter()
+        .enumerate()
+        .map(|(idx, image)| NewProductImage {
+            pid,
+            url: &image.url,
+            alt_text: &image.alt_text,
+            order_idx: idx as i32,
+        })
+        .collect()
+}
+
+fn money(amount: i64) -> String {
+    format!("{}.{:02}", amount / 100, amount % 100)
+}
@@ -1 +1,5 @@
+pub mod change_product_handle;
 pub mod create_product;
+pub mod create_product_option;
+pub mod import_catalog;
+pub mod set_variant_options;
@@ -0,0 +1,17 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::{Connection, PgConnection};
+use volo_gen::product::v1::{Product, SelectedOption};
+
+pub(in crate::domain) fn execute(
+    variant_id: i64,
+    selected: Vec<SelectedOption>,
+    conn: &mut PgConnection,
+) -> Result<Product> {
+    conn.transaction(|conn| {
+        // the uniqueness of the combination is checked against the committed ones
+        let mut product = ProductDomain::query_by_variant_for_update(variant_id, conn)?;
+        product.set_variant_options(variant_id, selected, conn)?;
+        Ok(product.into_product())
+    })
+}
@@ -0,0 +1,12 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::product::v1::CatalogPage;
+
+pub(in crate::domain) fn execute(
+    cursor: Option<i64>,
+    inventory: bool,
+    conn: &mut PgConnection,
+) -> Result<CatalogPage> {
+    ProductDomain::query_catalog(cursor, inventory, conn)
+}
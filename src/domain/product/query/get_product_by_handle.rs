@@ -0,0 +1,8 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use volo_gen::product::v1::Product;
+
+pub(in crate::domain) fn execute(handle: String, conn: &mut PgConnection) -> Result<Product> {
+    ProductDomain::query_by_handle(&handle, conn).map(|v| v.into_product())
+}
@@ -0,0 +1,16 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::{Result, Status};
+use diesel::PgConnection;
+use volo_gen::product::v1::ProductVariant;
+
+pub(in crate::domain) fn execute(
+    variant_id: i64,
+    conn: &mut PgConnection,
+) -> Result<ProductVariant> {
+    ProductDomain::query_by_variant(variant_id, conn)?
+        .into_product()
+        .variants
+        .into_iter()
+        .find(|v| v.id == variant_id)
+        .ok_or_else(|| Status::not_found(format!("product_variant({})", variant_id)))
+}
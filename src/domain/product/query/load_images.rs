@@ -0,0 +1,12 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use std::collections::HashMap;
+use volo_gen::common::v1::Image;
+
+pub(in crate::domain) fn execute(
+    pids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i64, Vec<Image>>> {
+    ProductDomain::query_images(pids, conn)
+}
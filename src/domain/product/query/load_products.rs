@@ -0,0 +1,15 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use std::collections::HashMap;
+use volo_gen::product::v1::Product;
+
+pub(in crate::domain) fn execute(
+    ids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i64, Product>> {
+    Ok(ProductDomain::query_many(ids, conn)?
+        .into_iter()
+        .map(|(id, v)| (id, v.into_product()))
+        .collect())
+}
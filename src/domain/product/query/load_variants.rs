@@ -0,0 +1,13 @@
+use crate::domain::product::model::ProductDomain;
+use crate::infra::error::Result;
+use diesel::PgConnection;
+use std::collections::HashMap;
+use volo_gen::product::v1::{ProductOption, ProductVariant};
+
+#[allow(clippy::type_complexity)]
+pub(in crate::domain) fn execute(
+    pids: Vec<i64>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<i64, (Vec<ProductOption>, Vec<ProductVariant>)>> {
+    ProductDomain::query_variants(pids, conn)
+}
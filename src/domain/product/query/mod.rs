@@ -1,2 +1,8 @@
+pub mod export_catalog;
 pub mod get_product;
+pub mod get_product_by_handle;
+pub mod get_variant;
 pub mod list_products;
+pub mod load_images;
+pub mod load_products;
+pub mod load_variants;
@@ -0,0 +1,277 @@
+//! The limits of the graphql service as a poem middleware, requests over the limits
+//! are answered with graphql errors of their status.
+//!
+//! Fields are limited per client by the [FieldLimit] extension, the quota of a
+//! client is counted in redis and shared by all instances of the service.
+
+use crate::graphql::metrics::SERVICE;
+use crate::graphql::Resolver;
+use crate::infra::config::service::RateLimitConfig;
+use crate::infra::error::{QuotaViolation, Status};
+use crate::infra::limit::{self, Limiter};
+use crate::infra::metrics;
+use crate::infra::resolver::{BaseResolver, Register};
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextResolve, ResolveInfo,
+};
+use async_graphql::{
+    ErrorExtensions, PathSegment, QueryPathSegment, ServerError, ServerResult, Value,
+};
+use poem::http::{header, HeaderValue};
+use poem::web::RemoteAddr;
+use poem::{
+    async_trait, Addr, Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody,
+    Response,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Limit the requests with the limiter of the register, it is resolved per request
+/// and follows the reloads of the config.
+pub struct Limit(Register<Arc<Limiter>>);
+
+impl Limit {
+    pub fn new(limiter: Register<Arc<Limiter>>) -> Self {
+        Self(limiter)
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Limit {
+    type Output = LimitEndpoint<E>;
+
+    fn transform(&self, inner: E) -> Self::Output {
+        LimitEndpoint {
+            inner,
+            limiter: self.0.clone(),
+        }
+    }
+}
+
+pub struct LimitEndpoint<E> {
+    inner: E,
+    limiter: Register<Arc<Limiter>>,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for LimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        match self.limiter.get().run(self.inner.call(req)).await {
+            Ok(res) => res.map(IntoResponse::into_response),
+            Err(status) => {
+                metrics::error(SERVICE, status.code().name());
+                Ok(reject(&status))
+            }
+        }
+    }
+}
+
+/// A graphql response with the status as its error, `Retry-After` is set if the
+/// status has retry info.
+pub fn reject(status: &Status) -> Response {
+    let body = async_graphql::Response::from_errors(vec![server_error(status)]);
+    let mut res = Response::builder()
+        .status(status.code().to_http_code())
+        .content_type("application/json")
+        .body(serde_json::to_string(&body).unwrap_or_default());
+    if let Some(delay) = status.retry_delay() {
+        res.headers_mut()
+            .insert(header::RETRY_AFTER, retry_after(delay));
+    }
+    res
+}
+
+fn server_error(status: &Status) -> ServerError {
+    let err = status.extend();
+    let mut server_error = ServerError::new(err.message, None);
+    server_error.extensions = err.extensions;
+    server_error
+}
+
+/// Seconds to wait before a retry, at least 1.
+fn retry_after(delay: Duration) -> HeaderValue {
+    let secs = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+    secs.max(1).into()
+}
+
+/// Quotas of a field per client, `0` disables a quota.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FieldLimitConfig {
+    pub ip: RateLimitConfig,    // per IP address
+    pub token: RateLimitConfig, // per bearer token, clients without a token are not limited by it
+}
+
+/// The client of a graphql request, requests without a client are not limited by
+/// [FieldLimit], e.g. the ones executed by tests.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub ip: Option<IpAddr>,
+    // sha256 of the bearer token, tokens are not written to redis
+    pub token: Option<String>,
+}
+
+impl Client {
+    pub fn new(ip: Option<IpAddr>, token: Option<&str>) -> Self {
+        Self {
+            ip,
+            token: token.map(|token| format!("{:x}", Sha256::digest(token))),
+        }
+    }
+}
+
+/// The IP address is the peer's, or the first one of `X-Forwarded-For` when the
+/// service runs behind a trusted proxy, see `trust_forwarded_for` of the config.
+#[async_trait]
+impl<'a> FromRequest<'a> for Client {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> poem::Result<Self> {
+        let trust_forwarded_for = req
+            .data::<Resolver>()
+            .map(|resolver| resolver.resolve(&resolver.trust_forwarded_for))
+            .unwrap_or_default();
+        let forwarded_for = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next()?.trim().parse().ok());
+        let ip = match <&RemoteAddr>::from_request(req, body).await?.0 {
+            _ if trust_forwarded_for => forwarded_for,
+            Addr::SocketAddr(addr) => Some(addr.ip()),
+            _ => None,
+        };
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        Ok(Self::new(ip, token))
+    }
+}
+
+/// Limit the fields of `field_limit` in the config per [Client], fields are named
+/// `Type.field`, e.g. `GraphqlMutation.createCart`. A field over its quota fails
+/// with resource_exhausted, and `Retry-After` is set on the response.
+///
+/// Redis errors are logged and the field is not limited, like the cache.
+pub struct FieldLimit;
+
+impl ExtensionFactory for FieldLimit {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(FieldLimitExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct FieldLimitExtension {
+    // the longest delay of the rejected fields of a request
+    retry_delay: Mutex<Option<Duration>>,
+}
+
+#[async_trait]
+impl Extension for FieldLimitExtension {
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> async_graphql::Response {
+        let mut res = next.run(ctx, operation_name).await;
+        if let Some(delay) = *self.retry_delay.lock().unwrap() {
+            res.http_headers
+                .insert(header::RETRY_AFTER, retry_after(delay));
+        }
+        res
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if let (Some(resolver), Some(client)) =
+            (ctx.data_opt::<Resolver>(), ctx.data_opt::<Client>())
+        {
+            let field = format!("{}.{}", info.parent_type, info.name);
+            if let Some(status) = resolver.take_field_quota(field, client).await {
+                let mut retry_delay = self.retry_delay.lock().unwrap();
+                *retry_delay = (*retry_delay).max(status.retry_delay());
+                let mut err = server_error(&status);
+                let mut nodes = info.path_node.parents().collect::<Vec<_>>();
+                nodes.reverse();
+                nodes.push(info.path_node);
+                err.path = nodes
+                    .into_iter()
+                    .map(|node| match node.segment {
+                        QueryPathSegment::Name(name) => PathSegment::Field(name.to_string()),
+                        QueryPathSegment::Index(idx) => PathSegment::Index(idx),
+                    })
+                    .collect();
+                return Err(err);
+            }
+        }
+        next.run(ctx, info).await
+    }
+}
+
+impl Resolver {
+    /// Count a request of `field` by the client, or return the status if one of
+    /// its quotas is exceeded.
+    async fn take_field_quota(&self, field: String, client: &Client) -> Option<Status> {
+        let config = self.resolve(&self.field_limit);
+        let config = config.get(&field)?;
+        // the subject of a violation, the id of the client and its quota, the token
+        // is known by the client and it is not exposed
+        let mut quotas = vec![];
+        if let Some(ip) = client.ip.filter(|_| config.ip.num > 0) {
+            quotas.push((format!("ip:{ip}"), ip.to_string(), config.ip.clone()));
+        }
+        if let Some(token) = client.token.clone().filter(|_| config.token.num > 0) {
+            quotas.push(("token".to_string(), token, config.token.clone()));
+        }
+        if quotas.is_empty() {
+            return None;
+        }
+        let res = self
+            .redis_blocking(move |conn| {
+                let mut conn = conn?;
+                let keys = quotas
+                    .iter()
+                    .map(|(subject, id, _)| {
+                        let kind = subject.split(':').next().unwrap_or_default();
+                        format!("limit:{field}:{kind}:{id}")
+                    })
+                    .collect::<Vec<_>>();
+                let windows = keys
+                    .iter()
+                    .zip(&quotas)
+                    .map(|(key, (_, _, rate))| (key.as_str(), rate))
+                    .collect::<Vec<_>>();
+                // the quotas are all checked before the request is counted, a request
+                // rejected by one of them is counted by none
+                let (idx, delay) = match limit::take_windows(&mut conn, &windows) {
+                    Ok(exceeded) => exceeded?,
+                    Err(err) => {
+                        tracing::warn!("redis is unavailable, bypass the limit: {err}");
+                        return None;
+                    }
+                };
+                let (subject, _, rate) = &quotas[idx];
+                let violation = QuotaViolation {
+                    subject: subject.clone(),
+                    description: format!("{} requests per {:?} to {field}", rate.num, rate.per),
+                };
+                Some(
+                    Status::resource_exhausted()
+                        .with_quota(vec![violation])
+                        .with_retry_info(delay),
+                )
+            })
+            .await;
+        res.ok().flatten()
+    }
+}
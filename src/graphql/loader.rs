@@ -0,0 +1,82 @@
+//! DataLoaders batch the loads issued by nested fields, e.g. the products in
+//! `checkout { cart { entries { product } } }` are loaded in one round trip.
+
+use crate::graphql::Resolver;
+use crate::infra::error::Status;
+use crate::infra::mqsrs::Query;
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use volo_gen::checkout::v1::{Payment, Shipping};
+use volo_gen::common::v1::Image;
+use volo_gen::product::v1::{Product, ProductOption, ProductVariant};
+
+/// Load products by id, read through the cache.
+pub struct ProductLoader(pub Resolver);
+
+/// Load images by product id.
+pub struct ImageLoader(pub Resolver);
+
+/// Load options and variants by product id.
+pub struct VariantLoader(pub Resolver);
+
+/// Load shipping methods by id.
+pub struct ShippingLoader(pub Resolver);
+
+/// Load payment methods by id.
+pub struct PaymentLoader(pub Resolver);
+
+#[async_trait]
+impl Loader<i64> for ProductLoader {
+    type Value = Product;
+    type Error = Status;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let query = self.0.create_load_products();
+        query.execute(keys.to_vec()).await
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for ImageLoader {
+    type Value = Vec<Image>;
+    type Error = Status;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let query = self.0.create_load_product_images();
+        query.execute(keys.to_vec()).await
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for VariantLoader {
+    type Value = (Vec<ProductOption>, Vec<ProductVariant>);
+    type Error = Status;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let query = self.0.create_load_product_variants();
+        query.execute(keys.to_vec()).await
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for ShippingLoader {
+    type Value = Shipping;
+    type Error = Status;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let query = self.0.create_load_shipping();
+        query.execute(keys.to_vec()).await
+    }
+}
+
+#[async_trait]
+impl Loader<i64> for PaymentLoader {
+    type Value = Payment;
+    type Error = Status;
+
+    async fn load(&self, keys: &[i64]) -> Result<HashMap<i64, Self::Value>, Self::Error> {
+        let query = self.0.create_load_payment();
+        query.execute(keys.to_vec()).await
+    }
+}
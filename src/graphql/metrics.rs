@@ -0,0 +1,77 @@
+//! Metrics of the graphql requests, see [crate::infra::metrics].
+//!
+//! Requests are labelled by their operation, `anonymous` when it has no name, and
+//! `invalid` when it was not executed, e.g. a syntax error. Errors are counted by the
+//! `code` of their extensions, `UNKNOWN` if absent.
+
+use crate::infra::error::Code;
+use crate::infra::metrics;
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextRequest,
+};
+use async_graphql::{Response, Value};
+use once_cell::sync::Lazy;
+use poem::async_trait;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub const SERVICE: &str = "graphql";
+
+// operations are named by the clients, the later ones are counted as `other`
+const MAX_OPERATIONS: usize = 200;
+static OPERATIONS: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Count the requests and their errors.
+pub struct Metrics;
+
+impl ExtensionFactory for Metrics {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(MetricsExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct MetricsExtension {
+    operation: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl Extension for MetricsExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let start = Instant::now();
+        let res = next.run(ctx).await;
+        let operation = self.operation.lock().unwrap().take();
+        let operation = operation.as_deref().unwrap_or("invalid");
+        metrics::observe(SERVICE, operation, start.elapsed());
+        for err in &res.errors {
+            match err.extensions.as_ref().and_then(|ext| ext.get("code")) {
+                Some(Value::String(code)) => metrics::error(SERVICE, code),
+                _ => metrics::error(SERVICE, Code::Unknown.name()),
+            }
+        }
+        res
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let operation = match operation_name {
+            Some(name) => {
+                let mut operations = OPERATIONS.lock().unwrap();
+                if operations.contains(name) || operations.len() < MAX_OPERATIONS {
+                    operations.insert(name.to_string());
+                    name
+                } else {
+                    "other"
+                }
+            }
+            None => "anonymous",
+        };
+        *self.operation.lock().unwrap() = Some(operation.to_string());
+        next.run(ctx, operation_name).await
+    }
+}
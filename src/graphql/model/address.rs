@@ -0,0 +1,89 @@
+use crate::infra::id::Id;
+use async_graphql::*;
+
+#[derive(SimpleObject, InputObject, Clone, Default)]
+#[graphql(input_name = "MailingAddressInput")]
+pub struct MailingAddress {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub company: Option<String>,
+    /// Street address.
+    pub line1: Option<String>,
+    /// Apartment, suite, unit, etc.
+    pub line2: Option<String>,
+    pub city: Option<String>,
+    /// Region, state or province.
+    pub province: Option<String>,
+    pub zip: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `US`.
+    pub country_code: Option<String>,
+}
+
+impl From<volo_gen::common::v1::MailingAddress> for MailingAddress {
+    fn from(value: volo_gen::common::v1::MailingAddress) -> Self {
+        Self {
+            first_name: value.first_name.map(Into::into),
+            last_name: value.last_name.map(Into::into),
+            company: value.company.map(Into::into),
+            line1: value.line1.map(Into::into),
+            line2: value.line2.map(Into::into),
+            city: value.city.map(Into::into),
+            province: value.province.map(Into::into),
+            zip: value.zip.map(Into::into),
+            country_code: value.country_code.map(Into::into),
+        }
+    }
+}
+
+impl From<MailingAddress> for volo_gen::common::v1::MailingAddress {
+    fn from(value: MailingAddress) -> Self {
+        Self {
+            first_name: value.first_name.map(Into::into),
+            last_name: value.last_name.map(Into::into),
+            company: value.company.map(Into::into),
+            line1: value.line1.map(Into::into),
+            line2: value.line2.map(Into::into),
+            city: value.city.map(Into::into),
+            province: value.province.map(Into::into),
+            zip: value.zip.map(Into::into),
+            country_code: value.country_code.map(Into::into),
+        }
+    }
+}
+
+/// An entry of the address book of a customer.
+pub struct CustomerAddress {
+    pub id: Id<CustomerAddress>,
+    pub customer_id: String,
+    pub address: MailingAddress,
+}
+
+#[derive(SimpleObject)]
+pub struct MutationCustomerAddress {
+    pub address: CustomerAddress,
+}
+
+impl From<volo_gen::address::v1::Address> for CustomerAddress {
+    fn from(value: volo_gen::address::v1::Address) -> Self {
+        Self {
+            id: value.id.into(),
+            customer_id: value.customer_id.into_string(),
+            address: value.address.into(),
+        }
+    }
+}
+
+#[Object]
+impl CustomerAddress {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    async fn customer_id(&self) -> &String {
+        &self.customer_id
+    }
+
+    async fn address(&self) -> &MailingAddress {
+        &self.address
+    }
+}
@@ -34,25 +34,38 @@ impl Cart {
     }
 
     // todo return map<CurrencyCode, Money>
-    async fn total_amount(&self) -> Money {
-        self.entries.iter().map(|v| v.calculate_amount()).sum()
+    async fn total_amount(&self) -> Result<Money> {
+        self.entries
+            .iter()
+            .map(|v| v.calculate_amount())
+            .sum::<Result<Money, Status>>()
+            .extend()
     }
 }
 
 impl CartEntry {
-    pub(crate) fn calculate_amount(&self) -> Money {
-        if self.product.variants.is_empty() {
-            return Money {
+    /// Status maybe returned:
+    /// 1. not_found, the selected variant is not among the loaded ones
+    pub(crate) fn calculate_amount(&self) -> Result<Money, Status> {
+        let variants = self.product.loaded_variants();
+        if variants.is_empty() {
+            return Ok(Money {
                 amount: BigDecimal::from(0),
                 currency_code: CurrencyCode::USD,
-            };
+            });
         }
-        let price = &self.product.variants[self.variant as usize].price;
+        let variant = variants.get(self.variant as usize).ok_or_else(|| {
+            Status::not_found(format!(
+                "variant at {} of product({})",
+                self.variant, self.product.id
+            ))
+        })?;
+        let price = &variant.price;
         let total = price.amount.clone().mul(BigDecimal::from(self.quantity));
-        Money {
+        Ok(Money {
             amount: total,
             currency_code: price.currency_code,
-        }
+        })
     }
 }
 
@@ -70,8 +83,8 @@ impl CartEntry {
         &self.product
     }
 
-    async fn total_amount(&self) -> Money {
-        self.calculate_amount()
+    async fn total_amount(&self) -> Result<Money> {
+        self.calculate_amount().extend()
     }
 
     async fn variant_at(&self) -> i32 {
@@ -1,8 +1,13 @@
+use crate::graphql::loader::{PaymentLoader, ShippingLoader};
+use crate::graphql::model::address::MailingAddress;
 use crate::graphql::model::cart::Cart;
 use crate::graphql::model::common::Money;
 use crate::infra::error::Status;
 use crate::infra::id::Id;
+use async_graphql::dataloader::DataLoader;
 use async_graphql::*;
+use std::num::ParseIntError;
+use volo_gen::checkout::v1::{CheckoutField, PutCheckout};
 
 #[derive(Copy, Clone)]
 #[repr(u32)]
@@ -28,6 +33,7 @@ impl TryFrom<i32> for CheckoutStatus {
     }
 }
 
+#[derive(Clone)]
 pub struct Shipping {
     pub id: Id<Shipping>,
     pub vendor: String,
@@ -42,6 +48,7 @@ impl From<volo_gen::checkout::v1::Shipping> for Shipping {
     }
 }
 
+#[derive(Clone)]
 pub struct Payment {
     pub id: Id<Payment>,
     pub vendor: String,
@@ -56,19 +63,89 @@ impl From<volo_gen::checkout::v1::Payment> for Payment {
     }
 }
 
+/// Information submitted to a checkout, fields left out are unchanged and fields
+/// set to `null` are cleared.
+#[derive(InputObject)]
+pub struct CheckoutInformationInput {
+    pub shipping_id: MaybeUndefined<String>,
+    pub payment_id: MaybeUndefined<String>,
+    pub email: MaybeUndefined<String>,
+    /// Phone number in international format, e.g. `+1 555 010 9999`.
+    pub phone: MaybeUndefined<String>,
+    /// Replaces the whole shipping address.
+    pub shipping_address: MaybeUndefined<MailingAddress>,
+    /// Replaces the whole billing address, `billingSameAsShipping` is turned off
+    /// unless it is submitted as well.
+    pub billing_address: MaybeUndefined<MailingAddress>,
+    /// Turning it on removes the billing address submitted before.
+    pub billing_same_as_shipping: Option<bool>,
+}
+
+/// Take the value of an input field, `null` is recorded as a field to clear.
+fn patch<T>(
+    value: MaybeUndefined<T>,
+    field: CheckoutField,
+    clear: &mut Vec<CheckoutField>,
+) -> Option<T> {
+    if value.is_null() {
+        clear.push(field);
+    }
+    value.take()
+}
+
+impl TryFrom<CheckoutInformationInput> for PutCheckout {
+    type Error = ParseIntError;
+
+    fn try_from(value: CheckoutInformationInput) -> std::result::Result<Self, Self::Error> {
+        let mut clear = vec![];
+        let sid: Option<Id<Shipping>> =
+            patch(value.shipping_id, CheckoutField::Shipping, &mut clear)
+                .map(|v| v.parse())
+                .transpose()?;
+        let pid: Option<Id<Payment>> = patch(value.payment_id, CheckoutField::Payment, &mut clear)
+            .map(|v| v.parse())
+            .transpose()?;
+        let contact_email = patch(value.email, CheckoutField::ContactEmail, &mut clear);
+        let receiver_phone = patch(value.phone, CheckoutField::ReceiverPhone, &mut clear);
+        let shipping_address = patch(
+            value.shipping_address,
+            CheckoutField::ShippingAddress,
+            &mut clear,
+        );
+        let billing_address = patch(
+            value.billing_address,
+            CheckoutField::BillingAddress,
+            &mut clear,
+        );
+        Ok(PutCheckout {
+            shipping_id: sid.map(|v| v.raw()),
+            payment_id: pid.map(|v| v.raw()),
+            contact_email: contact_email.map(Into::into),
+            receiver_phone: receiver_phone.map(Into::into),
+            shipping_address: shipping_address.map(Into::into),
+            billing_address: billing_address.map(Into::into),
+            billing_same_as_shipping: value.billing_same_as_shipping,
+            clear: Some(clear),
+        })
+    }
+}
+
 pub struct Checkout {
     pub id: Id<Checkout>,
     pub cart: Cart,
     pub status: CheckoutStatus,
+    pub shipping_id: Option<Id<Shipping>>,
+    pub payment_id: Option<Id<Payment>>,
+    // `None` means not loaded yet, they will be loaded by `ShippingLoader` and
+    // `PaymentLoader` when requested.
     pub shipping: Option<Shipping>,
     pub payment: Option<Payment>,
     pub shipping_fee: Option<Money>,
     pub contact_email: Option<String>,
-    pub receiver_country_code: Option<String>,
-    pub receiver_name: Option<String>,
-    pub receiver_address: Option<String>,
-    pub receiver_postcode: Option<String>,
     pub receiver_phone: Option<String>,
+    pub shipping_address: Option<MailingAddress>,
+    pub billing_address: Option<MailingAddress>,
+    pub billing_same_as_shipping: bool,
 }
 
 #[derive(SimpleObject)]
@@ -84,6 +161,8 @@ impl TryFrom<volo_gen::checkout::v1::Checkout> for Checkout {
             id: value.id.into(),
             cart: value.cart.try_into()?,
             status: value.status.try_into()?,
+            shipping_id: value.shipping_id.map(Into::into),
+            payment_id: value.payment_id.map(Into::into),
             shipping: value.shipping.map(Into::into),
             payment: value.payment.map(Into::into),
             shipping_fee: if let Some(fee) = value.shipping_fee {
@@ -92,11 +171,10 @@ impl TryFrom<volo_gen::checkout::v1::Checkout> for Checkout {
                 None
             },
             contact_email: value.contact_email.map(Into::into),
-            receiver_country_code: value.receiver_country_code.map(Into::into),
-            receiver_name: value.receiver_name.map(Into::into),
-            receiver_address: value.receiver_address.map(Into::into),
-            receiver_postcode: value.receiver_postcode.map(Into::into),
             receiver_phone: value.receiver_phone.map(Into::into),
+            shipping_address: value.shipping_address.map(Into::into),
+            billing_address: value.billing_address.map(Into::into),
+            billing_same_as_shipping: value.billing_same_as_shipping,
         })
     }
 }
@@ -137,12 +215,26 @@ impl Checkout {
         self.status as u32
     }
 
-    async fn shipping(&self) -> Option<&Shipping> {
-        self.shipping.as_ref()
+    async fn shipping<'ctx>(&self, cx: &Context<'ctx>) -> Result<Option<Shipping>> {
+        if self.shipping.is_some() {
+            return Ok(self.shipping.clone());
+        }
+        let Some(id) = self.shipping_id else {
+            return Ok(None);
+        };
+        let loader = cx.data::<DataLoader<ShippingLoader>>()?;
+        Ok(loader.load_one(id.raw()).await.extend()?.map(Into::into))
     }
 
-    async fn payment(&self) -> Option<&Payment> {
-        self.payment.as_ref()
+    async fn payment<'ctx>(&self, cx: &Context<'ctx>) -> Result<Option<Payment>> {
+        if self.payment.is_some() {
+            return Ok(self.payment.clone());
+        }
+        let Some(id) = self.payment_id else {
+            return Ok(None);
+        };
+        let loader = cx.data::<DataLoader<PaymentLoader>>()?;
+        Ok(loader.load_one(id.raw()).await.extend()?.map(Into::into))
     }
 
     async fn shipping_fee(&self) -> Option<&Money> {
@@ -153,28 +245,31 @@ impl Checkout {
         self.contact_email.as_ref()
     }
 
-    async fn country_code(&self) -> Option<&String> {
-        self.receiver_country_code.as_ref()
-    }
-
-    async fn name(&self) -> Option<&String> {
-        self.receiver_name.as_ref()
+    async fn phone(&self) -> Option<&String> {
+        self.receiver_phone.as_ref()
     }
 
-    async fn address(&self) -> Option<&String> {
-        self.receiver_address.as_ref()
+    async fn shipping_address(&self) -> Option<&MailingAddress> {
+        self.shipping_address.as_ref()
     }
 
-    async fn postcode(&self) -> Option<&String> {
-        self.receiver_postcode.as_ref()
+    /// The shipping address when `billingSameAsShipping`.
+    async fn billing_address(&self) -> Option<&MailingAddress> {
+        self.billing_address.as_ref()
     }
 
-    async fn phone(&self) -> Option<&String> {
-        self.receiver_phone.as_ref()
+    async fn billing_same_as_shipping(&self) -> bool {
+        self.billing_same_as_shipping
     }
 
-    async fn total_amount(&self) -> Option<Money> {
-        let sub_total = self.cart.entries.iter().map(|v| v.calculate_amount()).sum();
-        self.shipping_fee.clone().map(|fee| fee + sub_total)
+    async fn total_amount(&self) -> Result<Option<Money>> {
+        let sub_total = self
+            .cart
+            .entries
+            .iter()
+            .map(|v| v.calculate_amount())
+            .sum::<Result<Money, Status>>()
+            .extend()?;
+        Ok(self.shipping_fee.clone().map(|fee| fee + sub_total))
     }
 }
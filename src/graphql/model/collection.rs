@@ -0,0 +1,94 @@
+use crate::graphql::model::product::Product;
+use crate::graphql::Resolver;
+use crate::infra::error::Status;
+use crate::infra::id::Id;
+use crate::infra::mqsrs::Query;
+use async_graphql::connection::{query, Connection, Edge};
+use async_graphql::*;
+use volo_gen::common::v1::PaginationOption;
+
+pub struct Collection {
+    pub id: Id<Collection>,
+    pub handle: String,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(SimpleObject)]
+pub struct MutationCollection {
+    pub collection: Collection,
+}
+
+#[Object]
+impl Collection {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    async fn handle(&self) -> &String {
+        &self.handle
+    }
+
+    async fn title(&self) -> &String {
+        &self.title
+    }
+
+    async fn description(&self) -> &String {
+        &self.description
+    }
+
+    /// Products in this collection by manual ordering.
+    async fn products<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<i64, Product>> {
+        let resolver = cx.data::<Resolver>()?;
+        let queries = resolver.create_list_collection_products();
+        query(
+            after,
+            before,
+            first,
+            last,
+            |after, before, first, last| async move {
+                let res = queries
+                    .execute((
+                        self.id.raw(),
+                        PaginationOption {
+                            after,
+                            before,
+                            first: first.map(|v| v as i32),
+                            last: last.map(|v| v as i32),
+                            order_by: None,
+                        },
+                    ))
+                    .await
+                    .extend()?;
+                let mut conn = Connection::new(res.has_previous_page, res.has_next_page);
+                conn.edges.extend(
+                    res.products
+                        .into_iter()
+                        .map(|v| Ok(Edge::new(v.order_idx as i64, v.product.try_into()?)))
+                        .collect::<Result<Vec<_>, Status>>()
+                        .extend()?,
+                );
+                Ok::<_, Error>(conn)
+            },
+        )
+        .await
+    }
+}
+
+impl From<volo_gen::collection::v1::Collection> for Collection {
+    fn from(value: volo_gen::collection::v1::Collection) -> Self {
+        Self {
+            id: value.id.into(),
+            handle: value.handle.into_string(),
+            title: value.title.into_string(),
+            description: value.description.into_string(),
+        }
+    }
+}
@@ -26,6 +26,15 @@ impl FromStr for CurrencyCode {
     }
 }
 
+impl CurrencyCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CurrencyCode::USD => "USD",
+            CurrencyCode::CNY => "CNY",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Money {
     pub amount: BigDecimal,
@@ -106,13 +115,10 @@ impl MulAssign for Money {
 
 impl Sum for Money {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        if iter.size_hint().0 == 0 {
-            return Money {
-                amount: BigDecimal::from(0),
-                currency_code: CurrencyCode::USD,
-            };
-        }
-        iter.reduce(|lhs, rhs| lhs.add(rhs)).unwrap()
+        iter.reduce(|lhs, rhs| lhs.add(rhs)).unwrap_or(Money {
+            amount: BigDecimal::from(0),
+            currency_code: CurrencyCode::USD,
+        })
     }
 }
 
@@ -148,3 +154,13 @@ impl Image {
         self.order_idx
     }
 }
+
+impl From<volo_gen::common::v1::Image> for Image {
+    fn from(value: volo_gen::common::v1::Image) -> Self {
+        Self {
+            url: value.url.into_string(),
+            alt_text: value.alt_text.into_string(),
+            order_idx: value.order_idx,
+        }
+    }
+}
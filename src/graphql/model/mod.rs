@@ -1,32 +1,45 @@
+mod address;
 mod cart;
 mod checkout;
+mod collection;
 mod common;
 mod product;
 
+use crate::graphql::loader::ProductLoader;
+use crate::graphql::model::address::{CustomerAddress, MailingAddress, MutationCustomerAddress};
 use crate::graphql::model::cart::{Cart, MutationCart};
-use crate::graphql::model::checkout::{Checkout, MutationCheckout, Payment, Shipping};
-use crate::graphql::model::product::Product;
+use crate::graphql::model::checkout::{
+    Checkout, CheckoutInformationInput, MutationCheckout, Payment, Shipping,
+};
+use crate::graphql::model::collection::{Collection, MutationCollection};
+use crate::graphql::model::common::CurrencyCode;
+use crate::graphql::model::product::{MutationProduct, Product, ProductVariant, SelectedOption};
 use crate::graphql::Resolver;
 use crate::infra::error::{Code, Status};
 use crate::infra::id::Id;
 use crate::infra::mqsrs::*;
 use async_graphql::connection::{query, Connection, Edge};
+use async_graphql::dataloader::DataLoader;
 use async_graphql::*;
+use futures::{Stream, StreamExt};
 use volo_gen::checkout::v1::PutCheckout;
+use volo_gen::collection::v1::NewCollection;
 use volo_gen::common::v1::PaginationOption;
+use volo_gen::product::v1::NewProduct;
 
 pub struct GraphqlQuery;
 pub struct GraphqlMutation;
+pub struct GraphqlSubscription;
 
 macro_rules! map_not_found {
     ($res:tt) => {
         match $res {
-            Ok(v) => Ok(Some(v.try_into()?)),
+            Ok(v) => Ok(Some(v.try_into().map_err(Status::from).extend()?)),
             Err(e) => {
                 if e.code() == Code::NotFound {
                     return Ok(None);
                 }
-                Err(Error::from(e))
+                Err(e.extend())
             }
         }
     };
@@ -36,9 +49,21 @@ macro_rules! map_not_found {
 impl GraphqlQuery {
     async fn product<'ctx>(&self, cx: &Context<'ctx>, id: String) -> Result<Option<Product>> {
         let id: Id<Product> = id.parse()?;
+        let loader = cx.data::<DataLoader<ProductLoader>>()?;
+        let res = loader.load_one(id.raw()).await.extend()?;
+        res.map(TryInto::try_into).transpose().extend()
+    }
+
+    /// Find a product by its handle, previous handles of a product are redirected
+    /// to it, compare with `Product.handle` to find the canonical one.
+    async fn product_by_handle<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        handle: String,
+    ) -> Result<Option<Product>> {
         let resolver = cx.data::<Resolver>()?;
-        let query = resolver.create_get_product();
-        let res = query.execute(id.raw()).await;
+        let query = resolver.create_get_product_by_handle();
+        let res = query.execute(handle).await;
         map_not_found!(res)
     }
 
@@ -66,13 +91,64 @@ impl GraphqlQuery {
                         last: last.map(|v| v as i32),
                         order_by: None,
                     })
-                    .await?;
+                    .await
+                    .extend()?;
                 let mut conn = Connection::new(res.has_previous_page, res.has_next_page);
                 conn.edges.extend(
                     res.products
                         .into_iter()
                         .map(|product| Ok(Edge::new(product.id, product.try_into()?)))
-                        .collect::<Result<Vec<_>, Status>>()?,
+                        .collect::<Result<Vec<_>, Status>>()
+                        .extend()?,
+                );
+                Ok::<_, Error>(conn)
+            },
+        )
+        .await
+    }
+
+    async fn collection<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        handle: String,
+    ) -> Result<Option<Collection>> {
+        let resolver = cx.data::<Resolver>()?;
+        let query = resolver.create_get_collection();
+        let res = query.execute(handle).await;
+        map_not_found!(res)
+    }
+
+    async fn collections<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+    ) -> Result<Connection<i64, Collection>> {
+        let resolver = cx.data::<Resolver>()?;
+        let queries = resolver.create_list_collection();
+        query(
+            after,
+            before,
+            first,
+            last,
+            |after, before, first, last| async move {
+                let res = queries
+                    .execute(PaginationOption {
+                        after,
+                        before,
+                        first: first.map(|v| v as i32),
+                        last: last.map(|v| v as i32),
+                        order_by: None,
+                    })
+                    .await
+                    .extend()?;
+                let mut conn = Connection::new(res.has_previous_page, res.has_next_page);
+                conn.edges.extend(
+                    res.collections
+                        .into_iter()
+                        .map(|collection| Edge::new(collection.id, collection.into())),
                 );
                 Ok::<_, Error>(conn)
             },
@@ -111,26 +187,81 @@ impl GraphqlQuery {
     async fn shipping_methods<'ctx>(&self, cx: &Context<'ctx>) -> Result<Vec<Shipping>> {
         let resolver = cx.data::<Resolver>()?;
         let list = resolver.create_list_shipping();
-        let res = list.execute(()).await?;
+        let res = list.execute(()).await.extend()?;
         Ok(res.into_iter().map(Into::into).collect())
     }
 
     async fn payment_methods<'ctx>(&self, cx: &Context<'ctx>) -> Result<Vec<Payment>> {
         let resolver = cx.data::<Resolver>()?;
         let list = resolver.create_list_payment();
-        let res = list.execute(()).await?;
+        let res = list.execute(()).await.extend()?;
+        Ok(res.into_iter().map(Into::into).collect())
+    }
+
+    /// The address book of a customer.
+    async fn customer_addresses<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        customer_id: String,
+    ) -> Result<Vec<CustomerAddress>> {
+        let resolver = cx.data::<Resolver>()?;
+        let list = resolver.create_list_addresses();
+        let res = list.execute(customer_id).await.extend()?;
         Ok(res.into_iter().map(Into::into).collect())
     }
 }
 
 #[Object]
 impl GraphqlMutation {
+    /// Create a product, the handle will be generated from title if it is not specified.
+    async fn create_product<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        title: String,
+        sub_title: Option<String>,
+        description: Option<String>,
+        currency_code: CurrencyCode,
+        handle: Option<String>,
+    ) -> Result<MutationProduct> {
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_create_product();
+        let product = mutate
+            .execute(NewProduct {
+                title: title.into(),
+                sub_title: sub_title.map(Into::into),
+                description: description.map(Into::into),
+                currency_code: currency_code.as_str().into(),
+                handle: handle.map(Into::into),
+            })
+            .await
+            .extend()?;
+        Ok(MutationProduct {
+            product: product.try_into().extend()?,
+        })
+    }
+
+    /// Change the handle of a product, the previous handle keeps redirecting to it.
+    async fn change_product_handle<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        id: String,
+        handle: String,
+    ) -> Result<MutationProduct> {
+        let id: Id<Product> = id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_change_product_handle();
+        let product = mutate.execute((id.raw(), handle)).await.extend()?;
+        Ok(MutationProduct {
+            product: product.try_into().extend()?,
+        })
+    }
+
     async fn create_cart<'ctx>(&self, cx: &Context<'ctx>) -> Result<MutationCart> {
         let resolver = cx.data::<Resolver>()?;
         let mutate = resolver.create_create_cart();
-        let cart = mutate.execute(()).await?;
+        let cart = mutate.execute(()).await.extend()?;
         Ok(MutationCart {
-            cart: cart.try_into()?,
+            cart: cart.try_into().extend()?,
         })
     }
 
@@ -144,9 +275,12 @@ impl GraphqlMutation {
         let variant_id: Id<Cart> = variant_id.parse()?;
         let resolver = cx.data::<Resolver>()?;
         let mutate = resolver.create_add_to_cart();
-        let cart = mutate.execute((cart_id.raw(), variant_id.raw())).await?;
+        let cart = mutate
+            .execute((cart_id.raw(), variant_id.raw()))
+            .await
+            .extend()?;
         Ok(MutationCart {
-            cart: cart.try_into()?,
+            cart: cart.try_into().extend()?,
         })
     }
 
@@ -160,9 +294,12 @@ impl GraphqlMutation {
         let entry_id: Id<Cart> = entry_id.parse()?;
         let resolver = cx.data::<Resolver>()?;
         let mutate = resolver.create_remove_from_cart();
-        let cart = mutate.execute((cart_id.raw(), entry_id.raw())).await?;
+        let cart = mutate
+            .execute((cart_id.raw(), entry_id.raw()))
+            .await
+            .extend()?;
         Ok(MutationCart {
-            cart: cart.try_into()?,
+            cart: cart.try_into().extend()?,
         })
     }
 
@@ -174,56 +311,257 @@ impl GraphqlMutation {
         let cart_id: Id<Cart> = cart_id.parse()?;
         let resolver = cx.data::<Resolver>()?;
         let mutate = resolver.create_create_checkout();
-        let checkout = mutate.execute(cart_id.raw()).await?;
+        let checkout = mutate.execute(cart_id.raw()).await.extend()?;
         Ok(MutationCheckout {
-            checkout: checkout.try_into()?,
+            checkout: checkout.try_into().extend()?,
         })
     }
 
-    #[allow(clippy::too_many_arguments)]
     async fn submit_information<'ctx>(
         &self,
         cx: &Context<'ctx>,
         id: String,
-        shipping_id: Option<String>,
-        payment_id: Option<String>,
-        email: Option<String>,
-        name: Option<String>,
-        country_code: Option<String>,
-        address: Option<String>,
-        postcode: Option<String>,
-        phone: Option<String>,
+        input: CheckoutInformationInput,
     ) -> Result<MutationCheckout> {
         let id: Id<Checkout> = id.parse()?;
-        let sid: Option<Id<Shipping>> = if let Some(sid) = shipping_id {
-            Some(sid.parse()?)
-        } else {
-            None
-        };
-        let pid: Option<Id<Shipping>> = if let Some(pid) = payment_id {
-            Some(pid.parse()?)
-        } else {
-            None
-        };
+        let put: PutCheckout = input.try_into()?;
         let resolver = cx.data::<Resolver>()?;
         let mutate = resolver.create_submit_information();
-        let checkout = mutate
+        let checkout = mutate.execute((id.raw(), put)).await.extend()?;
+        Ok(MutationCheckout {
+            checkout: checkout.try_into().extend()?,
+        })
+    }
+
+    /// Add an address to the address book of a customer, `lastName`, `line1`,
+    /// `city` and `countryCode` are required.
+    async fn create_customer_address<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        customer_id: String,
+        address: MailingAddress,
+    ) -> Result<MutationCustomerAddress> {
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_create_address();
+        let address = mutate
+            .execute((customer_id, address.into()))
+            .await
+            .extend()?;
+        Ok(MutationCustomerAddress {
+            address: address.into(),
+        })
+    }
+
+    /// Replace an address of the address book of a customer.
+    async fn update_customer_address<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        customer_id: String,
+        id: String,
+        address: MailingAddress,
+    ) -> Result<MutationCustomerAddress> {
+        let id: Id<CustomerAddress> = id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_update_address();
+        let address = mutate
+            .execute((customer_id, id.raw(), address.into()))
+            .await
+            .extend()?;
+        Ok(MutationCustomerAddress {
+            address: address.into(),
+        })
+    }
+
+    /// Remove an address from the address book of a customer.
+    async fn delete_customer_address<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        customer_id: String,
+        id: String,
+    ) -> Result<MutationCustomerAddress> {
+        let id: Id<CustomerAddress> = id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_delete_address();
+        let address = mutate.execute((customer_id, id.raw())).await.extend()?;
+        Ok(MutationCustomerAddress {
+            address: address.into(),
+        })
+    }
+
+    /// Append an option with its values to a product, e.g. `Edition: [Standard, Collector]`.
+    async fn create_product_option<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        product_id: String,
+        name: String,
+        values: Vec<String>,
+    ) -> Result<MutationProduct> {
+        let product_id: Id<Product> = product_id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_create_product_option();
+        let product = mutate
+            .execute((product_id.raw(), name, values))
+            .await
+            .extend()?;
+        Ok(MutationProduct {
+            product: product.try_into().extend()?,
+        })
+    }
+
+    /// Select option values for a variant, every option of the product must be selected
+    /// and no other variant of the product may select the same combination.
+    async fn set_variant_options<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        variant_id: String,
+        selected_options: Vec<SelectedOption>,
+    ) -> Result<MutationProduct> {
+        let variant_id: Id<ProductVariant> = variant_id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_set_variant_options();
+        let product = mutate
             .execute((
-                id.raw(),
-                PutCheckout {
-                    shipping_id: sid.map(|v| v.raw()),
-                    payment_id: pid.map(|v| v.raw()),
-                    contact_email: email.map(Into::into),
-                    receiver_name: name.map(Into::into),
-                    receiver_country_code: country_code.map(Into::into),
-                    receiver_address: address.map(Into::into),
-                    receiver_postcode: postcode.map(Into::into),
-                    receiver_phone: phone.map(Into::into),
-                },
+                variant_id.raw(),
+                selected_options.into_iter().map(Into::into).collect(),
             ))
-            .await?;
-        Ok(MutationCheckout {
-            checkout: checkout.try_into()?,
+            .await
+            .extend()?;
+        Ok(MutationProduct {
+            product: product.try_into().extend()?,
+        })
+    }
+
+    async fn create_collection<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        handle: String,
+        title: String,
+        description: Option<String>,
+    ) -> Result<MutationCollection> {
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_create_collection();
+        let collection = mutate
+            .execute(NewCollection {
+                handle: handle.into(),
+                title: title.into(),
+                description: description.map(Into::into),
+            })
+            .await
+            .extend()?;
+        Ok(MutationCollection {
+            collection: collection.into(),
+        })
+    }
+
+    async fn add_products_to_collection<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        collection_id: String,
+        product_ids: Vec<String>,
+    ) -> Result<MutationCollection> {
+        let collection_id: Id<Collection> = collection_id.parse()?;
+        let product_ids = product_ids
+            .iter()
+            .map(|v| v.parse::<Id<Product>>().map(|id| id.raw()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_add_to_collection();
+        let collection = mutate
+            .execute((collection_id.raw(), product_ids))
+            .await
+            .extend()?;
+        Ok(MutationCollection {
+            collection: collection.into(),
+        })
+    }
+
+    async fn remove_products_from_collection<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        collection_id: String,
+        product_ids: Vec<String>,
+    ) -> Result<MutationCollection> {
+        let collection_id: Id<Collection> = collection_id.parse()?;
+        let product_ids = product_ids
+            .iter()
+            .map(|v| v.parse::<Id<Product>>().map(|id| id.raw()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_remove_from_collection();
+        let collection = mutate
+            .execute((collection_id.raw(), product_ids))
+            .await
+            .extend()?;
+        Ok(MutationCollection {
+            collection: collection.into(),
         })
     }
+
+    /// Move the given products to the front of the collection in the given order.
+    async fn reorder_collection_products<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        collection_id: String,
+        product_ids: Vec<String>,
+    ) -> Result<MutationCollection> {
+        let collection_id: Id<Collection> = collection_id.parse()?;
+        let product_ids = product_ids
+            .iter()
+            .map(|v| v.parse::<Id<Product>>().map(|id| id.raw()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let resolver = cx.data::<Resolver>()?;
+        let mutate = resolver.create_reorder_collection();
+        let collection = mutate
+            .execute((collection_id.raw(), product_ids))
+            .await
+            .extend()?;
+        Ok(MutationCollection {
+            collection: collection.into(),
+        })
+    }
+}
+
+#[Subscription]
+impl GraphqlSubscription {
+    /// Push the cart whenever its entries change.
+    async fn cart_updated<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        id: String,
+    ) -> Result<impl Stream<Item = Result<Cart>>> {
+        let id: Id<Cart> = id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let subscription = resolver.create_subscribe_cart();
+        let carts = subscription.execute(id.raw()).await;
+        Ok(carts.map(|res| res.and_then(TryInto::try_into).extend()))
+    }
+
+    /// Push the checkout whenever it or its cart changes, e.g. the payment is confirmed.
+    async fn checkout_updated<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        id: String,
+    ) -> Result<impl Stream<Item = Result<Checkout>>> {
+        let id: Id<Checkout> = id.parse()?;
+        let resolver = cx.data::<Resolver>()?;
+        let subscription = resolver.create_subscribe_checkout();
+        let checkouts = subscription.execute(id.raw()).await;
+        Ok(checkouts.map(|res| res.and_then(TryInto::try_into).extend()))
+    }
+
+    /// Push a variant whenever its inventory changes, e.g. it runs out of stock.
+    async fn variant_inventory_changed<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        variant_ids: Vec<String>,
+    ) -> Result<impl Stream<Item = Result<ProductVariant>>> {
+        let ids = variant_ids
+            .iter()
+            .map(|id| Ok(id.parse::<Id<ProductVariant>>()?.raw()))
+            .collect::<Result<Vec<_>>>()?;
+        let resolver = cx.data::<Resolver>()?;
+        let subscription = resolver.create_subscribe_variant_inventory();
+        let variants = subscription.execute(ids).await;
+        Ok(variants.map(|res| res.and_then(TryInto::try_into).extend()))
+    }
 }
@@ -1,6 +1,8 @@
+use crate::graphql::loader::{ImageLoader, VariantLoader};
 use crate::graphql::model::common::{CurrencyCode, Image, Money};
 use crate::infra::error::Status;
 use crate::infra::id::Id;
+use async_graphql::dataloader::DataLoader;
 use async_graphql::*;
 use bigdecimal::BigDecimal;
 
@@ -11,16 +13,40 @@ pub struct ProductVariant {
     pub title: String,
     pub inventory_count: i32,
     pub order_idx: i32,
+    pub selected_options: Vec<SelectedOption>,
+}
+
+#[derive(Clone)]
+pub struct ProductOption {
+    pub id: Id<ProductOption>,
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+#[derive(SimpleObject, InputObject, Clone, Eq, PartialEq)]
+#[graphql(input_name = "SelectedOptionInput")]
+pub struct SelectedOption {
+    pub name: String,
+    pub value: String,
 }
 
 #[derive(Clone)]
 pub struct Product {
     pub id: Id<Product>,
+    pub handle: String,
     pub title: String,
     pub sub_title: String,
     pub description: String,
-    pub images: Vec<Image>,
-    pub variants: Vec<ProductVariant>,
+    // `None` means not loaded yet, they will be loaded by `ImageLoader` and
+    // `VariantLoader` when requested.
+    pub images: Option<Vec<Image>>,
+    pub variants: Option<Vec<ProductVariant>>,
+    pub options: Option<Vec<ProductOption>>,
+}
+
+#[derive(SimpleObject)]
+pub struct MutationProduct {
+    pub product: Product,
 }
 
 #[derive(SimpleObject)]
@@ -54,6 +80,25 @@ impl ProductVariant {
     async fn order_idx(&self) -> i32 {
         self.order_idx
     }
+
+    async fn selected_options(&self) -> &[SelectedOption] {
+        self.selected_options.as_slice()
+    }
+}
+
+#[Object]
+impl ProductOption {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    async fn name(&self) -> &String {
+        &self.name
+    }
+
+    async fn values(&self) -> &[String] {
+        self.values.as_slice()
+    }
 }
 
 #[Object]
@@ -62,6 +107,10 @@ impl Product {
         self.id.to_string()
     }
 
+    async fn handle(&self) -> &String {
+        &self.handle
+    }
+
     async fn title(&self) -> &String {
         &self.title
     }
@@ -74,24 +123,42 @@ impl Product {
         &self.description
     }
 
-    async fn featured_image(&self) -> Option<&Image> {
-        if self.images.is_empty() {
-            return None;
-        }
-        self.images.iter().find(|v| v.order_idx == 0)
+    async fn featured_image<'ctx>(&self, cx: &Context<'ctx>) -> Result<Option<Image>> {
+        let images = self.load_images(cx).await?;
+        Ok(images.into_iter().find(|v| v.order_idx == 0))
     }
 
-    async fn images(&self) -> &[Image] {
-        self.images.as_slice()
+    async fn images<'ctx>(&self, cx: &Context<'ctx>) -> Result<Vec<Image>> {
+        self.load_images(cx).await
     }
 
-    async fn variants(&self) -> &[ProductVariant] {
-        self.variants.as_slice()
+    async fn variants<'ctx>(&self, cx: &Context<'ctx>) -> Result<Vec<ProductVariant>> {
+        Ok(self.load_variants(cx).await?.1)
     }
 
-    async fn price_range(&self) -> PriceRange {
-        if self.variants.is_empty() {
-            return PriceRange {
+    async fn options<'ctx>(&self, cx: &Context<'ctx>) -> Result<Vec<ProductOption>> {
+        Ok(self.load_variants(cx).await?.0)
+    }
+
+    /// Find the variant which selects exactly the given options, e.g. `Edition=Collector`.
+    async fn variant_by_selected_options<'ctx>(
+        &self,
+        cx: &Context<'ctx>,
+        selected_options: Vec<SelectedOption>,
+    ) -> Result<Option<ProductVariant>> {
+        let (_, variants) = self.load_variants(cx).await?;
+        Ok(variants.into_iter().find(|variant| {
+            variant.selected_options.len() == selected_options.len()
+                && selected_options
+                    .iter()
+                    .all(|v| variant.selected_options.contains(v))
+        }))
+    }
+
+    async fn price_range<'ctx>(&self, cx: &Context<'ctx>) -> Result<PriceRange> {
+        let (_, variants) = self.load_variants(cx).await?;
+        if variants.is_empty() {
+            return Ok(PriceRange {
                 max_variant_price: Money {
                     amount: BigDecimal::from(0),
                     currency_code: CurrencyCode::USD,
@@ -100,22 +167,65 @@ impl Product {
                     amount: BigDecimal::from(0),
                     currency_code: CurrencyCode::USD,
                 },
-            };
+            });
         }
         let mut min_at: usize = 0;
         let mut max_at: usize = 0;
-        for (idx, variant) in self.variants.iter().enumerate() {
-            if variant.price > self.variants[max_at].price {
+        for (idx, variant) in variants.iter().enumerate() {
+            if variant.price > variants[max_at].price {
                 max_at = idx;
             }
-            if variant.price < self.variants[min_at].price {
+            if variant.price < variants[min_at].price {
                 min_at = idx;
             }
         }
-        PriceRange {
-            max_variant_price: self.variants[max_at].price.clone(),
-            min_variant_price: self.variants[min_at].price.clone(),
+        Ok(PriceRange {
+            max_variant_price: variants[max_at].price.clone(),
+            min_variant_price: variants[min_at].price.clone(),
+        })
+    }
+}
+
+impl Product {
+    /// Variants that have been loaded along with this product.
+    pub(crate) fn loaded_variants(&self) -> &[ProductVariant] {
+        self.variants.as_deref().unwrap_or_default()
+    }
+
+    async fn load_images(&self, cx: &Context<'_>) -> Result<Vec<Image>> {
+        if let Some(images) = &self.images {
+            return Ok(images.clone());
+        }
+        let loader = cx.data::<DataLoader<ImageLoader>>()?;
+        let images = loader
+            .load_one(self.id.raw())
+            .await
+            .extend()?
+            .unwrap_or_default();
+        Ok(images.into_iter().map(Into::into).collect())
+    }
+
+    async fn load_variants(
+        &self,
+        cx: &Context<'_>,
+    ) -> Result<(Vec<ProductOption>, Vec<ProductVariant>)> {
+        if let (Some(options), Some(variants)) = (&self.options, &self.variants) {
+            return Ok((options.clone(), variants.clone()));
         }
+        let loader = cx.data::<DataLoader<VariantLoader>>()?;
+        let (options, variants) = loader
+            .load_one(self.id.raw())
+            .await
+            .extend()?
+            .unwrap_or_default();
+        Ok((
+            options.into_iter().map(Into::into).collect(),
+            variants
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<_>, Status>>()
+                .extend()?,
+        ))
     }
 }
 
@@ -125,31 +235,64 @@ impl TryFrom<volo_gen::product::v1::Product> for Product {
     fn try_from(value: volo_gen::product::v1::Product) -> std::result::Result<Self, Self::Error> {
         Ok(Self {
             id: value.id.into(),
+            handle: value.handle.into_string(),
             title: value.title.into_string(),
             sub_title: value.sub_title.into_string(),
             description: value.description.into_string(),
-            images: value
-                .images
-                .into_iter()
-                .map(|v| Image {
-                    url: v.url.into_string(),
-                    alt_text: v.alt_text.into_string(),
-                    order_idx: v.order_idx,
-                })
-                .collect(),
-            variants: value
-                .variants
-                .into_iter()
-                .map(|v| {
-                    Ok(ProductVariant {
-                        id: v.id.into(),
-                        price: v.price.try_into()?,
-                        title: v.title.into_string(),
-                        inventory_count: v.inventory_count,
-                        order_idx: v.order_idx,
-                    })
-                })
-                .collect::<Result<Vec<_>, Status>>()?,
+            images: Some(value.images.into_iter().map(Into::into).collect()),
+            variants: Some(
+                value
+                    .variants
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<_>, Status>>()?,
+            ),
+            options: Some(value.options.into_iter().map(Into::into).collect()),
         })
     }
 }
+
+impl TryFrom<volo_gen::product::v1::ProductVariant> for ProductVariant {
+    type Error = Status;
+
+    fn try_from(
+        value: volo_gen::product::v1::ProductVariant,
+    ) -> std::result::Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id.into(),
+            price: value.price.try_into()?,
+            title: value.title.into_string(),
+            inventory_count: value.inventory_count,
+            order_idx: value.order_idx,
+            selected_options: value.selected_options.into_iter().map(Into::into).collect(),
+        })
+    }
+}
+
+impl From<volo_gen::product::v1::ProductOption> for ProductOption {
+    fn from(value: volo_gen::product::v1::ProductOption) -> Self {
+        Self {
+            id: value.id.into(),
+            name: value.name.into_string(),
+            values: value.values.into_iter().map(|v| v.into_string()).collect(),
+        }
+    }
+}
+
+impl From<volo_gen::product::v1::SelectedOption> for SelectedOption {
+    fn from(value: volo_gen::product::v1::SelectedOption) -> Self {
+        Self {
+            name: value.name.into_string(),
+            value: value.value.into_string(),
+        }
+    }
+}
+
+impl From<SelectedOption> for volo_gen::product::v1::SelectedOption {
+    fn from(value: SelectedOption) -> Self {
+        Self {
+            name: value.name.into(),
+            value: value.value.into(),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The ids of the graphql requests as a poem middleware, a request is served in
+//! the scope of the id of its `X-Request-Id` header, or of a new one, and the id is
+//! answered in the same header, see [crate::infra::telemetry::log].
+
+use crate::infra::telemetry::log::{self, REQUEST_ID};
+use poem::http::HeaderValue;
+use poem::{async_trait, Endpoint, IntoResponse, Middleware, Request, Response};
+use std::time::Instant;
+
+pub struct RequestId;
+
+impl<E: Endpoint> Middleware<E> for RequestId {
+    type Output = RequestIdEndpoint<E>;
+
+    fn transform(&self, inner: E) -> Self::Output {
+        RequestIdEndpoint { inner }
+    }
+}
+
+pub struct RequestIdEndpoint<E> {
+    inner: E,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for RequestIdEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let given = req.headers().get(REQUEST_ID).and_then(|v| v.to_str().ok());
+        let id = log::request_id(given);
+        let header = HeaderValue::from_str(&id).ok();
+        let (method, path) = (req.method().clone(), req.uri().path().to_string());
+        log::scope(id, async move {
+            let start = Instant::now();
+            let mut res = match self.inner.call(req).await {
+                Ok(res) => res.into_response(),
+                Err(err) => err.into_response(),
+            };
+            tracing::info!(
+                %method,
+                path,
+                status = res.status().as_u16(),
+                elapsed_ms = start.elapsed().as_millis() as u64,
+                "request served"
+            );
+            if let Some(header) = header {
+                res.headers_mut().insert(REQUEST_ID, header);
+            }
+            Ok(res)
+        })
+        .await
+    }
+}
@@ -1,8 +1,60 @@
+use crate::graphql::limit::Client;
+use crate::graphql::{GraphqlSchema, Resolver};
+use crate::infra::health::Readiness;
 use async_graphql::http::GraphiQLSource;
-use poem::web::Html;
+use async_graphql_poem::{GraphQLRequest, GraphQLResponse};
+use poem::http::StatusCode;
+use poem::web::{Data, Html, Json};
 use poem::*;
 
 #[handler]
 pub async fn graphiql() -> impl IntoResponse {
-    Html(GraphiQLSource::build().endpoint("/").finish())
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/")
+            .subscription_endpoint("/graphql/ws")
+            .finish(),
+    )
+}
+
+/// Execute a graphql request of the client, see [crate::graphql::limit::FieldLimit].
+#[handler]
+pub async fn graphql(
+    schema: Data<&GraphqlSchema>,
+    client: Client,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema.execute(req.0.data(client)).await.into()
+}
+
+/// Liveness, the service answers.
+#[handler]
+pub fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness, the dependencies are available, see [crate::infra::health]. Answered
+/// with 503 when they are not, the checks tell which ones failed.
+#[handler]
+pub async fn readyz(resolver: Data<&Resolver>) -> poem::Result<Response> {
+    let readiness: Readiness = resolver.readiness().await.map_err(|status| {
+        poem::Error::from_string(status.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(Json(readiness).with_status(status).into_response())
+}
+
+/// The metrics in the text format of Prometheus, see [crate::infra::metrics].
+#[handler]
+pub async fn metrics(resolver: Data<&Resolver>) -> poem::Result<Response> {
+    resolver.observe_pools().await.map_err(|status| {
+        poem::Error::from_string(status.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+    Ok(crate::infra::metrics::render()
+        .with_content_type("text/plain; version=0.0.4")
+        .into_response())
 }
@@ -0,0 +1,176 @@
+//! The trace context of the graphql requests as a poem middleware, the spans of a
+//! request are children of the `traceparent` of its headers, see
+//! [crate::infra::telemetry]. The spans of the request are started by [Spans].
+
+use crate::infra::telemetry;
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextExecute, NextParseQuery, NextRequest,
+    NextResolve, NextSubscribe, NextValidation, ResolveInfo,
+};
+use async_graphql::parser::types::ExecutableDocument;
+use async_graphql::{ServerError, ServerResult, ValidationResult, Value, Variables};
+use futures::stream::BoxStream;
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{FutureExt, SpanKind, TraceContextExt};
+use opentelemetry::{Context, KeyValue};
+use poem::http::HeaderMap;
+use poem::{async_trait, Endpoint, IntoResponse, Middleware, Request, Response};
+use std::sync::Arc;
+
+pub struct Trace;
+
+impl<E: Endpoint> Middleware<E> for Trace {
+    type Output = TraceEndpoint<E>;
+
+    fn transform(&self, inner: E) -> Self::Output {
+        TraceEndpoint { inner }
+    }
+}
+
+pub struct TraceEndpoint<E> {
+    inner: E,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for TraceEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&Headers(req.headers()))
+        });
+        let res = self.inner.call(req).with_context(parent).await;
+        res.map(IntoResponse::into_response)
+    }
+}
+
+struct Headers<'a>(&'a HeaderMap);
+
+impl Extractor for Headers<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// The spans of the steps of a graphql request and of its fields, introspection
+/// excluded, named like the ones of the OpenTelemetry extension of async-graphql.
+pub struct Spans;
+
+impl ExtensionFactory for Spans {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(Spans)
+    }
+}
+
+fn server_span(name: impl Into<std::borrow::Cow<'static, str>>) -> Context {
+    telemetry::span(name, SpanKind::Server)
+}
+
+#[async_trait::async_trait]
+impl Extension for Spans {
+    async fn request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextRequest<'_>,
+    ) -> async_graphql::Response {
+        next.run(ctx).with_context(server_span("request")).await
+    }
+
+    fn subscribe<'s>(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        stream: BoxStream<'s, async_graphql::Response>,
+        next: NextSubscribe<'_>,
+    ) -> BoxStream<'s, async_graphql::Response> {
+        Box::pin(next.run(ctx, stream).with_context(server_span("subscribe")))
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        async move {
+            let res = next.run(ctx, query, variables).await;
+            if let Ok(doc) = &res {
+                let source = ctx.stringify_execute_doc(doc, variables);
+                Context::current()
+                    .span()
+                    .set_attribute(KeyValue::new("graphql.source", source));
+            }
+            res
+        }
+        .with_context(server_span("parse"))
+        .await
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        async move {
+            let res = next.run(ctx).await;
+            if let Ok(res) = &res {
+                let cx = Context::current();
+                let span = cx.span();
+                span.set_attribute(KeyValue::new("graphql.complexity", res.complexity as i64));
+                span.set_attribute(KeyValue::new("graphql.depth", res.depth as i64));
+            }
+            res
+        }
+        .with_context(server_span("validation"))
+        .await
+    }
+
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> async_graphql::Response {
+        next.run(ctx, operation_name)
+            .with_context(server_span("execute"))
+            .await
+    }
+
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.is_for_introspection {
+            return next.run(ctx, info).await;
+        }
+        let cx = server_span(info.path_node.to_string());
+        let span = cx.span();
+        span.set_attribute(KeyValue::new(
+            "graphql.parentType",
+            info.parent_type.to_string(),
+        ));
+        span.set_attribute(KeyValue::new(
+            "graphql.returnType",
+            info.return_type.to_string(),
+        ));
+        async move {
+            let res = next.run(ctx, info).await;
+            if let Err(err) = &res {
+                Context::current().span().add_event(
+                    "error",
+                    vec![KeyValue::new("graphql.error", err.message.clone())],
+                );
+            }
+            res
+        }
+        .with_context(cx)
+        .await
+    }
+}
@@ -0,0 +1,141 @@
+//! Read-through cache of thrift messages in redis.
+//!
+//! The cache is best-effort, redis errors are logged and the value is loaded
+//! from the source instead, a request never fails because of the cache.
+
+use crate::infra::error::Result;
+use bytes::BytesMut;
+use pilota::thrift::binary::TBinaryProtocol;
+use pilota::thrift::Message;
+use redis::{Commands, Connection, RedisResult};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::DerefMut;
+
+pub struct Cache<C> {
+    conn: Option<C>,
+}
+
+impl<C: DerefMut<Target = Connection>> Cache<C> {
+    /// Create a cache, `None` means redis is unavailable and every fetch is a miss.
+    pub fn new(conn: Option<C>) -> Self {
+        Self { conn }
+    }
+
+    /// Read `key` from the cache, or `load` and store it for `ttl` seconds.
+    pub fn fetch<M: Message>(
+        &mut self,
+        key: &str,
+        ttl: u64,
+        load: impl FnOnce() -> Result<M>,
+    ) -> Result<M> {
+        if let Some(msg) = self.get(key) {
+            return Ok(msg);
+        }
+        let msg = load()?;
+        self.set(key, ttl, &msg);
+        Ok(msg)
+    }
+
+    /// Like [Cache::fetch] for many values, only the missing ones are loaded.
+    pub fn fetch_many<K, M>(
+        &mut self,
+        ids: Vec<K>,
+        key: impl Fn(&K) -> String,
+        ttl: u64,
+        load: impl FnOnce(Vec<K>) -> Result<HashMap<K, M>>,
+    ) -> Result<HashMap<K, M>>
+    where
+        K: Hash + Eq,
+        M: Message,
+    {
+        let keys = ids.iter().map(&key).collect::<Vec<_>>();
+        let mut cached = self
+            .call(|conn| {
+                redis::cmd("MGET")
+                    .arg(&keys)
+                    .query::<Vec<Option<Vec<u8>>>>(conn)
+            })
+            .unwrap_or_default()
+            .into_iter();
+        let mut found = HashMap::new();
+        let mut missing = Vec::new();
+        for (id, key) in ids.into_iter().zip(&keys) {
+            match cached
+                .next()
+                .flatten()
+                .and_then(|buf| self.decode(key, buf))
+            {
+                Some(msg) => {
+                    found.insert(id, msg);
+                }
+                None => missing.push(id),
+            }
+        }
+        if !missing.is_empty() {
+            for (id, msg) in load(missing)? {
+                self.set(&key(&id), ttl, &msg);
+                found.insert(id, msg);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Current version of a namespace, prefix the keys with it so that
+    /// [Cache::bump] drops every entry of the namespace at once.
+    pub fn version(&mut self, namespace: &str) -> u64 {
+        let key = format!("{namespace}:version");
+        self.call(|conn| conn.get::<_, Option<u64>>(&key))
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Drop every entry of the namespace, the stale ones expire by their ttl.
+    pub fn bump(&mut self, namespace: &str) {
+        let key = format!("{namespace}:version");
+        self.call(|conn| conn.incr::<_, _, u64>(&key, 1));
+    }
+
+    pub fn evict(&mut self, key: &str) {
+        self.call(|conn| conn.del::<_, ()>(key));
+    }
+
+    fn get<M: Message>(&mut self, key: &str) -> Option<M> {
+        let buf = self.call(|conn| conn.get::<_, Option<Vec<u8>>>(key))??;
+        self.decode(key, buf)
+    }
+
+    fn decode<M: Message>(&mut self, key: &str, buf: Vec<u8>) -> Option<M> {
+        let mut buf = BytesMut::from(buf.as_slice());
+        match M::decode(&mut TBinaryProtocol::new(&mut buf, false)) {
+            Ok(msg) => Some(msg),
+            Err(err) => {
+                tracing::warn!("drop undecodable cache entry `{key}`: {err}");
+                self.evict(key);
+                None
+            }
+        }
+    }
+
+    fn set<M: Message>(&mut self, key: &str, ttl: u64, msg: &M) {
+        let mut buf = BytesMut::new();
+        if let Err(err) = msg.encode(&mut TBinaryProtocol::new(&mut buf, false)) {
+            tracing::warn!("skip caching `{key}`: {err}");
+            return;
+        }
+        self.call(|conn| conn.set_ex::<_, _, ()>(key, buf.as_ref(), ttl as usize));
+    }
+
+    fn call<T>(&mut self, f: impl FnOnce(&mut Connection) -> RedisResult<T>) -> Option<T> {
+        let conn = self.conn.as_mut()?;
+        match f(conn) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                tracing::warn!("redis is unavailable, bypass the cache: {err}");
+                // the connection may be broken, do not use it again
+                self.conn = None;
+                None
+            }
+        }
+    }
+}
@@ -1,3 +1,23 @@
+//! Configuration of the services, loaded by [Config::load] from layered sources.
+//!
+//! A file may hold the config of several targets of a service: its top-level keys
+//! are shared by all targets, and the section of a target, e.g. `[graphql]`,
+//! overrides them for that target.
+//!
+//! Services reload their config when a [Watcher] notices a change, the parts that
+//! cannot change without a restart, e.g. addresses and DSNs, are kept.
+
+use crate::infra::config::service::CommonConfig;
+use crate::infra::error::FieldViolation;
+use crate::infra::resolver::Target;
+use crate::infra::validate::Validator;
+use ::config::{Environment, File, Map, Source, Value};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
 /// The root of all configuration types
 pub struct Config;
 
@@ -11,11 +31,269 @@ pub trait ConfigType: private::Bound {}
 impl<T> private::Bound for T where T: for<'de> serde::de::Deserialize<'de> {}
 impl<T> ConfigType for T where T: private::Bound {}
 
+/// The config of a target of a service.
+pub trait TargetConfig: ConfigType {
+    fn common(&self) -> &CommonConfig;
+
+    fn common_mut(&mut self) -> &mut CommonConfig;
+
+    /// Record the invalid values, the common ones are checked by [Config::load].
+    fn validate(&self, _v: &mut Validator) {}
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A source cannot be read or a value has a wrong type.
+    Load(::config::ConfigError),
+    /// Values are well typed but invalid.
+    Invalid(Vec<FieldViolation>),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Load(err) => write!(f, "cannot load the configuration: {err}"),
+            ConfigError::Invalid(violations) => {
+                write!(f, "invalid configuration:")?;
+                for violation in violations {
+                    write!(f, "\n  `{}`: {}", violation.filed, violation.description)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<::config::ConfigError> for ConfigError {
+    fn from(err: ::config::ConfigError) -> Self {
+        ConfigError::Load(err)
+    }
+}
+
+/// The keys of a source under the section of a target, as top-level keys.
+#[derive(Debug, Clone)]
+struct Section {
+    source: Box<dyn Source + Send + Sync>,
+    target: String,
+}
+
+impl Source for Section {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ::config::ConfigError> {
+        let prefix = format!("{}.", self.target);
+        let mut section = Map::new();
+        for (key, value) in self.source.collect()? {
+            // files have nested tables, environment variables have dotted keys
+            if key == self.target {
+                section.extend(value.into_table()?);
+            } else if let Some(key) = key.strip_prefix(&prefix) {
+                section.insert(key.to_string(), value);
+            }
+        }
+        Ok(section)
+    }
+}
+
+impl Config {
+    /// Load the config of `target`, the later sources override the former:
+    /// 1. the file at `path`
+    /// 2. `<stem>.<profile>.<ext>` next to it if `APP_PROFILE` is set, it is optional,
+    ///    e.g. `config/sys-graphql.production.toml`
+    /// 3. environment variables prefixed by `APP_`, nested keys are separated by `__`,
+    ///    e.g. `APP_LIMIT__CONCURRENCY` and `APP_GRAPHQL__PGSQL`
+    ///
+    /// The section of the target overrides the top-level keys of each source. `name`
+    /// defaults to `sid`.
+    pub fn load<T: TargetConfig>(
+        path: impl AsRef<Path>,
+        target: Target,
+        sid: &str,
+    ) -> Result<T, ConfigError> {
+        let path = path.as_ref();
+        let target = target.to_string();
+        let mut builder = ::config::Config::builder();
+        let mut layer = |source: Box<dyn Source + Send + Sync>| {
+            let section = Section {
+                source: source.clone(),
+                target: target.clone(),
+            };
+            builder = std::mem::take(&mut builder)
+                .add_source(vec![source])
+                .add_source(section);
+        };
+        layer(Box::new(File::from(path)));
+        if let Some(profile) = Self::profile(path) {
+            layer(Box::new(File::from(profile).required(false)));
+        }
+        layer(Box::new(
+            Environment::with_prefix("APP")
+                .prefix_separator("_")
+                .separator("__")
+                .try_parsing(true),
+        ));
+        let settings = builder.build()?;
+        // the common part is flattened into the config of a target, which loses the
+        // keys of its errors, it is deserialized alone first to report them
+        settings.clone().try_deserialize::<CommonConfig>()?;
+        let mut config: T = settings.try_deserialize()?;
+        if config.common().name.is_empty() {
+            config.common_mut().name = sid.to_string();
+        }
+
+        let mut v = Validator::new();
+        config.common().validate(&mut v);
+        config.validate(&mut v);
+        match v.into_violations() {
+            violations if violations.is_empty() => Ok(config),
+            violations => Err(ConfigError::Invalid(violations)),
+        }
+    }
+
+    /// The file of the profile given by `APP_PROFILE` next to `path`.
+    pub fn profile(path: &Path) -> Option<PathBuf> {
+        let profile = std::env::var("APP_PROFILE").ok()?;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        Some(path.with_file_name(format!("{stem}.{profile}.{ext}")))
+    }
+}
+
+/// Wait for the config to be reloaded, on SIGHUP or a change of the modification
+/// time of its files, which are polled every `interval`.
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Option<Duration>,
+    hangup: Signal,
+}
+
+impl Watcher {
+    /// Watch the files loaded from `path`, `interval` is zero to reload on SIGHUP
+    /// only.
+    pub fn new(path: &Path, interval: Duration) -> std::io::Result<Self> {
+        let files = std::iter::once(path.to_path_buf())
+            .chain(Config::profile(path))
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+        Ok(Self {
+            files,
+            interval: (!interval.is_zero()).then_some(interval),
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    pub async fn changed(&mut self) {
+        let Some(interval) = self.interval else {
+            self.hangup.recv().await;
+            return;
+        };
+        loop {
+            tokio::select! {
+                _ = self.hangup.recv() => return,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let mut changed = false;
+            for (path, last) in &mut self.files {
+                let modified = modified(path);
+                changed |= modified != *last;
+                *last = modified;
+            }
+            if changed {
+                return;
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|v| v.modified()).ok()
+}
+
+/// The changed values from `old` to `new` as `key: old -> new`, redacted.
+pub fn diff(old: &impl Serialize, new: &impl Serialize) -> Vec<String> {
+    fn walk(key: String, old: &serde_json::Value, new: &serde_json::Value, out: &mut Vec<String>) {
+        use serde_json::Value::{Null, Object};
+        match (old, new) {
+            (Object(old), Object(new)) => {
+                let mut keys = old.keys().chain(new.keys()).collect::<Vec<_>>();
+                keys.sort();
+                keys.dedup();
+                for k in keys {
+                    let key = if key.is_empty() {
+                        k.to_string()
+                    } else {
+                        format!("{key}.{k}")
+                    };
+                    walk(
+                        key,
+                        old.get(k).unwrap_or(&Null),
+                        new.get(k).unwrap_or(&Null),
+                        out,
+                    );
+                }
+            }
+            (old, new) if old != new => out.push(format!("{key}: {old} -> {new}")),
+            _ => {}
+        }
+    }
+    let mut out = vec![];
+    walk(String::new(), &redact(old), &redact(new), &mut out);
+    out
+}
+
+/// Keys of secrets, their values are redacted.
+const SECRETS: [&str; 4] = ["password", "secret", "token", "credential"];
+
+/// The config as json to be printed, the secrets and the passwords of urls, e.g.
+/// the DSN of PostgreSQL, are redacted.
+pub fn redact(config: &impl Serialize) -> serde_json::Value {
+    fn walk(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    let key = key.to_lowercase();
+                    if value.is_string() && SECRETS.iter().any(|v| key.contains(v)) {
+                        *value = "***".into();
+                    } else {
+                        walk(value);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(walk),
+            serde_json::Value::String(text) => {
+                if let Ok(mut url) = url::Url::parse(text) {
+                    if url.password().is_some() && url.set_password(Some("***")).is_ok() {
+                        *text = url.to_string();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(config).unwrap_or_default();
+    walk(&mut value);
+    value
+}
+
 pub mod service {
-    use crate::infra::config::ConfigType;
+    use crate::infra::config::{Config, ConfigError, ConfigType, TargetConfig};
+    use crate::infra::resolver::Target;
+    use crate::infra::validate::Validator;
     use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::path::Path;
     use std::time::Duration;
 
+    /// The config types of the targets of a service, a service is served by some
+    /// of them.
     pub trait ServiceConfig {
         /// back-end protocol
         type Grpc: ConfigType; // for gRPC service
@@ -23,33 +301,286 @@ pub mod service {
         /// front-end protocol
         type Rest: ConfigType; // for restful service
         type Graphql: ConfigType; // for graphql service
+
+        fn grpc(path: impl AsRef<Path>, sid: &str) -> Result<Self::Grpc, ConfigError>
+        where
+            Self::Grpc: TargetConfig,
+        {
+            Config::load(path, Target::GRPC, sid)
+        }
+
+        fn thrift(path: impl AsRef<Path>, sid: &str) -> Result<Self::Thrift, ConfigError>
+        where
+            Self::Thrift: TargetConfig,
+        {
+            Config::load(path, Target::THRIFT, sid)
+        }
+
+        fn rest(path: impl AsRef<Path>, sid: &str) -> Result<Self::Rest, ConfigError>
+        where
+            Self::Rest: TargetConfig,
+        {
+            Config::load(path, Target::REST, sid)
+        }
+
+        fn graphql(path: impl AsRef<Path>, sid: &str) -> Result<Self::Graphql, ConfigError>
+        where
+            Self::Graphql: TargetConfig,
+        {
+            Config::load(path, Target::GRAPHQL, sid)
+        }
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    /// `num` requests are allowed `per` duration, `0` disables it.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
     pub struct RateLimitConfig {
         pub num: u64,
         pub per: Duration,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    impl Default for RateLimitConfig {
+        fn default() -> Self {
+            Self {
+                num: 0,
+                per: Duration::from_secs(1),
+            }
+        }
+    }
+
+    impl RateLimitConfig {
+        pub fn validate(&self, field: &str, v: &mut Validator) {
+            v.check(
+                &format!("{field}.per"),
+                self.num == 0 || !self.per.is_zero(),
+                "must be positive when `num` is not 0",
+            );
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+    #[serde(default)]
     pub struct LimitConfig {
-        pub concurrency: usize,    // concurrency limit of this service
+        pub concurrency: usize, // concurrency limit of this service, `0` disables it
         pub rate: RateLimitConfig, // rate limit of this service
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    /// How the instances of services are found by SID, see [crate::infra::discovery].
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum DiscoveryKind {
+        #[default]
+        Static, // the addresses of `services`
+        Dns,   // the SRV records `_<sid>._tcp.<domain>`
+        File,  // the instances registered in the JSON file `registry`
+        Redis, // the instances registered in the redis `registry`
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
+    pub struct DiscoveryConfig {
+        pub kind: DiscoveryKind,
+        pub services: HashMap<String, Vec<String>>, // addresses of services by SID
+        pub domain: String,                         // domain of the SRV records
+        pub nameserver: String, // `ip:port` of the nameserver, the ones of /etc/resolv.conf if empty
+        pub registry: String,   // path of the file or DSN of redis
+        pub ttl: u64, // seconds to cache the instances of a service, and to keep a registration
+        pub eject: u64, // seconds to skip an instance once a request to it failed, `0` disables it
+    }
+
+    impl Default for DiscoveryConfig {
+        fn default() -> Self {
+            Self {
+                kind: DiscoveryKind::Static,
+                services: HashMap::new(),
+                domain: String::new(),
+                nameserver: String::new(),
+                registry: String::new(),
+                ttl: 10,
+                eject: 30,
+            }
+        }
+    }
+
+    impl DiscoveryConfig {
+        pub fn validate(&self, v: &mut Validator) {
+            for (sid, addrs) in &self.services {
+                v.check(
+                    &format!("discovery.services.{sid:?}"),
+                    addrs.iter().all(|addr| addr.parse::<SocketAddr>().is_ok()),
+                    "expected addresses like 127.0.0.1:8080",
+                );
+            }
+            match self.kind {
+                DiscoveryKind::Static => {}
+                DiscoveryKind::Dns => {
+                    v.check(
+                        "discovery.domain",
+                        !self.domain.is_empty(),
+                        "must be set for dns discovery",
+                    );
+                    v.check(
+                        "discovery.nameserver",
+                        self.nameserver.is_empty() || self.nameserver.parse::<SocketAddr>().is_ok(),
+                        "expected an address like 127.0.0.1:53",
+                    );
+                }
+                DiscoveryKind::File => v.check(
+                    "discovery.registry",
+                    !self.registry.is_empty(),
+                    "must be the path of the registry file",
+                ),
+                DiscoveryKind::Redis => v.check(
+                    "discovery.registry",
+                    redis::Client::open(self.registry.as_str()).is_ok(),
+                    "expected a DSN like redis://host/database",
+                ),
+            }
+            v.check(
+                "discovery.ttl",
+                self.kind == DiscoveryKind::Static || self.ttl > 0,
+                "must be positive",
+            );
+        }
+    }
+
+    /// How the logs are written to stderr, see [crate::infra::telemetry::log].
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LogFormat {
+        #[default]
+        Text, // human readable lines
+        Json, // one JSON object per line, with the fields of the spans
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
+    pub struct LogConfig {
+        pub format: LogFormat,
+        pub level: String, // filter of the logs like RUST_LOG, e.g. `info,shop_backend=debug`
+    }
+
+    impl Default for LogConfig {
+        fn default() -> Self {
+            Self {
+                format: LogFormat::Text,
+                level: "info".to_string(),
+            }
+        }
+    }
+
+    impl LogConfig {
+        pub fn validate(&self, v: &mut Validator) {
+            v.check(
+                "log.level",
+                tracing_subscriber::EnvFilter::try_new(&self.level).is_ok(),
+                "expected a filter like info,shop_backend=debug",
+            );
+        }
+    }
+
+    /// Where the spans are exported, see [crate::infra::telemetry].
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
+    pub struct TraceConfig {
+        pub endpoint: String, // OTLP/HTTP endpoint of the collector, e.g. http://localhost:4318, disabled if empty
+        pub sample_ratio: f64, // ratio of the traces started by this service to record
+        pub timeout: u64,     // milliseconds to export a batch of spans
+    }
+
+    impl Default for TraceConfig {
+        fn default() -> Self {
+            Self {
+                endpoint: String::new(),
+                sample_ratio: 1.0,
+                timeout: 10000,
+            }
+        }
+    }
+
+    impl TraceConfig {
+        pub fn validate(&self, v: &mut Validator) {
+            v.check(
+                "trace.endpoint",
+                self.endpoint.is_empty()
+                    || url::Url::parse(&self.endpoint).is_ok_and(|url| url.scheme() == "http"),
+                "expected an URL like http://localhost:4318",
+            );
+            v.check(
+                "trace.sample_ratio",
+                (0.0..=1.0).contains(&self.sample_ratio),
+                "must be between 0 and 1",
+            );
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[serde(default)]
     pub struct CommonConfig {
         pub name: String, // service name, which is unique under a domain provided by NameResolver
         pub listen_addr: String, // address that this service listens to
-        pub timeout: usize, // request timeout of this service
+        pub timeout: usize, // request timeout of this service in milliseconds, `0` disables it
         pub limit: LimitConfig, // limit config
         pub retry: usize, // maximum number of retry when service responses a ServerError
         pub load_shed: bool, // whether to load shed a request when it is not available
+        pub discovery: DiscoveryConfig, // how the clients of this service find other services
+        pub trace: TraceConfig, // where the spans of this service are exported
+        pub log: LogConfig, // how the logs of this service are written
+        pub shutdown_timeout: u64, // milliseconds to drain the requests and close the pools on shutdown, `0` waits for them
+    }
+
+    impl Default for CommonConfig {
+        fn default() -> Self {
+            Self {
+                name: String::new(),
+                listen_addr: "0.0.0.0:3000".to_string(),
+                timeout: 30000,
+                limit: LimitConfig::default(),
+                retry: 0,
+                load_shed: false,
+                discovery: DiscoveryConfig::default(),
+                trace: TraceConfig::default(),
+                log: LogConfig::default(),
+                shutdown_timeout: 30000,
+            }
+        }
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
-    pub struct GrpcConfig {
-        #[serde(flatten)]
-        pub common: CommonConfig,
+    impl CommonConfig {
+        pub fn validate(&self, v: &mut Validator) {
+            v.check(
+                "listen_addr",
+                self.listen_addr.parse::<SocketAddr>().is_ok(),
+                "expected an address like 0.0.0.0:3000",
+            );
+            self.limit.rate.validate("limit.rate", v);
+            self.discovery.validate(v);
+            self.trace.validate(v);
+            self.log.validate(v);
+        }
     }
+
+    macro_rules! target_config {
+        ($($name:ident),+) => {
+            $(
+                #[derive(Serialize, Deserialize, Debug, Default)]
+                pub struct $name {
+                    #[serde(flatten)]
+                    pub common: CommonConfig,
+                }
+
+                impl TargetConfig for $name {
+                    fn common(&self) -> &CommonConfig {
+                        &self.common
+                    }
+
+                    fn common_mut(&mut self) -> &mut CommonConfig {
+                        &mut self.common
+                    }
+                }
+            )+
+        };
+    }
+
+    target_config!(GrpcConfig, ThriftConfig, RestConfig);
 }
@@ -0,0 +1,82 @@
+//! The instances of services as the SRV records `_<sid>._tcp.<domain>`, queried
+//! from the nameserver by hickory, which falls back to TCP for truncated answers.
+//! Only the records of the lowest priority are used, their targets are resolved by
+//! the system resolver.
+
+use crate::infra::discovery::Source;
+use async_trait::async_trait;
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::lookup_host;
+use tokio::sync::OnceCell;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct Dns {
+    domain: String,
+    nameserver: Option<SocketAddr>,
+    // created by the first lookup, the system config may be unreadable until then
+    resolver: OnceCell<TokioAsyncResolver>,
+}
+
+impl Dns {
+    /// The nameservers are the ones of /etc/resolv.conf if `None`.
+    pub fn new(domain: String, nameserver: Option<SocketAddr>) -> Self {
+        Self {
+            domain,
+            nameserver,
+            resolver: OnceCell::new(),
+        }
+    }
+
+    async fn resolver(&self) -> anyhow::Result<&TokioAsyncResolver> {
+        let resolver = self.resolver.get_or_try_init(|| async {
+            match self.nameserver {
+                Some(nameserver) => {
+                    let mut options = ResolverOpts::default();
+                    options.timeout = TIMEOUT;
+                    let group = NameServerConfigGroup::from_ips_clear(
+                        &[nameserver.ip()],
+                        nameserver.port(),
+                        true,
+                    );
+                    let config = ResolverConfig::from_parts(None, vec![], group);
+                    Ok(TokioAsyncResolver::tokio(config, options))
+                }
+                None => TokioAsyncResolver::tokio_from_system_conf(),
+            }
+        });
+        Ok(resolver.await?)
+    }
+}
+
+#[async_trait]
+impl Source for Dns {
+    async fn lookup(&self, sid: &str) -> anyhow::Result<Vec<(SocketAddr, u32)>> {
+        let name = format!("_{sid}._tcp.{}.", self.domain.trim_end_matches('.'));
+        let records = match self.resolver().await?.srv_lookup(name).await {
+            Ok(records) => records,
+            // a name without records has none
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                return Ok(vec![])
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let priority = records.iter().map(|srv| srv.priority()).min();
+        let mut addrs = vec![];
+        for srv in records
+            .iter()
+            .filter(|srv| Some(srv.priority()) == priority)
+        {
+            let target = srv.target().to_utf8();
+            let target = target.trim_end_matches('.');
+            for addr in lookup_host((target, srv.port())).await? {
+                addrs.push((addr, u32::from(srv.weight())));
+            }
+        }
+        Ok(addrs)
+    }
+}
@@ -0,0 +1,391 @@
+//! Discovery of the instances of services by their SID, e.g. `product-thrift`, for
+//! the volo clients.
+//!
+//! The instances are looked up by the [Source] of the `discovery` config and cached
+//! for its `ttl`. A client is built with the SID as its service name, the [Discovery]
+//! as its discover, and the [Balance], the [HealthTransport] and the [HealthLayer] of
+//! the discovery as its load balance, transport and inner layer. The instances which
+//! cannot be connected or fail with transport errors are ejected for a while.
+//!
+//! Services registered in a file or redis registry keep their registration alive
+//! with [heartbeat] while they are serving.
+
+pub mod dns;
+pub mod registry;
+
+use crate::infra::config::service::{DiscoveryConfig, DiscoveryKind};
+use crate::infra::discovery::dns::Dns;
+use crate::infra::discovery::registry::{FileRegistry, RedisRegistry, Registry};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use volo::context::{Context, Endpoint};
+use volo::discovery::{Change, Discover, Instance};
+use volo::loadbalance::error::{LoadBalanceError, Retryable};
+use volo::loadbalance::LoadBalance;
+use volo::net::dial::{DefaultMakeTransport, MakeTransport};
+use volo::net::Address;
+use volo::{Layer, Service};
+
+/// Where the instances of services are found.
+#[async_trait]
+pub trait Source: Send + Sync {
+    /// The addresses of the instances of the service and their weights.
+    async fn lookup(&self, sid: &str) -> anyhow::Result<Vec<(SocketAddr, u32)>>;
+}
+
+/// The addresses of the services in the config.
+pub struct Static(pub HashMap<String, Vec<SocketAddr>>);
+
+#[async_trait]
+impl Source for Static {
+    async fn lookup(&self, sid: &str) -> anyhow::Result<Vec<(SocketAddr, u32)>> {
+        let addrs = self.0.get(sid).into_iter().flatten();
+        Ok(addrs.map(|addr| (*addr, 1)).collect())
+    }
+}
+
+type Instances = Vec<Arc<Instance>>;
+
+/// The volo discover of the services, keyed by SID.
+#[derive(Clone)]
+pub struct Discovery {
+    source: Arc<dyn Source>,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, (Instant, Instances)>>>,
+    health: Health,
+}
+
+impl Discovery {
+    pub fn new(source: Arc<dyn Source>, ttl: Duration, eject: Duration) -> Self {
+        Self {
+            source,
+            ttl,
+            cache: Default::default(),
+            health: Health::new(eject),
+        }
+    }
+
+    /// The discovery of the config, it is validated by [DiscoveryConfig::validate].
+    pub fn from_config(config: &DiscoveryConfig) -> anyhow::Result<Self> {
+        let source: Arc<dyn Source> = match config.kind {
+            DiscoveryKind::Static => Arc::new(Static(
+                config
+                    .services
+                    .iter()
+                    .map(|(sid, addrs)| {
+                        let addrs = addrs.iter().filter_map(|addr| addr.parse().ok());
+                        (sid.clone(), addrs.collect())
+                    })
+                    .collect(),
+            )),
+            DiscoveryKind::Dns => Arc::new(Dns::new(
+                config.domain.clone(),
+                config.nameserver.parse().ok(),
+            )),
+            DiscoveryKind::File => Arc::new(FileRegistry::new(&config.registry)),
+            DiscoveryKind::Redis => Arc::new(RedisRegistry::new(&config.registry)?),
+        };
+        Ok(Self::new(
+            source,
+            Duration::from_secs(config.ttl),
+            Duration::from_secs(config.eject),
+        ))
+    }
+
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    pub fn balance(&self) -> Balance {
+        Balance {
+            health: self.health.clone(),
+            next: Default::default(),
+        }
+    }
+
+    pub fn health_layer(&self) -> HealthLayer {
+        HealthLayer(self.health.clone())
+    }
+
+    pub fn transport(&self) -> HealthTransport<DefaultMakeTransport> {
+        HealthTransport {
+            inner: DefaultMakeTransport::new(),
+            health: self.health.clone(),
+        }
+    }
+
+    /// The instances of the service, the cached ones are kept when the source fails.
+    pub async fn instances(&self, sid: &str) -> anyhow::Result<Instances> {
+        let cached = self.cache.lock().unwrap().get(sid).cloned();
+        if let Some((at, instances)) = &cached {
+            if at.elapsed() < self.ttl {
+                return Ok(instances.clone());
+            }
+        }
+        let instances = match self.source.lookup(sid).await {
+            Ok(addrs) => addrs
+                .into_iter()
+                .map(|(addr, weight)| {
+                    Arc::new(Instance {
+                        address: Address::Ip(addr),
+                        weight: weight.max(1),
+                        tags: Default::default(),
+                    })
+                })
+                .collect::<Instances>(),
+            Err(err) => match cached {
+                Some((_, instances)) => {
+                    tracing::warn!("failed to discover `{sid}`, keep the known instances: {err}");
+                    instances
+                }
+                None => return Err(err),
+            },
+        };
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(sid.to_string(), (Instant::now(), instances.clone()));
+        Ok(instances)
+    }
+}
+
+impl Discover for Discovery {
+    type Key = String;
+    type Error = LoadBalanceError;
+    type DiscFut<'future> = impl Future<Output = Result<Instances, Self::Error>> + Send + 'future;
+
+    fn discover<'s>(&'s self, endpoint: &'s Endpoint) -> Self::DiscFut<'s> {
+        async move {
+            self.instances(endpoint.service_name_ref())
+                .await
+                .map_err(|err| LoadBalanceError::Discover(err.into()))
+        }
+    }
+
+    fn key(&self, endpoint: &Endpoint) -> Self::Key {
+        endpoint.service_name_ref().to_string()
+    }
+
+    // the instances are refreshed by `discover` once their ttl passed
+    fn watch(
+        &self,
+        _: Option<&[Self::Key]>,
+    ) -> Option<async_broadcast::Receiver<Change<Self::Key>>> {
+        None
+    }
+}
+
+/// The instances which failed recently, they are skipped for `eject`.
+#[derive(Clone)]
+pub struct Health {
+    eject: Duration,
+    down: Arc<Mutex<HashMap<Address, Instant>>>,
+}
+
+impl Health {
+    pub fn new(eject: Duration) -> Self {
+        Self {
+            eject,
+            down: Default::default(),
+        }
+    }
+
+    pub fn fail(&self, addr: &Address) {
+        if !self.eject.is_zero() {
+            let until = Instant::now() + self.eject;
+            self.down.lock().unwrap().insert(addr.clone(), until);
+        }
+    }
+
+    pub fn recover(&self, addr: &Address) {
+        self.down.lock().unwrap().remove(addr);
+    }
+
+    pub fn is_healthy(&self, addr: &Address) -> bool {
+        let mut down = self.down.lock().unwrap();
+        match down.get(addr) {
+            Some(until) if *until > Instant::now() => false,
+            Some(_) => {
+                down.remove(addr);
+                true
+            }
+            None => true,
+        }
+    }
+}
+
+/// Weighted round-robin over the healthy instances, the ejected ones are tried
+/// last, so that a request is still sent when all instances are ejected.
+#[derive(Clone)]
+pub struct Balance {
+    health: Health,
+    next: Arc<AtomicUsize>,
+}
+
+impl Balance {
+    /// The addresses to try in order.
+    pub fn pick(&self, instances: &[Arc<Instance>]) -> Vec<Address> {
+        let (mut healthy, ejected): (Vec<_>, Vec<_>) = instances
+            .iter()
+            .partition(|instance| self.health.is_healthy(&instance.address));
+        let total = healthy.iter().map(|i| i.weight as usize).sum::<usize>();
+        if total > 0 {
+            let mut n = self.next.fetch_add(1, Ordering::Relaxed) % total;
+            let first = healthy
+                .iter()
+                .position(|instance| match n.checked_sub(instance.weight as usize) {
+                    Some(rest) => {
+                        n = rest;
+                        false
+                    }
+                    None => true,
+                })
+                .unwrap_or_default();
+            healthy.rotate_left(first);
+        }
+        healthy
+            .into_iter()
+            .chain(ejected)
+            .map(|instance| instance.address.clone())
+            .collect()
+    }
+}
+
+impl LoadBalance<Discovery> for Balance {
+    type InstanceIter = std::vec::IntoIter<Address>;
+    type GetFut<'future>
+        = impl Future<Output = Result<Self::InstanceIter, LoadBalanceError>> + Send + 'future
+    where
+        Self: 'future;
+
+    fn get_picker<'future>(
+        &'future self,
+        endpoint: &'future Endpoint,
+        discover: &'future Discovery,
+    ) -> Self::GetFut<'future>
+    where
+        Self: 'future,
+    {
+        async move {
+            let instances = discover.discover(endpoint).await?;
+            Ok(self.pick(&instances).into_iter())
+        }
+    }
+
+    fn rebalance(&self, _: Change<String>) {}
+}
+
+/// Eject the callee of a request failing with a retryable error, i.e. a transport
+/// error of thrift, and recover it once a request to it succeeds.
+#[derive(Clone)]
+pub struct HealthLayer(Health);
+
+impl<S> Layer<S> for HealthLayer {
+    type Service = HealthService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        HealthService {
+            inner,
+            health: self.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HealthService<S> {
+    inner: S,
+    health: Health,
+}
+
+impl<Cx, Req, S> Service<Cx, Req> for HealthService<S>
+where
+    Cx: Context + Send + 'static,
+    Req: Send + 'static,
+    S: Service<Cx, Req> + Send + Sync + 'static,
+    S::Error: Retryable + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future<'cx> = impl Future<Output = Result<Self::Response, Self::Error>> + Send + 'cx;
+
+    fn call<'cx, 's>(&'s self, cx: &'cx mut Cx, req: Req) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        async move {
+            let res = self.inner.call(cx, req).await;
+            if let Some(addr) = cx.rpc_info().callee().and_then(Endpoint::address) {
+                match &res {
+                    Ok(_) => self.health.recover(&addr),
+                    Err(err) if err.retryable() => self.health.fail(&addr),
+                    // errors of the server, or of connecting, see [HealthTransport]
+                    Err(_) => {}
+                }
+            }
+            res
+        }
+    }
+}
+
+/// Eject the instances which cannot be connected, volo_thrift reports the errors of
+/// connecting as application errors, which are not told from the ones of the server.
+#[derive(Clone)]
+pub struct HealthTransport<T> {
+    inner: T,
+    health: Health,
+}
+
+#[async_trait]
+impl<T: MakeTransport> MakeTransport for HealthTransport<T> {
+    type ReadHalf = T::ReadHalf;
+    type WriteHalf = T::WriteHalf;
+
+    async fn make_transport(
+        &self,
+        addr: Address,
+    ) -> std::io::Result<(Self::ReadHalf, Self::WriteHalf)> {
+        let res = self.inner.make_transport(addr.clone()).await;
+        if res.is_err() {
+            self.health.fail(&addr);
+        }
+        res
+    }
+
+    fn set_connect_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_connect_timeout(timeout)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout)
+    }
+}
+
+/// Keep the registration of the instance at `addr` alive in the registry of the
+/// config, if the config is of a registry.
+pub fn heartbeat(config: &DiscoveryConfig, sid: &str, addr: SocketAddr) -> Option<JoinHandle<()>> {
+    let registry: Arc<dyn Registry> = match config.kind {
+        DiscoveryKind::File => Arc::new(FileRegistry::new(&config.registry)),
+        DiscoveryKind::Redis => Arc::new(RedisRegistry::new(&config.registry).ok()?),
+        DiscoveryKind::Static | DiscoveryKind::Dns => return None,
+    };
+    let ttl = Duration::from_secs(config.ttl);
+    let sid = sid.to_string();
+    Some(tokio::spawn(async move {
+        loop {
+            if let Err(err) = registry.register(&sid, addr, ttl).await {
+                tracing::warn!("failed to register `{sid}` at {addr}: {err}");
+            }
+            tokio::time::sleep(ttl / 3).await;
+        }
+    }))
+}
@@ -0,0 +1,152 @@
+//! Registries the instances of services register themselves in, a registration
+//! expires after its ttl unless it is renewed, see [crate::infra::discovery::heartbeat].
+//!
+//! The file registry is meant for running the services locally, concurrent
+//! registrations may overwrite each other until their next renewal. The redis
+//! registry keeps the instances of a service in the ZSET `discovery:<sid>`, scored
+//! by their expiration.
+
+use crate::infra::discovery::Source;
+use async_trait::async_trait;
+use redis::Commands;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[async_trait]
+pub trait Registry: Source {
+    /// Register the instance at `addr`, or renew its registration.
+    async fn register(&self, sid: &str, addr: SocketAddr, ttl: Duration) -> anyhow::Result<()>;
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Instances by SID in a JSON file, e.g. `{"product-thrift": {"127.0.0.1:8081": 1700000000000}}`,
+/// where the numbers are the expirations in milliseconds since the epoch.
+pub struct FileRegistry {
+    path: PathBuf,
+}
+
+type Registrations = HashMap<String, HashMap<SocketAddr, u64>>;
+
+impl FileRegistry {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    async fn read(&self) -> anyhow::Result<Registrations> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Source for FileRegistry {
+    async fn lookup(&self, sid: &str) -> anyhow::Result<Vec<(SocketAddr, u32)>> {
+        let now = now_millis();
+        let instances = self.read().await?.remove(sid).unwrap_or_default();
+        Ok(instances
+            .into_iter()
+            .filter(|(_, expires)| *expires > now)
+            .map(|(addr, _)| (addr, 1))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Registry for FileRegistry {
+    async fn register(&self, sid: &str, addr: SocketAddr, ttl: Duration) -> anyhow::Result<()> {
+        let now = now_millis();
+        let mut registrations = self.read().await?;
+        for instances in registrations.values_mut() {
+            instances.retain(|_, expires| *expires > now);
+        }
+        registrations.retain(|_, instances| !instances.is_empty());
+        let expires = now + ttl.as_millis() as u64;
+        registrations
+            .entry(sid.to_string())
+            .or_default()
+            .insert(addr, expires);
+        // replaced at once, readers never see a partial file
+        let tmp = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(&registrations)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+pub struct RedisRegistry {
+    client: redis::Client,
+}
+
+impl RedisRegistry {
+    pub fn new(dsn: &str) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(dsn)?,
+        })
+    }
+
+    /// Run `f` with a connection on the blocking thread pool.
+    async fn blocking<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&mut redis::Connection) -> redis::RedisResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let client = self.client.clone();
+        let res = tokio::task::spawn_blocking(move || {
+            let mut conn = client.get_connection_with_timeout(Duration::from_secs(1))?;
+            f(&mut conn)
+        });
+        Ok(res.await??)
+    }
+}
+
+#[async_trait]
+impl Source for RedisRegistry {
+    async fn lookup(&self, sid: &str) -> anyhow::Result<Vec<(SocketAddr, u32)>> {
+        let key = format!("discovery:{sid}");
+        let now = now_millis();
+        let addrs: Vec<String> = self
+            .blocking(move |conn| conn.zrangebyscore(key, format!("({now}"), "+inf"))
+            .await?;
+        Ok(addrs
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .map(|addr| (addr, 1))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Registry for RedisRegistry {
+    async fn register(&self, sid: &str, addr: SocketAddr, ttl: Duration) -> anyhow::Result<()> {
+        let key = format!("discovery:{sid}");
+        let now = now_millis();
+        let expires = now + ttl.as_millis() as u64;
+        self.blocking(move |conn| {
+            redis::pipe()
+                .zadd(&key, addr.to_string(), expires)
+                .ignore()
+                .zrembyscore(&key, "-inf", now)
+                .ignore()
+                // the key of a service without instances expires too
+                .pexpire(&key, ttl.as_millis() as usize)
+                .ignore()
+                .query(conn)
+        })
+        .await
+    }
+}
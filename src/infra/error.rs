@@ -27,6 +27,17 @@ impl Status {
         self.code
     }
 
+    /// The delay of the retry info detail, if any.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        self.details
+            .iter()
+            .flatten()
+            .find_map(|detail| match detail {
+                ErrorDetail::RetryInfo { retry_delay } => Some(*retry_delay),
+                _ => None,
+            })
+    }
+
     pub fn new(
         code: Code,
         message: String,
@@ -82,6 +93,25 @@ impl Status {
         )
     }
 
+    /// Like [Status::invalid_argument] for many fields, the violations are attached as
+    /// the bad request detail.
+    pub fn invalid_fields(vio: impl Into<Vec<FieldViolation>>) -> Self {
+        let vio = vio.into();
+        let fields = vio
+            .iter()
+            .map(|v| format!("'{}'", v.filed))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let msg = format!("Request fields {} are invalid.", fields);
+        Self::new(
+            Code::InvalidArgument,
+            msg.to_string(),
+            None,
+            Some(Arc::new(anyhow!(msg))),
+        )
+        .with_bad_request(vio)
+    }
+
     pub fn deadline_exceeded() -> Self {
         Self::new(
             Code::DeadlineExceeded,
@@ -416,6 +446,29 @@ impl Code {
         }
     }
 
+    /// The canonical name, e.g. `NOT_FOUND`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Code::Ok => "OK",
+            Code::Cancelled => "CANCELLED",
+            Code::Unknown => "UNKNOWN",
+            Code::InvalidArgument => "INVALID_ARGUMENT",
+            Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            Code::NotFound => "NOT_FOUND",
+            Code::AlreadyExists => "ALREADY_EXISTS",
+            Code::PermissionDenied => "PERMISSION_DENIED",
+            Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            Code::FailedPrecondition => "FAILED_PRECONDITION",
+            Code::Aborted => "ABORTED",
+            Code::OutOfRange => "OUT_OF_RANGE",
+            Code::Unimplemented => "UNIMPLEMENTED",
+            Code::Internal => "INTERNAL",
+            Code::Unavailable => "UNAVAILABLE",
+            Code::DataLoss => "DATA_LOSS",
+            Code::Unauthenticated => "UNAUTHENTICATED",
+        }
+    }
+
     pub fn to_http_code(&self) -> StatusCode {
         match self {
             Code::Ok => StatusCode::OK,
@@ -506,6 +559,38 @@ impl Serialize for Status {
     }
 }
 
+// TODO #[cfg(graphql)]
+/// The id of the request being served is added as request info, see
+/// [crate::infra::telemetry::log]. The causes of the server errors are not exposed,
+/// they are logged in the span of the request instead.
+impl async_graphql::ErrorExtensions for Status {
+    fn extend(&self) -> async_graphql::Error {
+        if let Some(inner) = &self.inner {
+            if self.code.to_http_code().is_server_error() {
+                tracing::error!(code = self.code.name(), "{inner:#}");
+            }
+        }
+        let has_request_info = self
+            .details
+            .iter()
+            .flatten()
+            .any(ErrorDetail::is_request_info);
+        let status = match crate::infra::telemetry::log::current() {
+            Some(id) if !has_request_info => self.clone().with_request_info(id, ""),
+            _ => self.clone(),
+        };
+        async_graphql::Error::new(&self.message).extend_with(|_, e| {
+            e.set("code", self.code.name());
+            e.set("httpStatus", self.code.to_http_code().as_u16());
+            // filter sensitive details when display status
+            let safe_details = status.details.as_deref().map(filter_details);
+            if let Ok(details) = async_graphql::to_value(safe_details.unwrap_or_default()) {
+                e.set("details", details);
+            }
+        })
+    }
+}
+
 impl<E> From<E> for Status
 where
     E: StdError + Send + Sync + 'static,
@@ -0,0 +1,85 @@
+//! Checks of the dependencies of the services, for the readiness probe of the
+//! graphql service and the `ping` of the rpc services.
+//!
+//! A service is ready when postgres answers with the latest migration applied, and
+//! redis answers. Liveness checks nothing, a service answering is alive.
+
+use anyhow::{anyhow, ensure};
+use diesel::r2d2::ConnectionManager;
+use diesel::sql_types::{Bool, Text};
+use diesel::{sql_query, PgConnection, QueryableByName, RunQueryDsl};
+use r2d2::Pool;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// The version of the latest migration, embedded by the build script.
+pub const MIGRATION: &str = env!("SHOP_MIGRATION");
+
+// probes are retried, they must not wait for a pool
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(QueryableByName)]
+struct Applied {
+    #[diesel(sql_type = Bool)]
+    applied: bool,
+}
+
+pub fn check_pgsql(pool: &Pool<ConnectionManager<PgConnection>>) -> anyhow::Result<()> {
+    let mut conn = pool.get_timeout(TIMEOUT)?;
+    // the table is kept by the diesel cli
+    let applied = sql_query(
+        "SELECT EXISTS (SELECT 1 FROM __diesel_schema_migrations WHERE version = $1) AS applied",
+    )
+    .bind::<Text, _>(MIGRATION)
+    .get_result::<Applied>(&mut conn)?;
+    ensure!(applied.applied, "migration {MIGRATION} is not applied");
+    Ok(())
+}
+
+pub fn check_redis(pool: &Pool<redis::Client>) -> anyhow::Result<()> {
+    let mut conn = pool.get_timeout(TIMEOUT)?;
+    redis::cmd("PING").query::<String>(&mut *conn)?;
+    Ok(())
+}
+
+/// The outcome of the checks by dependency, `ok` or the error.
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub ready: bool,
+    pub checks: BTreeMap<&'static str, String>,
+}
+
+impl Readiness {
+    /// Run the checks, they block on the pools.
+    pub fn check(
+        pgsql: &Pool<ConnectionManager<PgConnection>>,
+        redis: &Pool<redis::Client>,
+    ) -> Self {
+        let checks = BTreeMap::from([("pgsql", check_pgsql(pgsql)), ("redis", check_redis(redis))]);
+        Self {
+            ready: checks.values().all(Result::is_ok),
+            checks: checks
+                .into_iter()
+                .map(|(name, res)| match res {
+                    Ok(()) => (name, "ok".to_string()),
+                    Err(err) => (name, err.to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    /// An error naming the failed checks, if any.
+    pub fn into_result(self) -> anyhow::Result<()> {
+        if self.ready {
+            return Ok(());
+        }
+        let failed = self
+            .checks
+            .into_iter()
+            .filter(|(_, res)| res != "ok")
+            .map(|(name, err)| format!("{name}: {err}"))
+            .collect::<Vec<_>>();
+        Err(anyhow!("not ready, {}", failed.join(", ")))
+    }
+}
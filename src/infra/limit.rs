@@ -0,0 +1,165 @@
+//! Limits of a service declared by [CommonConfig], the protocol middlewares run
+//! requests with a [Limiter], see [crate::graphql::limit] and [crate::rpc::limit].
+//!
+//! Limits of a client are shared by all instances of a service, they are counted
+//! in redis by [take_window].
+
+use crate::infra::config::service::{CommonConfig, LimitConfig, RateLimitConfig};
+use crate::infra::error::{Result, Status};
+use once_cell::sync::Lazy;
+use redis::{Connection, RedisResult, Script};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+/// Suggested to clients whose requests are shed, there is no way to know when
+/// a request completes.
+const SHED_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Requests over the limits are rejected with:
+/// 1. resource_exhausted with retry info, when the rate is exceeded or all the
+///    concurrency is taken with `load_shed`
+/// 2. deadline_exceeded, when the request is not completed within `timeout`, the
+///    time waiting for the concurrency included
+///
+/// `0` disables a limit.
+pub struct Limiter {
+    timeout: Option<Duration>,
+    concurrency: Option<Semaphore>,
+    rate: Option<Mutex<Bucket>>,
+    load_shed: bool,
+}
+
+/// A token bucket refilled by `num` tokens `per` duration, at most `num` tokens
+/// are held.
+struct Bucket {
+    capacity: f64,
+    per_token: Duration,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    /// Take a token, or return the time until one is refilled.
+    fn take(&mut self) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() / self.per_token.as_secs_f64()).min(self.capacity);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(self.per_token.mul_f64(1.0 - self.tokens))
+    }
+}
+
+impl Limiter {
+    /// `timeout` is in milliseconds.
+    pub fn new(timeout: usize, limit: &LimitConfig, load_shed: bool) -> Self {
+        let rate = &limit.rate;
+        Self {
+            timeout: (timeout > 0).then(|| Duration::from_millis(timeout as u64)),
+            concurrency: (limit.concurrency > 0).then(|| Semaphore::new(limit.concurrency)),
+            rate: (rate.num > 0 && !rate.per.is_zero()).then(|| {
+                Mutex::new(Bucket {
+                    capacity: rate.num as f64,
+                    per_token: rate.per.div_f64(rate.num as f64),
+                    tokens: rate.num as f64,
+                    refilled_at: Instant::now(),
+                })
+            }),
+            load_shed,
+        }
+    }
+
+    pub fn from_config(config: &CommonConfig) -> Self {
+        Self::new(config.timeout, &config.limit, config.load_shed)
+    }
+
+    /// Run a request within the limits.
+    pub async fn run<F: Future>(&self, request: F) -> Result<F::Output> {
+        if let Some(rate) = &self.rate {
+            let taken = rate.lock().unwrap().take();
+            if let Err(delay) = taken {
+                return Err(Status::resource_exhausted().with_retry_info(delay));
+            }
+        }
+        let limited = async {
+            let _permit = match &self.concurrency {
+                Some(concurrency) if self.load_shed => {
+                    Some(concurrency.try_acquire().map_err(|_| {
+                        Status::resource_exhausted().with_retry_info(SHED_RETRY_DELAY)
+                    })?)
+                }
+                Some(concurrency) => Some(concurrency.acquire().await?),
+                None => None,
+            };
+            Ok(request.await)
+        };
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, limited)
+                .await
+                .map_err(|_| Status::deadline_exceeded())?,
+            None => limited.await,
+        }
+    }
+}
+
+/// Sliding window logs: the times of the requests within a window are kept in a
+/// sorted set, the time of redis is used so that the instances agree on it. A
+/// request is counted in all of its windows only if none of them is full.
+static WINDOWS: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+local time = redis.call('TIME')
+local now = time[1] * 1000 + math.floor(time[2] / 1000)
+local counts = {}
+for i, key in ipairs(KEYS) do
+    local window = tonumber(ARGV[2 * i - 1])
+    redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+    counts[i] = redis.call('ZCARD', key)
+    if counts[i] >= tonumber(ARGV[2 * i]) then
+        local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+        return {i, tonumber(oldest[2]) + window - now}
+    end
+end
+for i, key in ipairs(KEYS) do
+    redis.call('ZADD', key, now, time[1] .. time[2] .. '-' .. counts[i])
+    redis.call('PEXPIRE', key, tonumber(ARGV[2 * i - 1]))
+end
+return {0, -1}
+",
+    )
+});
+
+/// Count a request in the window of `key`, or return the time until the oldest
+/// request leaves the window if `rate` is exceeded.
+pub fn take_window(
+    conn: &mut Connection,
+    key: &str,
+    rate: &RateLimitConfig,
+) -> RedisResult<Option<Duration>> {
+    Ok(take_windows(conn, &[(key, rate)])?.map(|(_, delay)| delay))
+}
+
+/// Like [take_window] for many windows at once, the request is counted in none of
+/// them if one is exceeded. The index of the first window exceeded is returned
+/// with its delay.
+pub fn take_windows(
+    conn: &mut Connection,
+    windows: &[(&str, &RateLimitConfig)],
+) -> RedisResult<Option<(usize, Duration)>> {
+    let mut script = WINDOWS.prepare_invoke();
+    for (key, rate) in windows {
+        script
+            .key(*key)
+            .arg(rate.per.as_millis().max(1) as u64)
+            .arg(rate.num);
+    }
+    let (idx, delay): (usize, i64) = script.invoke(conn)?;
+    Ok((idx > 0).then(|| (idx - 1, Duration::from_millis(delay.max(1) as u64))))
+}
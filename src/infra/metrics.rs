@@ -0,0 +1,264 @@
+//! Metrics of the services in the text format of Prometheus, the graphql service
+//! serves them at `/metrics`.
+//!
+//! The metrics are process-wide statics, labelled by their service and operation:
+//!
+//! - `shop_request_duration_seconds`, the latency of the graphql operations and the
+//!   rpc methods, its `_count` is the number of requests.
+//! - `shop_errors_total`, the errors by the name of their code, e.g. `NOT_FOUND`,
+//!   see [crate::infra::error::Code].
+//! - `shop_pool_connections`, the connections of the r2d2 pools by `state`, they are
+//!   observed by [observe_pool] when scraped.
+//! - `shop_carts_created_total` and `shop_checkouts_paid_total`.
+
+use once_cell::sync::Lazy;
+use r2d2::{ManageConnection, Pool};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the buckets of the latencies, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub static REQUEST_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    Histogram::new(
+        "shop_request_duration_seconds",
+        "Latency of the requests by service and operation.",
+        &["service", "operation"],
+    )
+});
+
+pub static ERRORS: Lazy<Counter> = Lazy::new(|| {
+    Counter::new(
+        "shop_errors_total",
+        "Errors by service and code.",
+        &["service", "code"],
+    )
+});
+
+pub static POOL_CONNECTIONS: Lazy<Gauge> = Lazy::new(|| {
+    Gauge::new(
+        "shop_pool_connections",
+        "Connections of the pools by state, idle, active or max.",
+        &["pool", "state"],
+    )
+});
+
+pub static CARTS_CREATED: Lazy<Counter> =
+    Lazy::new(|| Counter::new("shop_carts_created_total", "Carts created.", &[]));
+
+// counted once the payment of a checkout is confirmed, there is no payment yet and
+// it stays at 0
+pub static CHECKOUTS_PAID: Lazy<Counter> =
+    Lazy::new(|| Counter::new("shop_checkouts_paid_total", "Checkouts paid.", &[]));
+
+/// Count a request of `service` which took `elapsed`.
+pub fn observe(service: &str, operation: &str, elapsed: Duration) {
+    REQUEST_DURATION.observe(&[service, operation], elapsed);
+}
+
+/// Count an error of `service` by the name of its code, e.g. `NOT_FOUND`.
+pub fn error(service: &str, code: &str) {
+    ERRORS.inc(&[service, code]);
+}
+
+/// Set the connections of the pool, they are observed when scraped.
+pub fn observe_pool<M: ManageConnection>(name: &str, pool: &Pool<M>) {
+    let state = pool.state();
+    let active = state.connections - state.idle_connections;
+    for (label, value) in [
+        ("idle", state.idle_connections),
+        ("active", active),
+        ("max", pool.max_size()),
+    ] {
+        POOL_CONNECTIONS.set(&[name, label], value as f64);
+    }
+}
+
+/// All metrics in the text format.
+pub fn render() -> String {
+    let mut out = String::new();
+    REQUEST_DURATION.render(&mut out);
+    ERRORS.render(&mut out);
+    POOL_CONNECTIONS.render(&mut out);
+    CARTS_CREATED.render(&mut out);
+    CHECKOUTS_PAID.render(&mut out);
+    out
+}
+
+/// Values of a metric by their labels.
+struct Family<T> {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, T>>,
+}
+
+impl<T: Default> Family<T> {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Default::default(),
+        }
+    }
+
+    fn with<R>(&self, labels: &[&str], f: impl FnOnce(&mut T) -> R) -> R {
+        debug_assert_eq!(labels.len(), self.labels.len(), "labels of {}", self.name);
+        let key = labels.iter().map(|label| label.to_string()).collect();
+        f(self.values.lock().unwrap().entry(key).or_default())
+    }
+
+    /// Like [Family::with], but the value is not added if absent.
+    fn get<R: Default>(&self, labels: &[&str], f: impl FnOnce(&T) -> R) -> R {
+        let key = labels
+            .iter()
+            .map(|label| label.to_string())
+            .collect::<Vec<_>>();
+        self.values
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(f)
+            .unwrap_or_default()
+    }
+
+    fn render(&self, out: &mut String, kind: &str, f: impl Fn(&mut String, &str, &T)) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {kind}", self.name);
+        for (values, value) in self.values.lock().unwrap().iter() {
+            let labels = self
+                .labels
+                .iter()
+                .zip(values)
+                .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            f(out, &labels, value);
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `{labels}`, or nothing without labels.
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{labels}}}")
+    }
+}
+
+pub struct Counter(Family<u64>);
+
+impl Counter {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self(Family::new(name, help, labels))
+    }
+
+    pub fn inc(&self, labels: &[&str]) {
+        self.0.with(labels, |value| *value += 1)
+    }
+
+    pub fn get(&self, labels: &[&str]) -> u64 {
+        self.0.get(labels, |value| *value)
+    }
+
+    fn render(&self, out: &mut String) {
+        let name = self.0.name;
+        // counters without labels are exported from the start
+        if self.0.labels.is_empty() {
+            self.0.with(&[], |_| ());
+        }
+        self.0.render(out, "counter", |out, labels, value| {
+            let _ = writeln!(out, "{name}{} {value}", braced(labels));
+        })
+    }
+}
+
+pub struct Gauge(Family<f64>);
+
+impl Gauge {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self(Family::new(name, help, labels))
+    }
+
+    pub fn set(&self, labels: &[&str], value: f64) {
+        self.0.with(labels, |v| *v = value)
+    }
+
+    pub fn get(&self, labels: &[&str]) -> f64 {
+        self.0.get(labels, |value| *value)
+    }
+
+    fn render(&self, out: &mut String) {
+        let name = self.0.name;
+        self.0.render(out, "gauge", |out, labels, value| {
+            let _ = writeln!(out, "{name}{} {value}", braced(labels));
+        })
+    }
+}
+
+/// Counts of the observations in each bucket of [BUCKETS], not cumulated.
+#[derive(Default)]
+struct Buckets {
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+pub struct Histogram(Family<Buckets>);
+
+impl Histogram {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self(Family::new(name, help, labels))
+    }
+
+    pub fn observe(&self, labels: &[&str], elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        self.0.with(labels, |buckets| {
+            if let Some(i) = BUCKETS.iter().position(|bound| secs <= *bound) {
+                buckets.counts[i] += 1;
+            }
+            buckets.count += 1;
+            buckets.sum += secs;
+        })
+    }
+
+    /// The number of observations.
+    pub fn count(&self, labels: &[&str]) -> u64 {
+        self.0.get(labels, |buckets| buckets.count)
+    }
+
+    fn render(&self, out: &mut String) {
+        let name = self.0.name;
+        self.0.render(out, "histogram", |out, labels, buckets| {
+            let sep = if labels.is_empty() { "" } else { "," };
+            let mut cumulated = 0;
+            for (bound, count) in BUCKETS.iter().zip(buckets.counts) {
+                cumulated += count;
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulated}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+                buckets.count
+            );
+            let _ = writeln!(out, "{name}_sum{} {}", braced(labels), buckets.sum);
+            let _ = writeln!(out, "{name}_count{} {}", braced(labels), buckets.count);
+        })
+    }
+}
@@ -1,5 +1,14 @@
+pub mod cache;
 pub mod config;
+pub mod discovery;
 pub mod error;
+pub mod health;
 pub mod id;
+pub mod limit;
+pub mod metrics;
 pub mod mqsrs;
+pub mod pubsub;
 pub mod resolver;
+pub mod shutdown;
+pub mod telemetry;
+pub mod validate;
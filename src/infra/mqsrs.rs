@@ -1,8 +1,13 @@
 //! MQSRS
 //! Mutation,Query,Subscription Responsibility Separation
+//!
+//! Mutations and queries are [Traced], they are executed in a span named by their
+//! domain, see [crate::infra::telemetry::domain_span].
 
+use crate::infra::telemetry::domain_span;
 use async_trait::async_trait;
 use futures::Stream;
+use opentelemetry::trace::FutureExt;
 use std::future::Future;
 
 #[async_trait]
@@ -21,27 +26,38 @@ pub trait Subscription<I, O> {
     async fn execute(&self, input: I) -> Self::OutStream;
 }
 
+/// A mutation or a query of a domain, executed in a span of its name, e.g. `get_cart`
+/// for the query of `Resolver::create_get_cart`.
+pub struct Traced<F> {
+    name: &'static str,
+    f: F,
+}
+
+pub fn traced<F>(name: &'static str, f: F) -> Traced<F> {
+    Traced { name, f }
+}
+
 #[async_trait]
-impl<I, O, TMutation, TFuture> Mutation<I, O> for TMutation
+impl<I, O, TMutation, TFuture> Mutation<I, O> for Traced<TMutation>
 where
     TMutation: (FnOnce(I) -> TFuture) + Send,
     TFuture: Future<Output = O> + Send,
     I: Send + 'static,
 {
     async fn execute(self, input: I) -> O {
-        self(input).await
+        (self.f)(input).with_context(domain_span(self.name)).await
     }
 }
 
 #[async_trait]
-impl<I, O, TQuery, TFuture> Query<I, O> for TQuery
+impl<I, O, TQuery, TFuture> Query<I, O> for Traced<TQuery>
 where
     TQuery: (Fn(I) -> TFuture) + Sync,
     TFuture: Future<Output = O> + Send,
     I: Send + 'static,
 {
     async fn execute(&self, input: I) -> O {
-        self(input).await
+        (self.f)(input).with_context(domain_span(self.name)).await
     }
 }
 
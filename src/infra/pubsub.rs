@@ -0,0 +1,26 @@
+//! Change notifications over redis pub/sub.
+//!
+//! A message on a channel tells that the resource has changed and subscribers
+//! should reload it, the payload is ignored. Other services may publish to the
+//! same channels, e.g. the payment service once a payment is confirmed.
+
+use futures::{Stream, StreamExt};
+use redis::{Client, Connection, RedisResult};
+
+pub fn publish(conn: &mut Connection, channel: &str) -> RedisResult<()> {
+    redis::cmd("PUBLISH").arg(channel).arg("").query(conn)
+}
+
+/// Subscribe to the channels, yields the channel of every message.
+pub async fn subscribe(
+    client: &Client,
+    channels: &[String],
+) -> RedisResult<impl Stream<Item = String>> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    for channel in channels {
+        pubsub.subscribe(channel).await?;
+    }
+    Ok(pubsub
+        .into_on_message()
+        .map(|msg| msg.get_channel_name().to_string()))
+}
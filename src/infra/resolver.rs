@@ -53,6 +53,11 @@ impl<T> Register<T> {
     pub fn factory(f: impl Fn() -> T + Send + Sync + 'static) -> Self {
         Register(Arc::new(f))
     }
+
+    /// Generate a value without a resolver, e.g. in a middleware holding the register.
+    pub fn get(&self) -> T {
+        self.0()
+    }
 }
 
 pub trait BaseResolver {
@@ -0,0 +1,132 @@
+//! Graceful shutdown of the services.
+//!
+//! On SIGTERM or ctrl-c a service stops accepting requests and waits for the ones
+//! in flight, then it closes the pools and stops its background tasks. The whole
+//! shutdown is bounded by the `shutdown_timeout` of the config.
+//!
+//! The pools live as long as the process, r2d2 cannot close them. They are closed
+//! by refusing the calls which would take a connection, see [spawn_blocking], and
+//! by waiting for the ones running, so that e.g. a checkout being written is not
+//! cut. Their connections are closed when the process exits.
+
+use crate::infra::error::{Result, Status};
+use once_cell::sync::OnceCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+static CLOSED: AtomicBool = AtomicBool::new(false);
+static BLOCKING: AtomicUsize = AtomicUsize::new(0);
+
+/// The signal of the shutdown and its deadline.
+pub struct Shutdown {
+    timeout: Option<Duration>,
+    since: OnceCell<Instant>,
+    trigger: Notify,
+}
+
+impl Shutdown {
+    /// Wait `timeout` milliseconds at most once signaled, `0` waits without a
+    /// deadline.
+    pub fn new(timeout: u64) -> Self {
+        Self {
+            timeout: (timeout > 0).then(|| Duration::from_millis(timeout)),
+            since: OnceCell::new(),
+            trigger: Notify::new(),
+        }
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Shut down without a signal, e.g. in tests.
+    pub fn trigger(&self) {
+        self.trigger.notify_one();
+    }
+
+    /// Resolve on SIGTERM, ctrl-c or [Shutdown::trigger], the deadline starts then.
+    pub async fn signal(&self) {
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut sigterm) => sigterm.recv().await,
+                Err(_) => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<Option<()>>();
+        tokio::select! {
+            _ = terminate => {}
+            Ok(()) = tokio::signal::ctrl_c() => {}
+            _ = self.trigger.notified() => {}
+        }
+        self.since.get_or_init(Instant::now);
+        tracing::info!("shutting down, waiting {:?} at most", self.timeout);
+    }
+
+    /// The time left before the deadline, `None` without a deadline.
+    pub fn remaining(&self) -> Option<Duration> {
+        let since = *self.since.get_or_init(Instant::now);
+        self.timeout
+            .map(|timeout| timeout.saturating_sub(since.elapsed()))
+    }
+
+    /// Wait for `fut` until the deadline, `None` if it did not complete in time.
+    pub async fn drain<F: Future>(&self, what: &str, fut: F) -> Option<F::Output> {
+        let res = match self.remaining() {
+            Some(remaining) => tokio::time::timeout(remaining, fut).await.ok(),
+            None => Some(fut.await),
+        };
+        if res.is_none() {
+            tracing::warn!("the deadline of the shutdown is over, stop waiting for {what}");
+        }
+        res
+    }
+
+    /// Close the pools, refusing the new calls and waiting for the running ones
+    /// until the deadline.
+    pub async fn close_pools(&self) {
+        CLOSED.store(true, Ordering::SeqCst);
+        let running = async {
+            while BLOCKING.load(Ordering::SeqCst) > 0 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+        self.drain("the calls to the pools", running).await;
+    }
+}
+
+/// The number of calls running with [spawn_blocking].
+pub fn blocking() -> usize {
+    BLOCKING.load(Ordering::SeqCst)
+}
+
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        BLOCKING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Run `f` on the blocking thread pool, where the connections of the pools are
+/// used. Fail with [Status::unavailable] once the pools are closed.
+pub async fn spawn_blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    BLOCKING.fetch_add(1, Ordering::SeqCst);
+    let running = Running;
+    if CLOSED.load(Ordering::SeqCst) {
+        return Err(Status::unavailable());
+    }
+    Ok(tokio::task::spawn_blocking(move || {
+        let _running = running;
+        f()
+    })
+    .await?)
+}
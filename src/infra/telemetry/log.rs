@@ -0,0 +1,77 @@
+//! The logs of the services, written to stderr as text or JSON lines by the
+//! subscriber of the `log` config.
+//!
+//! A request is served in the scope of its id, see [scope]. The events logged while
+//! serving it are in a `request` span with the id, and its errors carry the id as
+//! request info, see [crate::infra::error::Status], so a response can be correlated
+//! with the logs.
+
+use crate::infra::config::service::{LogConfig, LogFormat};
+use std::future::Future;
+use std::io::{self, IsTerminal};
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+/// The header of the request id, in the requests and responses.
+pub const REQUEST_ID: &str = "x-request-id";
+
+// longer ids are not taken from the clients
+const MAX_REQUEST_ID: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// Install the subscriber of the config globally, it is kept if one is installed.
+pub fn init(config: &LogConfig) {
+    let filter = EnvFilter::try_new(&config.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(|| Stderr);
+    let _ = match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+}
+
+// The stdout of `shop-admin` may be an exported catalog. Written with `eprint!`, the
+// logs of the tests are captured.
+struct Stderr;
+
+impl io::Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        eprint!("{}", String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The id of a request, the one given by the client if it is printable, otherwise a
+/// new one.
+pub fn request_id(given: Option<&str>) -> String {
+    match given {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID
+                && id.bytes().all(|b| b.is_ascii_graphic()) =>
+        {
+            id.to_string()
+        }
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+/// Serve a request in the scope of its id.
+pub async fn scope<F: Future>(id: String, fut: F) -> F::Output {
+    let span = tracing::info_span!("request", request_id = %id);
+    CURRENT.scope(id, fut.instrument(span)).await
+}
+
+/// The id of the request being served, if any.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
@@ -0,0 +1,97 @@
+//! Tracing of the requests with OpenTelemetry, the spans are exported in batches to
+//! the OTLP/HTTP collector of the `trace` config.
+//!
+//! The trace context is propagated with the W3C `traceparent`, in the headers of
+//! the graphql requests, see [crate::graphql::trace], and in the transient metainfo
+//! of the thrift requests, see [crate::rpc::trace]. Besides the spans of the graphql
+//! requests, see [crate::graphql::trace::Spans], spans are started around the
+//! `execute` of the domain and the blocking calls to postgres and redis.
+//!
+//! Without an endpoint the global tracer is a no-op, spans are not recorded but the
+//! context of the callers is still propagated.
+//!
+//! The logs and the ids of the requests are set up by [log].
+
+pub mod log;
+
+use crate::infra::config::service::TraceConfig;
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::trace::{SpanKind, TraceContextExt, Tracer};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self, BatchSpanProcessor, Sampler, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// The name of the tracer of the services.
+pub const TRACER: &str = "shop-backend";
+
+/// Install the propagator and the tracer of the config globally, the spans are
+/// exported by a batch span processor on the tokio runtime.
+pub fn init(name: &str, config: &TraceConfig) {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    if config.endpoint.is_empty() {
+        return;
+    }
+    let timeout = Duration::from_millis(config.timeout);
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(&config.endpoint)
+        .with_timeout(timeout)
+        .build_span_exporter();
+    let exporter = match exporter {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            tracing::warn!("spans will not be exported: {err}");
+            return;
+        }
+    };
+    let processor = BatchSpanProcessor::builder(exporter, runtime::Tokio)
+        .with_max_timeout(timeout)
+        .build();
+    let sampler = Sampler::TraceIdRatioBased(config.sample_ratio);
+    let provider = TracerProvider::builder()
+        .with_span_processor(processor)
+        .with_config(
+            trace::config()
+                // the callers decide whether their traces are recorded
+                .with_sampler(Sampler::ParentBased(Box::new(sampler)))
+                .with_resource(Resource::new([KeyValue::new(
+                    "service.name",
+                    name.to_string(),
+                )])),
+        )
+        .build();
+    global::set_tracer_provider(provider);
+}
+
+/// Export the pending spans and stop the exporter.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+pub fn tracer() -> BoxedTracer {
+    global::tracer(TRACER)
+}
+
+/// The current context with a new child span, the span ends when the context is
+/// dropped.
+pub fn span(name: impl Into<Cow<'static, str>>, kind: SpanKind) -> Context {
+    let tracer = tracer();
+    let span = tracer.span_builder(name).with_kind(kind).start(&tracer);
+    Context::current_with_span(span)
+}
+
+/// A span of a call to a database, e.g. `postgresql` or `redis`.
+pub fn db_span(system: &'static str) -> Context {
+    let cx = span(system, SpanKind::Client);
+    cx.span().set_attribute(KeyValue::new("db.system", system));
+    cx
+}
+
+/// A span of the `execute` of a domain, see [crate::infra::mqsrs::Traced].
+pub fn domain_span(name: &'static str) -> Context {
+    span(name, SpanKind::Internal)
+}
@@ -0,0 +1,141 @@
+//! Validators of user inputs, failed validations are collected by [Validator]
+//! and returned at once as an `invalid_argument` status.
+
+use crate::infra::error::{FieldViolation, Result, Status};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct Validator(Vec<FieldViolation>);
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a violation of `field` unless `valid`.
+    pub fn check(&mut self, field: &str, valid: bool, description: impl Into<String>) {
+        if !valid {
+            self.0.push(FieldViolation {
+                filed: field.to_string(),
+                description: description.into(),
+            });
+        }
+    }
+
+    /// Fails with all the violations recorded.
+    pub fn finish(self) -> Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        Err(Status::invalid_fields(self.0))
+    }
+
+    pub fn into_violations(self) -> Vec<FieldViolation> {
+        self.0
+    }
+}
+
+static EMAIL: Lazy<Regex> = Lazy::new(|| {
+    // the `valid e-mail address` of the HTML standard with a top level domain required
+    Regex::new(
+        r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)+$",
+    )
+    .unwrap()
+});
+
+pub fn is_email(email: &str) -> bool {
+    email.len() <= 254 && EMAIL.is_match(email)
+}
+
+/// ISO 3166-1 alpha-2 codes.
+const COUNTRY_CODES: [&str; 249] = [
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+/// Check an ISO 3166-1 alpha-2 code, it is case-sensitive, e.g. `US`.
+pub fn is_country_code(code: &str) -> bool {
+    COUNTRY_CODES.binary_search(&code).is_ok()
+}
+
+static POSTCODES: Lazy<HashMap<&'static str, Regex>> = Lazy::new(|| {
+    [
+        ("AT", r"^\d{4}$"),
+        ("AU", r"^\d{4}$"),
+        ("BE", r"^\d{4}$"),
+        ("BR", r"^\d{5}-?\d{3}$"),
+        ("CA", r"^[A-Z]\d[A-Z] ?\d[A-Z]\d$"),
+        ("CH", r"^\d{4}$"),
+        ("CN", r"^\d{6}$"),
+        ("CZ", r"^\d{3} ?\d{2}$"),
+        ("DE", r"^\d{5}$"),
+        ("DK", r"^\d{4}$"),
+        ("ES", r"^\d{5}$"),
+        ("FI", r"^\d{5}$"),
+        ("FR", r"^\d{5}$"),
+        ("GB", r"^[A-Z]{1,2}\d[A-Z\d]? ?\d[A-Z]{2}$"),
+        ("IE", r"^[A-Z]\d[\dW] ?[A-Z\d]{4}$"),
+        ("IN", r"^\d{6}$"),
+        ("IT", r"^\d{5}$"),
+        ("JP", r"^\d{3}-?\d{4}$"),
+        ("KR", r"^\d{5}$"),
+        ("MX", r"^\d{5}$"),
+        ("NL", r"^\d{4} ?[A-Z]{2}$"),
+        ("NO", r"^\d{4}$"),
+        ("NZ", r"^\d{4}$"),
+        ("PL", r"^\d{2}-\d{3}$"),
+        ("PT", r"^\d{4}-\d{3}$"),
+        ("RU", r"^\d{6}$"),
+        ("SE", r"^\d{3} ?\d{2}$"),
+        ("SG", r"^\d{6}$"),
+        ("TW", r"^\d{3}(\d{2,3})?$"),
+        ("US", r"^\d{5}(-\d{4})?$"),
+    ]
+    .into_iter()
+    .map(|(country, pattern)| (country, Regex::new(pattern).unwrap()))
+    .collect()
+});
+
+// countries without a known pattern
+static POSTCODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z\d][A-Z\d -]{1,9}$").unwrap());
+
+/// Check a postcode in upper case against the pattern of the country.
+pub fn is_postcode(country_code: &str, postcode: &str) -> bool {
+    POSTCODES
+        .get(country_code)
+        .unwrap_or(&POSTCODE)
+        .is_match(postcode)
+}
+
+/// Normalize a phone number in international format to E.164, e.g.
+/// `+1 (555) 010-9999` and `001 555 010 9999` to `+15550109999`.
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let phone = phone.trim();
+    let number = phone
+        .strip_prefix('+')
+        .or_else(|| phone.strip_prefix("00"))?;
+    let digits = number
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect::<String>();
+    let valid = (7..=15).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.starts_with('0');
+    valid.then(|| format!("+{digits}"))
+}
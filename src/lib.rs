@@ -1,6 +1,8 @@
 #![feature(type_alias_impl_trait)]
 #![feature(backtrace_frames)]
+#![feature(impl_trait_in_assoc_type)]
 
+pub mod admin;
 pub mod domain;
 pub mod graphql;
 pub mod infra;
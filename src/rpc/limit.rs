@@ -0,0 +1,127 @@
+//! The limits of the rpc services as volo layers, requests over the limits are
+//! answered with application errors of their status.
+//!
+//! Servers are layered with a [LimitLayer] of `Limiter::from_config` with the
+//! common part of their config, and clients with a [RetryLayer] of its `retry`.
+//! The code of a rejection is put in the extensions of the context for the
+//! [crate::rpc::metrics::MetricsLayer].
+
+use crate::infra::limit::Limiter;
+use anyhow::anyhow;
+use std::future::Future;
+use std::sync::Arc;
+use volo::context::Context;
+use volo::loadbalance::error::Retryable;
+use volo::{Layer, Service};
+
+#[derive(Clone)]
+pub struct LimitLayer(Arc<Limiter>);
+
+impl LimitLayer {
+    pub fn new(limiter: Limiter) -> Self {
+        Self(Arc::new(limiter))
+    }
+}
+
+impl<S> Layer<S> for LimitLayer {
+    type Service = LimitService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        LimitService {
+            inner,
+            limiter: self.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LimitService<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<Cx, Req, S> Service<Cx, Req> for LimitService<S>
+where
+    Cx: Context + Send + 'static,
+    Req: Send + 'static,
+    S: Service<Cx, Req> + Send + Sync + 'static,
+    S::Error: From<anyhow::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future<'cx> = impl Future<Output = Result<Self::Response, Self::Error>> + Send + 'cx;
+
+    fn call<'cx, 's>(&'s self, cx: &'cx mut Cx, req: Req) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        async move {
+            let res = self.limiter.run(self.inner.call(cx, req)).await;
+            match res {
+                Ok(res) => res,
+                // the status is displayed with its details, the retry info included
+                Err(status) => {
+                    cx.extensions_mut().insert(status.code());
+                    Err(anyhow!("{status}").into())
+                }
+            }
+        }
+    }
+}
+
+/// Retry a request of a client at most `retry` times when it fails with a
+/// retryable error, i.e. a transport error of thrift.
+#[derive(Clone)]
+pub struct RetryLayer {
+    retry: usize,
+}
+
+impl RetryLayer {
+    pub fn new(retry: usize) -> Self {
+        Self { retry }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = RetryService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            retry: self.retry,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RetryService<S> {
+    inner: S,
+    retry: usize,
+}
+
+impl<Cx, Req, S> Service<Cx, Req> for RetryService<S>
+where
+    Cx: Send + 'static,
+    Req: Clone + Send + 'static,
+    S: Service<Cx, Req> + Send + Sync + 'static,
+    S::Error: Retryable + Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future<'cx> = impl Future<Output = Result<Self::Response, Self::Error>> + Send + 'cx;
+
+    fn call<'cx, 's>(&'s self, cx: &'cx mut Cx, req: Req) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        async move {
+            let mut retried = 0;
+            loop {
+                match self.inner.call(cx, req.clone()).await {
+                    Err(e) if e.retryable() && retried < self.retry => retried += 1,
+                    res => return res,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Metrics of the rpc requests as a volo layer, see [crate::infra::metrics].
+//!
+//! Servers are layered with a [MetricsLayer] of their SID outside of the other
+//! layers. Requests are labelled by their method, and errors by the [Code] put in
+//! the extensions of the context, e.g. by [crate::rpc::limit::LimitService], or
+//! `UNKNOWN` if absent.
+
+use crate::infra::error::Code;
+use crate::infra::metrics;
+use std::future::Future;
+use std::time::Instant;
+use volo::context::Context;
+use volo::{Layer, Service};
+
+#[derive(Clone)]
+pub struct MetricsLayer {
+    sid: &'static str,
+}
+
+impl MetricsLayer {
+    pub fn new(sid: &'static str) -> Self {
+        Self { sid }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            sid: self.sid,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    sid: &'static str,
+}
+
+impl<Cx, Req, S> Service<Cx, Req> for MetricsService<S>
+where
+    Cx: Context + Send + 'static,
+    Req: Send + 'static,
+    S: Service<Cx, Req> + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future<'cx> = impl Future<Output = Result<Self::Response, Self::Error>> + Send + 'cx;
+
+    fn call<'cx, 's>(&'s self, cx: &'cx mut Cx, req: Req) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        async move {
+            let start = Instant::now();
+            let res = self.inner.call(cx, req).await;
+            let method = cx.rpc_info().method().map(|method| method.to_string());
+            let operation = method.as_deref().unwrap_or("unknown");
+            metrics::observe(self.sid, operation, start.elapsed());
+            if res.is_err() {
+                let code = cx.extensions().get::<Code>().unwrap_or(&Code::Unknown);
+                metrics::error(self.sid, code.name());
+            }
+            res
+        }
+    }
+}
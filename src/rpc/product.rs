@@ -1 +1,166 @@
+//! The product service over thrift, see the `ProductService` of `idl/product.thrift`.
+//!
+//! The server is layered with the [MetricsLayer], the [TraceLayer] and the
+//! [LimitLayer] of its config, in that order. Once listening it keeps its
+//! registration alive in the registry of the `discovery` config, if any, and it
+//! stops gracefully, see [crate::rpc::serve].
+//!
+//! The clients find its instances with a [Discovery], see [client].
 
+use crate::infra::discovery::{self, Discovery};
+use crate::infra::error::Status;
+use crate::infra::limit::Limiter;
+use crate::infra::mqsrs::{Mutation, Query};
+use crate::infra::resolver::*;
+use crate::infra::shutdown::Shutdown;
+use crate::rpc::limit::{LimitLayer, RetryLayer};
+use crate::rpc::metrics::MetricsLayer;
+use crate::rpc::trace::TraceLayer;
+use crate::rpc::{self, Resolver};
+use anyhow::anyhow;
+use pilota::FastStr;
+use std::net::SocketAddr;
+use volo::net::Address;
+use volo_gen::common::v1::PaginationOption;
+use volo_gen::product::v1::{
+    CatalogChange, CatalogPage, CatalogProduct, NewProduct, Product, ProductConnection,
+    ProductService, ProductServiceClient, ProductServiceClientBuilder, ProductServiceServer,
+    SelectedOption,
+};
+
+/// The errors of the domain are displayed with their details, like the rejections
+/// of the [LimitLayer].
+fn error(status: Status) -> anyhow::Error {
+    anyhow!("{status}")
+}
+
+pub struct ProductServer(pub Resolver);
+
+#[async_trait::async_trait]
+impl ProductService for ProductServer {
+    async fn ping(&self) -> Result<(), anyhow::Error> {
+        self.0.ping().await
+    }
+
+    async fn get_product(&self, id: i64) -> Result<Product, anyhow::Error> {
+        let query = self.0.create_get_product();
+        query.execute(id).await.map_err(error)
+    }
+
+    async fn get_product_by_handle(&self, handle: FastStr) -> Result<Product, anyhow::Error> {
+        let query = self.0.create_get_product_by_handle();
+        query.execute(handle.into_string()).await.map_err(error)
+    }
+
+    async fn list_products(
+        &self,
+        params: PaginationOption,
+    ) -> Result<ProductConnection, anyhow::Error> {
+        let query = self.0.create_list_product();
+        query.execute(params).await.map_err(error)
+    }
+
+    async fn create_product(&self, product: NewProduct) -> Result<Product, anyhow::Error> {
+        let mutation = self.0.create_create_product();
+        mutation.execute(product).await.map_err(error)
+    }
+
+    async fn change_product_handle(
+        &self,
+        id: i64,
+        handle: FastStr,
+    ) -> Result<Product, anyhow::Error> {
+        let mutation = self.0.create_change_product_handle();
+        mutation
+            .execute((id, handle.into_string()))
+            .await
+            .map_err(error)
+    }
+
+    async fn create_product_option(
+        &self,
+        id: i64,
+        name: FastStr,
+        values: Vec<FastStr>,
+    ) -> Result<Product, anyhow::Error> {
+        let values = values.into_iter().map(FastStr::into_string).collect();
+        let mutation = self.0.create_create_product_option();
+        mutation
+            .execute((id, name.into_string(), values))
+            .await
+            .map_err(error)
+    }
+
+    async fn set_variant_options(
+        &self,
+        variant_id: i64,
+        selected_options: Vec<SelectedOption>,
+    ) -> Result<Product, anyhow::Error> {
+        let mutation = self.0.create_set_variant_options();
+        mutation
+            .execute((variant_id, selected_options))
+            .await
+            .map_err(error)
+    }
+
+    async fn import_catalog(
+        &self,
+        products: Vec<CatalogProduct>,
+        dry_run: bool,
+    ) -> Result<Vec<CatalogChange>, anyhow::Error> {
+        let mutation = self.0.create_import_catalog();
+        mutation.execute((products, dry_run)).await.map_err(error)
+    }
+
+    async fn export_catalog(
+        &self,
+        cursor: i64,
+        inventory: bool,
+    ) -> Result<CatalogPage, anyhow::Error> {
+        // thrift does not tell an absent argument, the first page is after 0 all the same
+        let query = self.0.create_export_catalog();
+        query
+            .execute((Some(cursor), inventory))
+            .await
+            .map_err(error)
+    }
+}
+
+impl Resolver {
+    /// Serve the products until SIGTERM or ctrl-c, see [crate::rpc::serve].
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        let shutdown = Shutdown::new(self.config().common.shutdown_timeout);
+        self.serve_until(&shutdown).await
+    }
+
+    /// Serve the products until `shutdown` is signaled, see [crate::rpc::serve].
+    pub async fn serve_until(&self, shutdown: &Shutdown) -> anyhow::Result<()> {
+        let addr: SocketAddr = self.resolve(&self.listen_addr).parse()?;
+        let common = &self.config().common;
+        let server = ProductServiceServer::new(ProductServer(self.clone()))
+            .layer_front(LimitLayer::new(Limiter::from_config(common)))
+            .layer_front(TraceLayer)
+            .layer_front(MetricsLayer::new(Self::SID))
+            .run(Address::from(addr));
+        let heartbeat = discovery::heartbeat(&common.discovery, Self::SID, addr);
+        let metrics = rpc::serve_metrics(self);
+        let res = rpc::serve(server, heartbeat, shutdown).await;
+        if let Some(metrics) = metrics {
+            metrics.abort();
+        }
+        res.map_err(|err| anyhow!("{err}"))
+    }
+}
+
+/// A client of the product service, its instances are found by `discovery`, and
+/// the requests failing with transport errors are retried `retry` times.
+pub fn client(discovery: &Discovery, retry: usize) -> ProductServiceClient {
+    ProductServiceClientBuilder::new(Resolver::SID)
+        .discover(discovery.clone())
+        .load_balance(discovery.balance())
+        .make_transport(discovery.transport())
+        .layer_inner(discovery.health_layer())
+        .layer_outer(TraceLayer)
+        .layer_outer(RetryLayer::new(retry))
+        .build()
+}
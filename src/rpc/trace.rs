@@ -0,0 +1,136 @@
+//! The trace context of the rpc requests as a volo layer, see
+//! [crate::infra::telemetry].
+//!
+//! Clients and servers are layered with a [TraceLayer]. A client starts a span of
+//! the method and sends its context as the transient metainfo `traceparent`, which
+//! thrift carries in the TTHeader of the request. A server continues the trace of
+//! the metainfo of the request.
+//!
+//! The id of the request being served travels the same way, as `x-request-id`, and
+//! the server serves the request in its scope, see [crate::infra::telemetry::log].
+
+use crate::infra::telemetry::{self, log};
+use metainfo::{Forward, MetaInfo, METAINFO};
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::{FutureExt, SpanKind, TraceContextExt};
+use opentelemetry::Context as OtelContext;
+use std::future::Future;
+use volo::context::{Context, Role};
+use volo::{Layer, Service};
+
+#[derive(Clone, Default)]
+pub struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = TraceService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        TraceService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct TraceService<S> {
+    inner: S,
+}
+
+impl<Cx, Req, S> Service<Cx, Req> for TraceService<S>
+where
+    Cx: Context + Send + 'static,
+    Req: Send + 'static,
+    S: Service<Cx, Req> + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future<'cx> = impl Future<Output = Result<Self::Response, Self::Error>> + Send + 'cx;
+
+    fn call<'cx, 's>(&'s self, cx: &'cx mut Cx, req: Req) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        let method = cx
+            .rpc_info()
+            .method()
+            .map(|method| method.to_string())
+            .unwrap_or_default();
+        let (span, request_id) = match cx.rpc_info().role() {
+            Role::Client => {
+                let span = telemetry::span(method, SpanKind::Client);
+                let _ = METAINFO.try_with(|metainfo| {
+                    let mut metainfo = metainfo.borrow_mut();
+                    inject(&span, &mut metainfo);
+                    if let Some(id) = log::current() {
+                        metainfo.set_transient(log::REQUEST_ID, id);
+                    }
+                });
+                (span, None)
+            }
+            Role::Server => {
+                let (parent, request_id) = METAINFO
+                    .try_with(|metainfo| {
+                        let metainfo = metainfo.borrow();
+                        (extract(&metainfo), metainfo.get_transient(log::REQUEST_ID))
+                    })
+                    .unwrap_or_else(|_| (OtelContext::current(), None));
+                let _guard = parent.attach();
+                let span = telemetry::span(method, SpanKind::Server);
+                (span, request_id.map(|id| log::request_id(Some(&id))))
+            }
+        };
+        // the inner service may start its work before being polled
+        let guard = span.clone().attach();
+        let fut = self.inner.call(cx, req);
+        drop(guard);
+        async move {
+            let fut = fut.with_context(span.clone());
+            let res = match request_id {
+                Some(id) => log::scope(id, fut).await,
+                None => fut.await,
+            };
+            if res.is_err() {
+                span.span()
+                    .set_status(opentelemetry::trace::Status::error(""));
+            }
+            res
+        }
+    }
+}
+
+/// Put the trace context of `cx` in the transient metainfo.
+pub fn inject(cx: &OtelContext, metainfo: &mut MetaInfo) {
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(cx, &mut Metainfo(metainfo))
+    });
+}
+
+/// The trace context of the transient metainfo, the current one if absent.
+pub fn extract(metainfo: &MetaInfo) -> OtelContext {
+    global::get_text_map_propagator(|propagator| propagator.extract(&MetainfoRef(metainfo)))
+}
+
+struct Metainfo<'a>(&'a mut MetaInfo);
+
+impl Injector for Metainfo<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.set_transient(key.to_string(), value);
+    }
+}
+
+struct MetainfoRef<'a>(&'a MetaInfo);
+
+impl Extractor for MetainfoRef<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .get_all_transients()
+            .and_then(|transients| transients.get(key))
+            .map(|value| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .get_all_transients()
+            .map(|transients| transients.keys().map(|key| key.as_str()).collect())
+            .unwrap_or_default()
+    }
+}
@@ -6,9 +6,9 @@ diesel::table! {
         cid -> Int8,
         pid -> Int8,
         quantity -> Int4,
-        variant -> Int4,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        vid -> Int8,
     }
 }
 
@@ -29,13 +29,52 @@ diesel::table! {
         pid -> Nullable<Int8>,
         shipping_fee -> Nullable<Money>,
         email -> Nullable<Varchar>,
-        full_name -> Nullable<Varchar>,
-        country_code -> Nullable<Varchar>,
-        address -> Nullable<Varchar>,
-        postcode -> Nullable<Varchar>,
         phone -> Nullable<Varchar>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        shipping_aid -> Nullable<Int8>,
+        billing_aid -> Nullable<Int8>,
+        billing_same_as_shipping -> Bool,
+    }
+}
+
+diesel::table! {
+    t_collection_products (id) {
+        id -> Int8,
+        cid -> Int8,
+        pid -> Int8,
+        order_idx -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    t_collections (id) {
+        id -> Int8,
+        handle -> Varchar,
+        title -> Varchar,
+        description -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    t_mailing_addresses (id) {
+        id -> Int8,
+        customer_id -> Nullable<Varchar>,
+        first_name -> Nullable<Varchar>,
+        last_name -> Nullable<Varchar>,
+        company -> Nullable<Varchar>,
+        line1 -> Nullable<Varchar>,
+        line2 -> Nullable<Varchar>,
+        city -> Nullable<Varchar>,
+        province -> Nullable<Varchar>,
+        zip -> Nullable<Varchar>,
+        country_code -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -58,6 +97,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    t_product_option_values (id) {
+        id -> Int8,
+        oid -> Int8,
+        value -> Varchar,
+        order_idx -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    t_product_options (id) {
+        id -> Int8,
+        pid -> Int8,
+        name -> Varchar,
+        order_idx -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    t_product_redirects (id) {
+        id -> Int8,
+        handle -> Varchar,
+        pid -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    t_product_variant_options (id) {
+        id -> Int8,
+        vid -> Int8,
+        oid -> Int8,
+        ovid -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     t_product_variants (id) {
         id -> Int8,
@@ -68,6 +150,7 @@ diesel::table! {
         order_idx -> Int4,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        external_key -> Nullable<Varchar>,
     }
 }
 
@@ -80,6 +163,8 @@ diesel::table! {
         currency_code -> Varchar,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        handle -> Varchar,
+        external_key -> Nullable<Varchar>,
     }
 }
 
@@ -91,17 +176,33 @@ diesel::table! {
 }
 
 diesel::joinable!(t_cart_entries -> t_carts (cid));
+diesel::joinable!(t_cart_entries -> t_product_variants (vid));
 diesel::joinable!(t_cart_entries -> t_products (pid));
 diesel::joinable!(t_checkouts -> t_carts (cid));
+diesel::joinable!(t_collection_products -> t_collections (cid));
+diesel::joinable!(t_collection_products -> t_products (pid));
 diesel::joinable!(t_product_images -> t_products (pid));
+diesel::joinable!(t_product_option_values -> t_product_options (oid));
+diesel::joinable!(t_product_options -> t_products (pid));
+diesel::joinable!(t_product_redirects -> t_products (pid));
+diesel::joinable!(t_product_variant_options -> t_product_option_values (ovid));
+diesel::joinable!(t_product_variant_options -> t_product_options (oid));
+diesel::joinable!(t_product_variant_options -> t_product_variants (vid));
 diesel::joinable!(t_product_variants -> t_products (pid));
 
 diesel::allow_tables_to_appear_in_same_query!(
     t_cart_entries,
     t_carts,
     t_checkouts,
+    t_collection_products,
+    t_collections,
+    t_mailing_addresses,
     t_payment_methods,
     t_product_images,
+    t_product_option_values,
+    t_product_options,
+    t_product_redirects,
+    t_product_variant_options,
     t_product_variants,
     t_products,
     t_shipping_methods,
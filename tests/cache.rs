@@ -0,0 +1,88 @@
+//! Tests of the redis cache, run with a reachable redis:
+//!
+//! ```shell
+//! REDIS_URL=redis://127.0.0.1/ cargo test --test cache -- --ignored
+//! ```
+
+use shop_backend::infra::cache::Cache;
+use std::cell::Cell;
+use std::collections::HashMap;
+use volo_gen::product::v1::Product;
+
+fn cache() -> Cache<Box<redis::Connection>> {
+    let url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1/".to_string());
+    let conn = redis::Client::open(url).unwrap().get_connection().unwrap();
+    Cache::new(Some(Box::new(conn)))
+}
+
+fn product(id: i64) -> Product {
+    Product {
+        id,
+        title: format!("product {id}").into(),
+        ..Default::default()
+    }
+}
+
+// keys are unique per run so that tests do not see each other's entries
+fn prefix() -> String {
+    format!("test:{}", std::process::id())
+}
+
+#[test]
+#[ignore = "requires redis"]
+fn fetch_reads_through() {
+    let mut cache = cache();
+    let key = format!("{}:fetch", prefix());
+    let loads = Cell::new(0);
+    let load = || {
+        loads.set(loads.get() + 1);
+        Ok(product(1))
+    };
+
+    assert_eq!(cache.fetch(&key, 60, load).unwrap(), product(1));
+    assert_eq!(cache.fetch(&key, 60, load).unwrap(), product(1));
+    assert_eq!(loads.get(), 1);
+
+    cache.evict(&key);
+    assert_eq!(cache.fetch(&key, 60, load).unwrap(), product(1));
+    assert_eq!(loads.get(), 2);
+}
+
+#[test]
+#[ignore = "requires redis"]
+fn fetch_many_loads_missing_only() {
+    let mut cache = cache();
+    let prefix = prefix();
+    let key = |id: &i64| format!("{prefix}:many:{id}");
+    let load = |ids: Vec<i64>| Ok(ids.into_iter().map(|id| (id, product(id))).collect());
+
+    cache.fetch_many(vec![1], key, 60, load).unwrap();
+    let res = cache
+        .fetch_many(vec![1, 2], key, 60, |ids| {
+            assert_eq!(ids, vec![2]);
+            load(ids)
+        })
+        .unwrap();
+    assert_eq!(res, HashMap::from([(1, product(1)), (2, product(2))]));
+}
+
+#[test]
+#[ignore = "requires redis"]
+fn bump_changes_version() {
+    let mut cache = cache();
+    let namespace = format!("{}:namespace", prefix());
+
+    let version = cache.version(&namespace);
+    cache.bump(&namespace);
+    assert_eq!(cache.version(&namespace), version + 1);
+}
+
+#[test]
+fn unavailable_redis_is_bypassed() {
+    let mut cache = Cache::<Box<redis::Connection>>::new(None);
+    assert_eq!(
+        cache.fetch("key", 60, || Ok(product(1))).unwrap(),
+        product(1)
+    );
+    assert_eq!(cache.version("namespace"), 0);
+}
@@ -0,0 +1,193 @@
+use shop_backend::infra::config::service::{DiscoveryConfig, DiscoveryKind};
+use shop_backend::infra::discovery::dns::Dns;
+use shop_backend::infra::discovery::registry::{FileRegistry, RedisRegistry, Registry};
+use shop_backend::infra::discovery::{heartbeat, Discovery, Source, Static};
+use std::collections::HashMap;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use volo::net::Address;
+use volo_gen::product::v1::ProductServiceClientBuilder;
+
+const SID: &str = "product-thrift";
+
+fn addr(port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], port))
+}
+
+fn discovery(addrs: Vec<SocketAddr>) -> Discovery {
+    let source = Static(HashMap::from([(SID.to_string(), addrs)]));
+    Discovery::new(
+        Arc::new(source),
+        Duration::from_secs(10),
+        Duration::from_secs(30),
+    )
+}
+
+#[tokio::test]
+async fn healthy_instances_are_picked_first() {
+    let discovery = discovery(vec![addr(1), addr(2), addr(3)]);
+    let balance = discovery.balance();
+    let instances = discovery.instances(SID).await.unwrap();
+    let first = (0..3)
+        .map(|_| balance.pick(&instances)[0].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(first, [addr(1), addr(2), addr(3)].map(Address::Ip).to_vec());
+
+    discovery.health().fail(&Address::Ip(addr(1)));
+    for _ in 0..3 {
+        let picked = balance.pick(&instances);
+        assert_eq!(picked.len(), 3);
+        assert_ne!(picked[0], Address::Ip(addr(1)));
+        assert_eq!(picked[2], Address::Ip(addr(1)));
+    }
+    discovery.health().recover(&Address::Ip(addr(1)));
+    assert!(discovery.health().is_healthy(&Address::Ip(addr(1))));
+    assert!(discovery.instances("cart-thrift").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn clients_eject_failing_instances() {
+    // the ports are closed once the listeners are dropped
+    let closed = (0..2)
+        .map(|_| TcpListener::bind(addr(0)).unwrap().local_addr().unwrap())
+        .collect::<Vec<_>>();
+    let discovery = discovery(closed.clone());
+    let client = ProductServiceClientBuilder::new(SID)
+        .discover(discovery.clone())
+        .load_balance(discovery.balance())
+        .make_transport(discovery.transport())
+        .layer_inner(discovery.health_layer())
+        .build();
+    assert!(client.ping().await.is_err());
+    assert!(client.ping().await.is_err());
+    for addr in closed {
+        assert!(!discovery.health().is_healthy(&Address::Ip(addr)));
+    }
+}
+
+/// Answer a query with SRV records of `localhost:8081`, and of an unresolvable
+/// target with a lower priority.
+async fn nameserver() -> SocketAddr {
+    let socket = UdpSocket::bind(addr(0)).await.unwrap();
+    let local = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 512];
+        loop {
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let query = &buf[..len];
+            // `_nx._tcp.<domain>` does not exist
+            let nx = query[13..].starts_with(b"_nx");
+            let mut res = query[..2].to_vec();
+            let records = if nx {
+                vec![]
+            } else {
+                vec![(10u16, "localhost"), (20, "backup.invalid")]
+            };
+            res.extend([0x81, if nx { 0x83 } else { 0x80 }, 0, 1, 0]);
+            res.extend([records.len() as u8, 0, 0, 0, 0]);
+            res.extend(&query[12..]);
+            for (priority, target) in records {
+                let mut rdata = priority.to_be_bytes().to_vec();
+                rdata.extend([0, 5, 0x1f, 0x91]); // weight 5, port 8081
+                for label in target.split('.') {
+                    rdata.push(label.len() as u8);
+                    rdata.extend(label.as_bytes());
+                }
+                rdata.push(0);
+                res.extend([0xc0, 12, 0, 33, 0, 1, 0, 0, 0, 60]);
+                res.extend((rdata.len() as u16).to_be_bytes());
+                res.extend(rdata);
+            }
+            socket.send_to(&res, peer).await.unwrap();
+        }
+    });
+    local
+}
+
+#[tokio::test]
+async fn dns_srv_records_are_resolved() {
+    let dns = Dns::new("shop.local".to_string(), Some(nameserver().await));
+    let addrs = dns.lookup(SID).await.unwrap();
+    assert!(addrs.contains(&(addr(8081), 5)), "{addrs:?}");
+    assert!(addrs.iter().all(|(addr, _)| addr.port() == 8081));
+    assert!(dns.lookup("nx").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn file_registrations_expire() {
+    let path = std::env::temp_dir().join(format!("shop-registry-{}.json", std::process::id()));
+    let registry = FileRegistry::new(&path);
+    assert!(registry.lookup(SID).await.unwrap().is_empty());
+    registry
+        .register(SID, addr(8081), Duration::from_secs(60))
+        .await
+        .unwrap();
+    registry
+        .register(SID, addr(8082), Duration::from_millis(50))
+        .await
+        .unwrap();
+    registry
+        .register("cart-thrift", addr(8083), Duration::from_secs(60))
+        .await
+        .unwrap();
+    let mut addrs = registry.lookup(SID).await.unwrap();
+    addrs.sort();
+    assert_eq!(addrs, [(addr(8081), 1), (addr(8082), 1)]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(registry.lookup(SID).await.unwrap(), [(addr(8081), 1)]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn heartbeats_renew_registrations() {
+    let path = std::env::temp_dir().join(format!("shop-heartbeat-{}.json", std::process::id()));
+    let config = DiscoveryConfig {
+        kind: DiscoveryKind::File,
+        registry: path.to_string_lossy().to_string(),
+        ttl: 1,
+        ..Default::default()
+    };
+    let task = heartbeat(&config, SID, addr(8081)).unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let discovery = Discovery::from_config(&config).unwrap();
+    let instances = discovery.instances(SID).await.unwrap();
+    assert_eq!(instances[0].address, Address::Ip(addr(8081)));
+    task.abort();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn invalid_registries_are_errors() {
+    // the configs are validated when loaded, a discovery made of another one fails
+    // instead of panicking
+    let config = DiscoveryConfig {
+        kind: DiscoveryKind::Redis,
+        registry: "registry.json".to_string(),
+        ..Default::default()
+    };
+    assert!(Discovery::from_config(&config).is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires redis"]
+async fn redis_registrations_expire() {
+    let registry = RedisRegistry::new("redis://127.0.0.1/").unwrap();
+    let sid = format!("test-{}", std::process::id());
+    registry
+        .register(&sid, addr(8081), Duration::from_secs(60))
+        .await
+        .unwrap();
+    registry
+        .register(&sid, addr(8082), Duration::from_millis(50))
+        .await
+        .unwrap();
+    let mut addrs = registry.lookup(&sid).await.unwrap();
+    addrs.sort();
+    assert_eq!(addrs, [(addr(8081), 1), (addr(8082), 1)]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(registry.lookup(&sid).await.unwrap(), [(addr(8081), 1)]);
+}
@@ -0,0 +1,131 @@
+use async_graphql::{ErrorExtensions, Value};
+use shop_backend::infra::error::*;
+use std::time::Duration;
+
+fn extension(status: &Status, name: &str) -> Value {
+    let err = status.extend();
+    err.extensions.unwrap().get(name).unwrap().clone()
+}
+
+#[test]
+fn every_code_is_extended() {
+    let cases = [
+        (Status::ok(), Code::Ok, "OK", 200),
+        (Status::cancelled(), Code::Cancelled, "CANCELLED", 499),
+        (Status::unknown(), Code::Unknown, "UNKNOWN", 500),
+        (
+            Status::invalid_argument("email", "foo", "an email address"),
+            Code::InvalidArgument,
+            "INVALID_ARGUMENT",
+            400,
+        ),
+        (
+            Status::deadline_exceeded(),
+            Code::DeadlineExceeded,
+            "DEADLINE_EXCEEDED",
+            504,
+        ),
+        (
+            Status::not_found("product(1)"),
+            Code::NotFound,
+            "NOT_FOUND",
+            404,
+        ),
+        (
+            Status::already_exists("product(1)"),
+            Code::AlreadyExists,
+            "ALREADY_EXISTS",
+            409,
+        ),
+        (
+            Status::permission_denied("write", "product(1)"),
+            Code::PermissionDenied,
+            "PERMISSION_DENIED",
+            403,
+        ),
+        (
+            Status::resource_exhausted(),
+            Code::ResourceExhausted,
+            "RESOURCE_EXHAUSTED",
+            429,
+        ),
+        (
+            Status::failed_precondition(),
+            Code::FailedPrecondition,
+            "FAILED_PRECONDITION",
+            400,
+        ),
+        (Status::aborted(), Code::Aborted, "ABORTED", 409),
+        (
+            Status::out_of_range("first", Range::Continuous(1, 100)),
+            Code::OutOfRange,
+            "OUT_OF_RANGE",
+            400,
+        ),
+        (
+            Status::unimplemented(),
+            Code::Unimplemented,
+            "UNIMPLEMENTED",
+            501,
+        ),
+        (Status::internal(), Code::Internal, "INTERNAL", 500),
+        (Status::unavailable(), Code::Unavailable, "UNAVAILABLE", 503),
+        (Status::data_loss(), Code::DataLoss, "DATA_LOSS", 500),
+        (
+            Status::unauthenticated(),
+            Code::Unauthenticated,
+            "UNAUTHENTICATED",
+            401,
+        ),
+    ];
+    for (status, code, name, http) in cases {
+        assert_eq!(status.code(), code);
+        assert_eq!(extension(&status, "code"), Value::from(name));
+        assert_eq!(extension(&status, "httpStatus"), Value::from(http));
+        assert_eq!(extension(&status, "details"), Value::List(vec![]));
+    }
+}
+
+#[test]
+fn message_is_kept() {
+    let err = Status::not_found("product(1)").extend();
+    assert_eq!(err.message, "Resource 'product(1)' not found.");
+}
+
+#[test]
+fn details_are_extended() {
+    let status = Status::invalid_argument("email", "foo", "an email address")
+        .with_bad_request(vec![FieldViolation {
+            filed: "email".to_string(),
+            description: "not an email address".to_string(),
+        }])
+        .with_retry_info(Duration::from_secs(3));
+    let details = serde_json::to_value(extension(&status, "details")).unwrap();
+    assert_eq!(
+        details,
+        serde_json::json!([
+            {
+                "@type": "BadRequest",
+                "field_violations": [{"filed": "email", "description": "not an email address"}],
+            },
+            {
+                "@type": "RetryInfo",
+                "retry_delay": {"secs": 3, "nanos": 0},
+            },
+        ])
+    );
+}
+
+#[test]
+fn sensitive_details_are_filtered() {
+    let status = Status::internal()
+        .with_debug_info(true, "diesel database error")
+        .with_precondition(vec![PreconditionViolation {
+            r#type: "STOCK".to_string(),
+            subject: "product_variant(1)".to_string(),
+            description: "out of stock".to_string(),
+        }]);
+    let details = serde_json::to_value(extension(&status, "details")).unwrap();
+    assert_eq!(details.as_array().unwrap().len(), 1);
+    assert_eq!(details[0]["@type"], "PreconditionFailure");
+}
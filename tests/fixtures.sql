@@ -0,0 +1,56 @@
+-- Fixtures loaded into the database of every integration test, ids are fixed
+-- so that tests can refer to them.
+
+insert into t_products (id, handle, title, sub_title, description, currency_code)
+values (1, 'super-mario-odyssey', 'Super Mario Odyssey', 'Nintendo Switch', 'A 3D platformer.', 'USD'),
+       (2, 'pro-controller', 'Pro Controller', 'Nintendo Switch', 'A wireless controller.', 'USD'),
+       (3, 'zelda-tears-of-the-kingdom', 'The Legend of Zelda: Tears of the Kingdom', 'Nintendo Switch',
+        'An open-world adventure.', 'USD');
+
+insert into t_product_variants (id, pid, price, title, inventory_count, order_idx)
+values (1, 1, 59.99, 'Standard', 10, 0),
+       (2, 1, 89.99, 'Collector', 2, 1),
+       (3, 2, 69.99, 'Black', 5, 0),
+       (4, 3, 69.99, 'Standard', 0, 0); -- out of stock
+
+insert into t_product_images (id, pid, url, alt_text, order_idx)
+values (1, 1, 'https://example.com/mario-1.png', 'Cover', 0),
+       (2, 1, 'https://example.com/mario-2.png', 'Back', 1),
+       (3, 2, 'https://example.com/controller.png', 'Controller', 0);
+
+insert into t_product_options (id, pid, name, order_idx)
+values (1, 1, 'Edition', 0);
+
+insert into t_product_option_values (id, oid, value, order_idx)
+values (1, 1, 'Standard', 0),
+       (2, 1, 'Collector', 1);
+
+insert into t_product_variant_options (id, vid, oid, ovid)
+values (1, 1, 1, 1),
+       (2, 2, 1, 2);
+
+insert into t_collections (id, handle, title, description)
+values (1, 'switch-games', 'Switch Games', 'Games for Nintendo Switch.');
+
+insert into t_collection_products (id, cid, pid, order_idx)
+values (1, 1, 1, 0),
+       (2, 1, 3, 1);
+
+insert into t_shipping_methods (id, vendor)
+values (1, 'UPS'),
+       (2, 'FedEx');
+
+insert into t_payment_methods (id, vendor)
+values (1, 'Stripe'),
+       (2, 'PayPal');
+
+select setval('t_products_id_seq', 3),
+       setval('t_product_variants_id_seq', 4),
+       setval('t_product_images_id_seq', 3),
+       setval('t_product_options_id_seq', 1),
+       setval('t_product_option_values_id_seq', 2),
+       setval('t_product_variant_options_id_seq', 2),
+       setval('t_collections_id_seq', 1),
+       setval('t_collection_products_id_seq', 2),
+       setval('t_shipping_methods_id_seq', 2),
+       setval('t_payment_methods_id_seq', 2);
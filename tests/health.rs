@@ -0,0 +1,76 @@
+//! Tests of the probes, the ignored ones run with PostgreSQL, and redis for the
+//! service to be ready:
+//!
+//! ```shell
+//! REDIS_URL=redis://127.0.0.1/ cargo test --test health -- --ignored
+//! ```
+
+mod common;
+
+use common::Harness;
+use diesel::{sql_query, RunQueryDsl};
+use poem::http::StatusCode;
+use poem::{get, Endpoint, EndpointExt, Request};
+use r2d2::Pool;
+use serde_json::Value;
+use shop_backend::graphql::{sys, Resolver};
+use shop_backend::infra::health::MIGRATION;
+use shop_backend::infra::resolver::Register;
+
+async fn readyz(resolver: &Resolver) -> (StatusCode, Value) {
+    let endpoint = get(sys::readyz).data(resolver.clone());
+    let res = endpoint.call(Request::default()).await.unwrap();
+    let status = res.status();
+    let body = res.into_body().into_json().await.unwrap();
+    (status, body)
+}
+
+#[tokio::test]
+async fn alive_without_dependencies() {
+    let res = get(sys::healthz).call(Request::default()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn not_ready_with_unreachable_dependencies() {
+    // the pools are made without connections, they fail the checks instead
+    let (status, body) = readyz(&common::unreachable()).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_ne!(body["checks"]["pgsql"], "ok");
+    assert_ne!(body["checks"]["redis"], "ok");
+}
+
+#[tokio::test]
+#[ignore = "requires PostgreSQL"]
+async fn not_ready_with_unavailable_dependencies() {
+    let h = Harness::new();
+    // redis of the harness is unreachable
+    let (status, body) = readyz(&h.resolver).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["checks"]["pgsql"], "ok");
+    assert_ne!(body["checks"]["redis"], "ok");
+
+    sql_query("DELETE FROM __diesel_schema_migrations WHERE version = $1")
+        .bind::<diesel::sql_types::Text, _>(MIGRATION)
+        .execute(&mut h.conn())
+        .unwrap();
+    let (_, body) = readyz(&h.resolver).await;
+    assert_eq!(
+        body["checks"]["pgsql"],
+        format!("migration {MIGRATION} is not applied")
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires PostgreSQL and redis"]
+async fn ready_with_dependencies() {
+    let mut h = Harness::new();
+    h.resolver.redis = Register::once_ref(|| {
+        let url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1/".to_string());
+        Pool::builder().build_unchecked(redis::Client::open(url).unwrap())
+    });
+    let (status, body) = readyz(&h.resolver).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["checks"]["redis"], "ok");
+}
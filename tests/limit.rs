@@ -0,0 +1,333 @@
+//! Tests of the limits, the ignored ones run with PostgreSQL and redis:
+//!
+//! ```shell
+//! REDIS_URL=redis://127.0.0.1/ cargo test --test limit -- --ignored
+//! ```
+
+mod common;
+
+use common::Harness;
+use poem::http::{header, StatusCode};
+use poem::{handler, Endpoint, EndpointExt, Request};
+use r2d2::Pool;
+use shop_backend::graphql::limit::{Client, FieldLimitConfig, Limit};
+use shop_backend::infra::config::service::{LimitConfig, RateLimitConfig};
+use shop_backend::infra::error::Code;
+use shop_backend::infra::limit::{self, Limiter};
+use shop_backend::infra::resolver::Register;
+use shop_backend::rpc::limit::{LimitLayer, RetryLayer};
+use std::collections::HashMap;
+use std::future::{pending, ready, Ready};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use volo::context::{Context, Role, RpcCx, RpcInfo};
+use volo::loadbalance::error::Retryable;
+use volo::{Layer, Service};
+
+fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1/".to_string())
+}
+
+fn rate(num: u64, per: Duration) -> RateLimitConfig {
+    RateLimitConfig { num, per }
+}
+
+fn limit(concurrency: usize, rate: u64) -> LimitConfig {
+    LimitConfig {
+        concurrency,
+        rate: RateLimitConfig {
+            num: rate,
+            per: Duration::from_secs(1),
+        },
+    }
+}
+
+#[tokio::test]
+async fn rate_is_limited() {
+    let limiter = Limiter::new(0, &limit(0, 2), false);
+    assert_eq!(limiter.run(ready(1)).await.unwrap(), 1);
+    assert_eq!(limiter.run(ready(2)).await.unwrap(), 2);
+    let status = limiter.run(ready(3)).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    let delay = status.retry_delay().unwrap();
+    assert!(
+        delay > Duration::ZERO && delay <= Duration::from_millis(500),
+        "{delay:?}"
+    );
+}
+
+#[tokio::test]
+async fn requests_are_shed() {
+    let limiter = Arc::new(Limiter::new(0, &limit(1, 0), true));
+    let holding = tokio::spawn({
+        let limiter = limiter.clone();
+        async move { limiter.run(pending::<()>()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let status = limiter.run(ready(())).await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(status.retry_delay(), Some(Duration::from_secs(1)));
+
+    // the concurrency is released by a completed request
+    holding.abort();
+    let _ = holding.await;
+    assert!(limiter.run(ready(())).await.is_ok());
+}
+
+#[tokio::test]
+async fn queued_requests_time_out() {
+    let limiter = Arc::new(Limiter::new(100, &limit(1, 0), false));
+    let first = tokio::spawn({
+        let limiter = limiter.clone();
+        async move {
+            limiter
+                .run(tokio::time::sleep(Duration::from_millis(50)))
+                .await
+        }
+    });
+    // waits for the first request
+    assert!(limiter.run(ready(())).await.is_ok());
+    assert!(first.await.unwrap().is_ok());
+
+    let status = limiter.run(pending::<()>()).await.unwrap_err();
+    assert_eq!(status.code(), Code::DeadlineExceeded);
+}
+
+#[handler]
+fn hello() -> &'static str {
+    "hello"
+}
+
+#[tokio::test]
+async fn graphql_rejects_with_retry_after() {
+    let limiter = Arc::new(Limiter::new(0, &limit(0, 1), false));
+    let endpoint = hello.with(Limit::new(Register::once(move || limiter.clone())));
+    let res = endpoint.call(Request::default()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = endpoint.call(Request::default()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.headers()[header::RETRY_AFTER], "1");
+    let body: serde_json::Value = res.into_body().into_json().await.unwrap();
+    assert_eq!(
+        body["errors"][0]["extensions"]["code"],
+        "RESOURCE_EXHAUSTED"
+    );
+}
+
+#[derive(Debug)]
+struct Error {
+    retryable: bool,
+    message: String,
+}
+
+impl Retryable for Error {
+    fn retryable(&self) -> bool {
+        self.retryable
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Self {
+            retryable: false,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Fails with a retryable error until it is called `succeed_at` times.
+struct Flaky {
+    calls: AtomicUsize,
+    succeed_at: usize,
+}
+
+impl<Cx: Send + 'static> Service<Cx, ()> for Flaky {
+    type Response = usize;
+    type Error = Error;
+    type Future<'cx> = Ready<Result<usize, Error>>;
+
+    fn call<'cx, 's>(&'s self, _cx: &'cx mut Cx, _req: ()) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        let calls = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+        ready(if calls < self.succeed_at {
+            Err(Error {
+                retryable: true,
+                message: "connection reset".to_string(),
+            })
+        } else {
+            Ok(calls)
+        })
+    }
+}
+
+fn flaky(succeed_at: usize) -> Flaky {
+    Flaky {
+        calls: AtomicUsize::new(0),
+        succeed_at,
+    }
+}
+
+#[tokio::test]
+async fn rpc_rejects_with_status() {
+    let service = LimitLayer::new(Limiter::new(0, &limit(0, 1), false)).layer(flaky(0));
+    let mut cx = RpcCx::<(), ()>::new(RpcInfo::with_role(Role::Server), ());
+    assert_eq!(service.call(&mut cx, ()).await.unwrap(), 1);
+    let err = service.call(&mut cx, ()).await.unwrap_err();
+    assert!(!err.retryable());
+    assert!(err.message.contains("RetryInfo"), "{}", err.message);
+    // the code of the rejection is kept for the metrics
+    assert_eq!(
+        cx.extensions().get::<Code>(),
+        Some(&Code::ResourceExhausted)
+    );
+}
+
+#[tokio::test]
+async fn rpc_clients_retry() {
+    let service = RetryLayer::new(2).layer(flaky(3));
+    assert_eq!(service.call(&mut (), ()).await.unwrap(), 3);
+
+    let service = RetryLayer::new(2).layer(flaky(4));
+    assert!(service.call(&mut (), ()).await.unwrap_err().retryable());
+}
+
+#[test]
+#[ignore = "requires redis"]
+fn windows_slide() {
+    let mut conn = redis::Client::open(redis_url())
+        .unwrap()
+        .get_connection()
+        .unwrap();
+    let key = format!("test:{}:window", std::process::id());
+    let rate = rate(2, Duration::from_millis(500));
+    assert_eq!(limit::take_window(&mut conn, &key, &rate).unwrap(), None);
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(limit::take_window(&mut conn, &key, &rate).unwrap(), None);
+    // until the first request leaves the window
+    let delay = limit::take_window(&mut conn, &key, &rate).unwrap().unwrap();
+    assert!(delay <= Duration::from_millis(300), "{delay:?}");
+    std::thread::sleep(delay);
+    assert_eq!(limit::take_window(&mut conn, &key, &rate).unwrap(), None);
+    assert!(limit::take_window(&mut conn, &key, &rate)
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+#[ignore = "requires PostgreSQL and redis"]
+async fn fields_are_limited_per_client() {
+    let h = Harness::new();
+    let mut resolver = h.resolver.clone();
+    resolver.redis = Register::once_ref(|| {
+        Pool::builder().build_unchecked(redis::Client::open(redis_url()).unwrap())
+    });
+    resolver.field_limit = Register::once(|| {
+        let quota = FieldLimitConfig {
+            ip: rate(2, Duration::from_secs(60)),
+            token: rate(1, Duration::from_secs(60)),
+        };
+        Arc::new(HashMap::from([(
+            "GraphqlMutation.createCart".to_string(),
+            quota,
+        )]))
+    });
+    let schema = resolver.schema();
+    // unique per run, the windows outlive the tests
+    let pid = std::process::id();
+    let ip = IpAddr::from([10, (pid >> 16) as u8, (pid >> 8) as u8, pid as u8]);
+    let create = |client: Option<Client>| {
+        let mut req = async_graphql::Request::new("mutation { createCart { cart { id } } }");
+        if let Some(client) = client {
+            req = req.data(client);
+        }
+        schema.execute(req)
+    };
+
+    for _ in 0..2 {
+        let res = create(Some(Client::new(Some(ip), None))).await;
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert!(res.http_headers.get(header::RETRY_AFTER).is_none());
+    }
+    let res = create(Some(Client::new(Some(ip), None))).await;
+    let err = serde_json::to_value(&res.errors[0]).unwrap();
+    assert_eq!(err["path"], serde_json::json!(["createCart"]));
+    assert_eq!(err["extensions"]["code"], "RESOURCE_EXHAUSTED");
+    let details = err["extensions"]["details"].as_array().unwrap();
+    assert_eq!(details[0]["@type"], "QuotaFailure");
+    assert_eq!(details[0]["violations"][0]["subject"], format!("ip:{ip}"));
+    assert_eq!(details[1]["@type"], "RetryInfo");
+    let retry_after = res.http_headers[header::RETRY_AFTER].to_str().unwrap();
+    assert!((59..=60).contains(&retry_after.parse::<u64>().unwrap()));
+
+    // other clients and the requests without a client are not limited
+    let token = format!("test-{pid}");
+    let res = create(Some(Client::new(None, Some(&token)))).await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let res = create(Some(Client::new(None, Some(&token)))).await;
+    let err = serde_json::to_value(&res.errors[0]).unwrap();
+    assert_eq!(
+        err["extensions"]["details"][0]["violations"][0]["subject"],
+        "token"
+    );
+    assert!(create(None).await.errors.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires PostgreSQL and redis"]
+async fn rejected_fields_take_no_quota() {
+    let h = Harness::new();
+    let mut resolver = h.resolver.clone();
+    resolver.redis = Register::once_ref(|| {
+        Pool::builder().build_unchecked(redis::Client::open(redis_url()).unwrap())
+    });
+    resolver.field_limit = Register::once(|| {
+        let quota = |ip, token| FieldLimitConfig {
+            ip: rate(ip, Duration::from_secs(60)),
+            token: rate(token, Duration::from_secs(60)),
+        };
+        Arc::new(HashMap::from([
+            ("GraphqlMutation.createCart".to_string(), quota(2, 1)),
+            ("GraphqlQuery.products".to_string(), quota(1, 0)),
+        ]))
+    });
+    let schema = resolver.schema();
+    // unique per run, the windows outlive the tests
+    let pid = std::process::id();
+    let ip = IpAddr::from([10, 1 + (pid >> 16) as u8, (pid >> 8) as u8, pid as u8]);
+    let token = format!("test-{pid}-both");
+    let execute = |query: &str, token: Option<&str>| {
+        let req = async_graphql::Request::new(query).data(Client::new(Some(ip), token));
+        schema.execute(req)
+    };
+    let subjects = |res: async_graphql::Response| {
+        res.errors
+            .iter()
+            .map(|err| {
+                let err = serde_json::to_value(err).unwrap();
+                err["extensions"]["details"][0]["violations"][0]["subject"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+    let create = "mutation { createCart { cart { id } } }";
+
+    assert!(subjects(execute(create, Some(&token)).await).is_empty());
+    // the request over the quota of the token is not counted for the IP address
+    assert_eq!(subjects(execute(create, Some(&token)).await), ["token"]);
+    assert!(subjects(execute(create, None).await).is_empty());
+    assert_eq!(subjects(execute(create, None).await), [format!("ip:{ip}")]);
+
+    // the fields of a request are counted apart
+    let list = "{ products(first: 1) { edges { node { id } } } }";
+    assert!(subjects(execute(list, None).await).is_empty());
+    let res = execute(list, None).await;
+    assert_eq!(res.errors[0].path.len(), 1);
+    assert_eq!(subjects(res), [format!("ip:{ip}")]);
+}
@@ -0,0 +1,51 @@
+use async_graphql::{ErrorExtensions, Value};
+use poem::endpoint::make;
+use poem::{Endpoint, EndpointExt, Request};
+use shop_backend::graphql::request_id::RequestId;
+use shop_backend::infra::error::Status;
+use shop_backend::infra::telemetry::log::{self, REQUEST_ID};
+
+#[test]
+fn request_ids_are_taken_or_generated() {
+    assert_eq!(log::request_id(Some("req-1")), "req-1");
+    for given in [None, Some(""), Some("two words"), Some(&*"x".repeat(129))] {
+        let id = log::request_id(given);
+        assert_eq!(id.len(), 36, "{given:?}");
+        assert_ne!(id, log::request_id(given));
+    }
+}
+
+#[tokio::test]
+async fn requests_are_served_in_the_scope_of_their_id() {
+    let endpoint = make(|_| async { log::current().unwrap_or_default() }).with(RequestId);
+
+    let req = Request::builder().header(REQUEST_ID, "req-1").finish();
+    let res = endpoint.call(req).await.unwrap();
+    assert_eq!(res.headers()[REQUEST_ID], "req-1");
+    assert_eq!(res.into_body().into_string().await.unwrap(), "req-1");
+
+    let res = endpoint.call(Request::default()).await.unwrap();
+    let id = res.headers()[REQUEST_ID].to_str().unwrap().to_string();
+    assert_eq!(res.into_body().into_string().await.unwrap(), id);
+    assert_eq!(log::current(), None);
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let err = log::scope("req-1".to_string(), async { Status::unknown().extend() }).await;
+    let details = err.extensions.unwrap().get("details").unwrap().clone();
+    let Value::List(details) = details else {
+        panic!("{details}")
+    };
+    let info = async_graphql::to_value(serde_json::json!({
+        "@type": "RequestInfo",
+        "request_id": "req-1",
+        "serving_data": "",
+    }))
+    .unwrap();
+    assert_eq!(details, [info]);
+
+    let err = Status::unknown().extend();
+    let details = err.extensions.unwrap().get("details").unwrap().clone();
+    assert_eq!(details, Value::List(vec![]));
+}
@@ -0,0 +1,109 @@
+//! Tests of the metrics, the ignored one runs with PostgreSQL:
+//!
+//! ```shell
+//! cargo test --test metrics -- --ignored
+//! ```
+
+mod common;
+
+use common::Harness;
+use poem::{get, Endpoint, EndpointExt, Request};
+use shop_backend::graphql::sys;
+use shop_backend::infra::config::service::{LimitConfig, RateLimitConfig};
+use shop_backend::infra::limit::Limiter;
+use shop_backend::infra::metrics::{self, CARTS_CREATED, ERRORS, REQUEST_DURATION};
+use shop_backend::rpc::limit::LimitLayer;
+use shop_backend::rpc::metrics::MetricsLayer;
+use std::future::{ready, Ready};
+use std::time::Duration;
+use volo::context::{Role, RpcCx, RpcInfo};
+use volo::{Layer, Service};
+
+#[test]
+fn metrics_are_rendered_as_text() {
+    metrics::observe("text", "getProduct", Duration::from_millis(30));
+    metrics::observe("text", "say \"hi\"", Duration::from_secs(60));
+    metrics::error("text", "NOT_FOUND");
+    let text = metrics::render();
+    for line in [
+        "# TYPE shop_request_duration_seconds histogram",
+        r#"shop_request_duration_seconds_bucket{service="text",operation="getProduct",le="0.025"} 0"#,
+        r#"shop_request_duration_seconds_bucket{service="text",operation="getProduct",le="0.05"} 1"#,
+        r#"shop_request_duration_seconds_bucket{service="text",operation="getProduct",le="+Inf"} 1"#,
+        r#"shop_request_duration_seconds_count{service="text",operation="getProduct"} 1"#,
+        r#"shop_request_duration_seconds_bucket{service="text",operation="say \"hi\"",le="10"} 0"#,
+        r#"shop_request_duration_seconds_sum{service="text",operation="say \"hi\""} 60"#,
+        r#"shop_errors_total{service="text",code="NOT_FOUND"} 1"#,
+        "# TYPE shop_checkouts_paid_total counter",
+        "shop_checkouts_paid_total 0",
+    ] {
+        assert!(text.lines().any(|l| l == line), "{line} not in\n{text}");
+    }
+}
+
+struct Echo;
+
+impl<Cx: Send + 'static> Service<Cx, ()> for Echo {
+    type Response = ();
+    type Error = anyhow::Error;
+    type Future<'cx> = Ready<Result<(), anyhow::Error>>;
+
+    fn call<'cx, 's>(&'s self, _cx: &'cx mut Cx, _req: ()) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn rpc_requests_are_counted() {
+    let limit = LimitConfig {
+        concurrency: 0,
+        rate: RateLimitConfig {
+            num: 1,
+            per: Duration::from_secs(1),
+        },
+    };
+    let service = MetricsLayer::new("metrics-thrift")
+        .layer(LimitLayer::new(Limiter::new(0, &limit, false)).layer(Echo));
+    for _ in 0..2 {
+        let mut cx = RpcCx::<(), ()>::new(RpcInfo::with_role(Role::Server), ());
+        cx.rpc_info.method = Some("getProduct".into());
+        let _ = service.call(&mut cx, ()).await;
+    }
+    let labels = ["metrics-thrift", "getProduct"];
+    assert_eq!(REQUEST_DURATION.count(&labels), 2);
+    assert_eq!(ERRORS.get(&["metrics-thrift", "RESOURCE_EXHAUSTED"]), 1);
+}
+
+#[tokio::test]
+#[ignore = "requires PostgreSQL"]
+async fn graphql_requests_are_counted() {
+    let h = Harness::new();
+    let carts = CARTS_CREATED.get(&[]);
+    let data = h
+        .query("mutation Create { createCart { cart { id } } }")
+        .await;
+    let cart = data["createCart"]["cart"]["id"].as_str().unwrap();
+    // the cart is empty
+    let query =
+        format!(r#"mutation {{ createCheckout(cartId: "{cart}") {{ checkout {{ id }} }} }}"#);
+    h.execute(query).await;
+    h.execute("{ cart(id: ").await;
+    assert_eq!(REQUEST_DURATION.count(&["graphql", "Create"]), 1);
+    assert_eq!(REQUEST_DURATION.count(&["graphql", "anonymous"]), 1);
+    assert_eq!(REQUEST_DURATION.count(&["graphql", "invalid"]), 1);
+    assert_eq!(ERRORS.get(&["graphql", "FAILED_PRECONDITION"]), 1);
+    assert_eq!(ERRORS.get(&["graphql", "UNKNOWN"]), 1);
+    assert_eq!(CARTS_CREATED.get(&[]), carts + 1);
+
+    let endpoint = get(sys::metrics).data(h.resolver.clone());
+    let res = endpoint.call(Request::default()).await.unwrap();
+    let text = res.into_body().into_string().await.unwrap();
+    assert!(
+        text.contains(r#"shop_pool_connections{pool="pgsql",state="max"} 4"#),
+        "{text}"
+    );
+    assert!(text.contains(r#"shop_pool_connections{pool="redis",state="idle"}"#));
+}
@@ -0,0 +1,98 @@
+use hyper::{Client, Uri};
+use shop_backend::graphql::Resolver;
+use shop_backend::infra::error::Code;
+use shop_backend::infra::shutdown::{self, Shutdown};
+use shop_backend::rpc;
+use std::future::pending;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn deadline_bounds_the_drain() {
+    let shutdown = Shutdown::new(50);
+    shutdown.trigger();
+    shutdown.signal().await;
+    let start = Instant::now();
+    assert_eq!(shutdown.drain("nothing", pending::<()>()).await, None);
+    assert!(start.elapsed() >= Duration::from_millis(40));
+
+    let shutdown = Shutdown::new(0);
+    shutdown.trigger();
+    shutdown.signal().await;
+    assert_eq!(shutdown.remaining(), None);
+    assert_eq!(shutdown.drain("something", async { 1 }).await, Some(1));
+}
+
+// The pools are closed once per process, the services are shut down in one test.
+#[tokio::test(flavor = "multi_thread")]
+async fn services_drain_then_close() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let conf = std::env::temp_dir().join(format!("shop-shutdown-{}.toml", std::process::id()));
+    std::fs::write(
+        &conf,
+        format!("listen_addr = \"127.0.0.1:{port}\"\nredis = \"redis://127.0.0.1:1/\"\n"),
+    )
+    .unwrap();
+    let resolver = Resolver::new(&conf).unwrap();
+    let shutdown = Arc::new(Shutdown::new(5000));
+    let server = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { resolver.serve_until(&shutdown).await }
+    });
+    let uri: Uri = format!("http://127.0.0.1:{port}/healthz").parse().unwrap();
+    // idle connections are kept until the deadline, see Resolver::serve_until
+    let client = Client::builder()
+        .pool_max_idle_per_host(0)
+        .build_http::<hyper::Body>();
+    let mut served = None;
+    for _ in 0..50 {
+        if let Ok(res) = client.get(uri.clone()).await {
+            served = Some(res.status().as_u16());
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(served, Some(200));
+
+    // e.g. a checkout being written
+    let write = tokio::spawn(shutdown::spawn_blocking(|| {
+        std::thread::sleep(Duration::from_millis(300));
+        "written"
+    }));
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(shutdown::blocking(), 1);
+    shutdown.trigger();
+    server.await.unwrap().unwrap();
+    assert_eq!(shutdown::blocking(), 0);
+    assert_eq!(write.await.unwrap().unwrap(), "written");
+    assert!(client.get(uri).await.is_err());
+    let refused = shutdown::spawn_blocking(|| ()).await.unwrap_err();
+    assert_eq!(refused.code(), Code::Unavailable);
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    struct Stop(Arc<AtomicBool>);
+    impl Drop for Stop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+    let heartbeat = tokio::spawn({
+        let stop = Stop(stopped.clone());
+        async move {
+            let _stop = stop;
+            pending::<()>().await
+        }
+    });
+    let shutdown = Shutdown::new(100);
+    shutdown.trigger();
+    // volo is stopped by the signals only, the deadline is over before
+    let res = rpc::serve(pending::<Result<(), ()>>(), Some(heartbeat), &shutdown).await;
+    assert_eq!(res, Ok(()));
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(stopped.load(Ordering::SeqCst));
+}
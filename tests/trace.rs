@@ -0,0 +1,182 @@
+//! Tests of the tracing, the spans are exported to a fake collector. The request
+//! ids travel with the trace context.
+
+use futures::future::BoxFuture;
+use metainfo::{Forward, MetaInfo, METAINFO};
+use opentelemetry::trace::{TraceContextExt, TraceId};
+use opentelemetry::Context;
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::common::v1::any_value;
+use opentelemetry_proto::tonic::trace::v1::span::SpanKind;
+use prost::Message;
+use shop_backend::infra::config::service::TraceConfig;
+use shop_backend::infra::mqsrs::{traced, Query};
+use shop_backend::infra::telemetry::{self, log};
+use shop_backend::rpc::trace::TraceLayer;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use volo::context::{Role, RpcCx, RpcInfo};
+use volo::{Layer, Service};
+
+/// Receive the requests posted to `/v1/traces`.
+async fn collector() -> (String, mpsc::UnboundedReceiver<ExportTraceServiceRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let body = loop {
+                let mut chunk = [0; 4096];
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..end]);
+                assert!(head.starts_with("POST /v1/traces "), "{head}");
+                let len = head
+                    .lines()
+                    .find_map(|l| {
+                        l.to_lowercase()
+                            .strip_prefix("content-length: ")?
+                            .parse()
+                            .ok()
+                    })
+                    .unwrap_or(0);
+                if buf.len() >= end + 4 + len {
+                    break buf[end + 4..end + 4 + len].to_vec();
+                }
+            };
+            tx.send(ExportTraceServiceRequest::decode(&*body).unwrap())
+                .unwrap();
+            let res = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+            stream.write_all(res.as_bytes()).await.unwrap();
+        }
+    });
+    (endpoint, rx)
+}
+
+/// Record the metainfo sent by the clients, and the trace and the request seen by
+/// the servers.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Captured>>);
+
+#[derive(Default)]
+struct Captured {
+    traceparent: Option<String>,
+    request_id: Option<String>,
+    trace_id: Option<TraceId>,
+    current_request_id: Option<String>,
+}
+
+impl<Cx: Send + 'static> Service<Cx, ()> for Capture {
+    type Response = ();
+    type Error = anyhow::Error;
+    type Future<'cx> = BoxFuture<'cx, Result<(), anyhow::Error>>;
+
+    fn call<'cx, 's>(&'s self, _cx: &'cx mut Cx, _req: ()) -> Self::Future<'cx>
+    where
+        's: 'cx,
+    {
+        // like the handlers of volo, the request is served when polled
+        Box::pin(async move {
+            let transient = |key| {
+                METAINFO
+                    .try_with(|metainfo| metainfo.borrow().get_transient(key))
+                    .ok()
+                    .flatten()
+                    .map(|value| value.to_string())
+            };
+            *self.0.lock().unwrap() = Captured {
+                traceparent: transient("traceparent"),
+                request_id: transient(log::REQUEST_ID),
+                trace_id: Some(Context::current().span().span_context().trace_id()),
+                current_request_id: log::current(),
+            };
+            Ok(())
+        })
+    }
+}
+
+async fn call(service: &impl Service<RpcCx<(), ()>, ()>, role: Role, metainfo: MetaInfo) {
+    let mut cx = RpcCx::<(), ()>::new(RpcInfo::with_role(role), ());
+    cx.rpc_info.method = Some("getProduct".into());
+    let fut = async { service.call(&mut cx, ()).await.ok() };
+    METAINFO.scope(RefCell::new(metainfo), fut).await;
+}
+
+fn hex(id: &[u8]) -> String {
+    id.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traces_are_propagated_and_exported() {
+    let (endpoint, mut rx) = collector().await;
+    let config = TraceConfig {
+        endpoint,
+        ..Default::default()
+    };
+    telemetry::init("shop-trace", &config);
+
+    let capture = Capture::default();
+    let client = TraceLayer.layer(capture.clone());
+    let fut = call(&client, Role::Client, MetaInfo::new());
+    log::scope("req-1".to_string(), fut).await;
+    let captured = std::mem::take(&mut *capture.0.lock().unwrap());
+    let traceparent = captured.traceparent.unwrap();
+    assert_eq!(captured.request_id.as_deref(), Some("req-1"));
+
+    let mut metainfo = MetaInfo::new();
+    metainfo.set_transient("traceparent", traceparent.clone());
+    metainfo.set_transient(log::REQUEST_ID, "req-1");
+    let server = TraceLayer.layer(capture.clone());
+    call(&server, Role::Server, metainfo).await;
+    let captured = std::mem::take(&mut *capture.0.lock().unwrap());
+    assert_eq!(captured.current_request_id.as_deref(), Some("req-1"));
+    let trace_id = captured.trace_id.unwrap();
+    assert_eq!(
+        traceparent.split('-').nth(1),
+        Some(&*format!("{trace_id:032x}"))
+    );
+
+    traced("get_cart", |_: ()| async {}).execute(()).await;
+    tokio::task::spawn_blocking(telemetry::shutdown)
+        .await
+        .unwrap();
+
+    let mut spans = Vec::new();
+    while let Ok(req) = rx.try_recv() {
+        for resource in req.resource_spans {
+            let attribute = &resource.resource.unwrap().attributes[0];
+            assert_eq!(attribute.key, "service.name");
+            assert_eq!(
+                attribute.value.as_ref().unwrap().value,
+                Some(any_value::Value::StringValue("shop-trace".to_string()))
+            );
+            for scope in resource.scope_spans {
+                assert_eq!(scope.scope.unwrap().name, telemetry::TRACER);
+                spans.extend(scope.spans);
+            }
+        }
+    }
+    let find = |kind: SpanKind| {
+        spans
+            .iter()
+            .find(|span| span.kind == kind as i32)
+            .unwrap_or_else(|| panic!("no span of kind {kind:?} in {spans:?}"))
+    };
+    let (client, server) = (find(SpanKind::Client), find(SpanKind::Server));
+    assert_eq!(client.name, "getProduct");
+    assert_eq!(
+        Some(hex(&client.trace_id).as_str()),
+        traceparent.split('-').nth(1)
+    );
+    assert_eq!(server.trace_id, client.trace_id);
+    assert_eq!(server.parent_span_id, client.span_id);
+    assert_eq!(find(SpanKind::Internal).name, "get_cart");
+}
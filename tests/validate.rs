@@ -0,0 +1,64 @@
+use shop_backend::infra::error::Code;
+use shop_backend::infra::validate::*;
+
+#[test]
+fn emails() {
+    assert!(is_email("alice@example.com"));
+    assert!(is_email("a.b+shop@mail.example.co.uk"));
+    assert!(!is_email("alice"));
+    assert!(!is_email("alice@localhost"));
+    assert!(!is_email("alice@@example.com"));
+    assert!(!is_email("alice@-example.com"));
+}
+
+#[test]
+fn country_codes() {
+    assert!(is_country_code("US"));
+    assert!(is_country_code("GB"));
+    assert!(!is_country_code("us"));
+    assert!(!is_country_code("UK"));
+    assert!(!is_country_code("USA"));
+}
+
+#[test]
+fn postcodes() {
+    assert!(is_postcode("US", "94103"));
+    assert!(is_postcode("US", "94103-1234"));
+    assert!(!is_postcode("US", "9410"));
+    assert!(is_postcode("GB", "SW1A 1AA"));
+    assert!(is_postcode("CA", "K1A 0B1"));
+    assert!(!is_postcode("DE", "1011"));
+    // countries without a known pattern
+    assert!(is_postcode("AR", "C1420"));
+    assert!(!is_postcode("AR", "C1420@"));
+}
+
+#[test]
+fn phones() {
+    assert_eq!(
+        normalize_phone("+1 (555) 010-9999").as_deref(),
+        Some("+15550109999")
+    );
+    assert_eq!(
+        normalize_phone("0044 20 7946 0958").as_deref(),
+        Some("+442079460958")
+    );
+    assert_eq!(normalize_phone("555 010 9999"), None);
+    assert_eq!(normalize_phone("+0 555 010 9999"), None);
+    assert_eq!(normalize_phone("+1 555 CALL NOW"), None);
+    assert_eq!(normalize_phone("+1234567890123456"), None);
+}
+
+#[test]
+fn violations_are_collected() {
+    let mut v = Validator::new();
+    v.check("email", false, "not an email address");
+    v.check("countryCode", true, "not a country code");
+    v.check("phone", false, "not a phone number");
+    let status = v.finish().unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(status
+        .to_string()
+        .contains("Request fields 'email', 'phone' are invalid."));
+    assert!(Validator::new().finish().is_ok());
+}